@@ -0,0 +1,147 @@
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{anyhow, Result};
+use clap::{Args, Subcommand};
+use core::{find_workspace_root, Alias, Aliases, Anchor, ContextFinder, FileTarget, ScopeKind};
+
+#[derive(Debug, Subcommand)]
+pub enum AliasCommands {
+    /// Add an alias for a target, run it with `exec @<name>`
+    Add(AddArgs),
+    /// List the aliases of the current workspace and user
+    List,
+    /// Remove an alias
+    Remove(RemoveArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct AddArgs {
+    pub name: String,
+    /// Target of the alias: `path[:line[:column]]`
+    pub target: FileTarget,
+    /// Store the alias in the workspace `.cargo-runner/aliases.toml` instead of the user one
+    #[arg(long)]
+    pub workspace: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RemoveArgs {
+    pub name: String,
+    /// Remove the alias from the workspace aliases instead of the user ones
+    #[arg(long)]
+    pub workspace: bool,
+}
+
+pub fn alias(command: AliasCommands) -> Result<ExitCode> {
+    match command {
+        AliasCommands::Add(args) => add(args),
+        AliasCommands::List => list(),
+        AliasCommands::Remove(args) => remove(args),
+    }
+}
+
+/// Resolves `@name` to its target and anchor, looking in the workspace aliases
+/// first and the user aliases second.
+pub fn resolve(name: &str) -> Result<(FileTarget, Option<Anchor>)> {
+    let mut stores = Vec::new();
+    if let Some(root) = workspace_root()? {
+        stores.push((Aliases::workspace_path(&root), Some(root)));
+    }
+    stores.push((Aliases::user_path()?, None));
+
+    for (path, root) in stores {
+        let aliases = Aliases::load(&path)?;
+        if let Some(alias) = aliases.get(name) {
+            let mut target = alias.file_target()?;
+            if let Some(root) = root.filter(|_| target.path.is_relative()) {
+                target.path = root.join(&target.path);
+            }
+            return Ok((target, alias.anchor()?));
+        }
+    }
+
+    Err(anyhow!("Alias not found: {}", name))
+}
+
+fn add(args: AddArgs) -> Result<ExitCode> {
+    let path = args.target.path.canonicalize()?;
+    let anchor = match args.target.line {
+        Some(line) => {
+            let scopes = ContextFinder::new(&path).scopes()?;
+            scopes
+                .iter()
+                .filter(|scope| scope.kind != ScopeKind::File && scope.contains(line as usize))
+                .max_by_key(|scope| scope.start_line)
+                .map(|scope| scope.path())
+        }
+        None => None,
+    };
+
+    let (store, stored_path) = if args.workspace {
+        let root = find_workspace_root(&path)
+            .ok_or_else(|| anyhow!("No workspace found for: {}", path.display()))?;
+        let relative = path.strip_prefix(&root)?.to_path_buf();
+        (Aliases::workspace_path(&root), relative)
+    } else {
+        (Aliases::user_path()?, path)
+    };
+
+    let target = FileTarget {
+        path: stored_path,
+        ..args.target
+    };
+    let mut aliases = Aliases::load(&store)?;
+    aliases.insert(
+        &args.name,
+        Alias {
+            target: target.to_string(),
+            anchor,
+        },
+    );
+    aliases.save(&store)?;
+
+    println!("@{} -> {}", args.name, target);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn list() -> Result<ExitCode> {
+    if let Some(root) = workspace_root()? {
+        print_aliases(&Aliases::workspace_path(&root))?;
+    }
+    print_aliases(&Aliases::user_path()?)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_aliases(path: &Path) -> Result<()> {
+    for (name, alias) in Aliases::load(path)?.0 {
+        match &alias.anchor {
+            Some(anchor) => println!("@{} -> {} ({})", name, alias.target, anchor),
+            None => println!("@{} -> {}", name, alias.target),
+        }
+    }
+    Ok(())
+}
+
+fn remove(args: RemoveArgs) -> Result<ExitCode> {
+    let store = if args.workspace {
+        let root = workspace_root()?.ok_or_else(|| anyhow!("No workspace found"))?;
+        Aliases::workspace_path(&root)
+    } else {
+        Aliases::user_path()?
+    };
+
+    let mut aliases = Aliases::load(&store)?;
+    if aliases.remove(&args.name).is_none() {
+        return Err(anyhow!("Alias not found: {}", args.name));
+    }
+    aliases.save(&store)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn workspace_root() -> Result<Option<PathBuf>> {
+    Ok(find_workspace_root(&std::env::current_dir()?))
+}
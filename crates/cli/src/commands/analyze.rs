@@ -0,0 +1,376 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{ExitCode, Stdio},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use core::{
+    analyze_files, analyze_within, candidates, find_workspace_root, head_commit, label,
+    AnalysisSummary, BuildContext, CargoRunner, CommandBuilder, Context, DetectionBudget,
+    DocTestMode, FileAnalysis, FoundRunnable, Runnable, RunnableIndex, RunnableKind, TestList,
+    WorkspaceIndex,
+};
+use serde_json::json;
+
+use super::{
+    exec::{find_config, load_runner},
+    index::{load_index, save_index},
+};
+
+#[derive(Debug, Args)]
+pub struct AnalyzeArgs {
+    /// Files to analyze, in parallel when there are several, or the directory to
+    /// analyze with `--all`
+    pub paths: Vec<PathBuf>,
+    /// Analyze every Rust file under the directory (the current one by default),
+    /// honoring `.gitignore` and `.ignore`
+    #[arg(long)]
+    pub all: bool,
+    /// Glob of paths to skip with `--all`, relative to the directory
+    #[arg(long)]
+    pub exclude: Vec<String>,
+    /// Exit with an error when files contain `#[test]` but no test was detected
+    #[arg(long)]
+    pub strict: bool,
+    /// Print the analysis as JSON with paths relative to the directory, the format
+    /// `--index-url` downloads
+    #[arg(long)]
+    pub json: bool,
+    /// URL of prebuilt `--json` indexes to try before analyzing with `--all`, `{commit}`
+    /// is replaced by the checked out commit. Defaults to `CARGO_RUNNER_INDEX_URL`
+    #[arg(long)]
+    pub index_url: Option<String>,
+    /// List the tests of every target with detected tests through the test binary
+    /// (`-- --list`), cache the lists and report tests detection missed or got wrong
+    #[arg(long)]
+    pub reconcile: bool,
+    /// Only detect the items outside function, impl and trait bodies of files larger
+    /// than this many bytes
+    #[arg(long, value_name = "BYTES")]
+    pub max_file_size: Option<usize>,
+    /// Stop detecting in a file after this many milliseconds, keeping what was found
+    #[arg(long, value_name = "MS")]
+    pub time_budget: Option<u64>,
+    /// Print every command that could run each runnable as JSON, with why each would be
+    /// picked
+    #[arg(long, conflicts_with_all = ["json", "reconcile"])]
+    pub all_candidates: bool,
+}
+
+/// Prints the runnables cargo-runner finds in files or, with `--all`, a directory.
+pub fn analyze(args: AnalyzeArgs) -> Result<ExitCode> {
+    // Several files are shown relative to the current directory.
+    let path = match args.paths.as_slice() {
+        [] if !args.all => return Err(anyhow!("A file to analyze is required without --all")),
+        [_, _, ..] if args.all => {
+            return Err(anyhow!(
+                "--all analyzes a single directory, pass either files or --all"
+            ))
+        }
+        [path] if args.all && !path.is_dir() => {
+            return Err(anyhow!(
+                "{} is not a directory, --all analyzes one",
+                path.display()
+            ))
+        }
+        [path] => path.clone(),
+        _ => std::env::current_dir()?,
+    };
+
+    let budget = DetectionBudget {
+        max_bytes: args.max_file_size,
+        max_time: args.time_budget.map(Duration::from_millis),
+    };
+    let files = if args.paths.len() > 1 {
+        if let Some(dir) = args.paths.iter().find(|path| path.is_dir()) {
+            return Err(anyhow!(
+                "{} is a directory, --all analyzes a single one",
+                dir.display()
+            ));
+        }
+        analyze_files(&args.paths, budget)?
+    } else if path.is_dir() {
+        if !args.all {
+            return Err(anyhow!(
+                "{} is a directory, use --all to analyze it",
+                path.display()
+            ));
+        }
+        let index_url = args
+            .index_url
+            .clone()
+            .or_else(|| std::env::var("CARGO_RUNNER_INDEX_URL").ok());
+        match index_url.and_then(|url| fetch_index(&url, &path)) {
+            Some(files) => files,
+            None => match load_index(&path) {
+                Some(index) => indexed_within(&index, &path, &args.exclude)?,
+                None => analyze_within(&path, &args.exclude, budget)?,
+            },
+        }
+    } else {
+        vec![FileAnalysis::within(&path, budget)?]
+    };
+
+    let root = if args.paths.len() > 1 || path.is_dir() {
+        path.as_path()
+    } else {
+        path.parent().unwrap_or(&path)
+    };
+    let summary = AnalysisSummary::new(&files);
+    let mut status = if args.strict && summary.has_gaps() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    };
+
+    if args.json {
+        let index = RunnableIndex::from_files(root, files).with_commit(head_commit(root));
+        println!("{}", serde_json::to_string_pretty(&index)?);
+        return Ok(status);
+    }
+
+    if args.all_candidates {
+        let runner = load_runner(&path)?;
+        let mut locations = Vec::new();
+        for file in &files {
+            for found in &file.runnables {
+                locations.push(json!({
+                    "path": file.path.strip_prefix(root).unwrap_or(&file.path),
+                    "line": found.scope.start_line,
+                    "kind": <&str>::from(found.runnable.kind),
+                    "candidates": candidates(&runner, &found.runnable, &file.path)?,
+                }));
+            }
+        }
+        println!("{}", serde_json::to_string_pretty(&locations)?);
+        return Ok(status);
+    }
+
+    let runner = load_runner(&path).ok();
+    for file in &files {
+        let display = file.path.strip_prefix(root).unwrap_or(&file.path);
+        println!("{}", display.display());
+        for found in &file.runnables {
+            println!("  {}", describe(found, runner.as_ref()));
+        }
+        for declared in runner
+            .iter()
+            .flat_map(|runner| runner.user_runnables(&file.path))
+        {
+            println!("  {} (from config)", declared.name);
+        }
+    }
+
+    let relative = |path: &PathBuf| {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+    println!();
+    println!("{} files", summary.files);
+    for (kind, count) in &summary.counts {
+        println!("  {:<13} {}", kind, count);
+    }
+    if !summary.ambiguous.is_empty() {
+        println!("no cargo target for {} files:", summary.ambiguous.len());
+        for path in &summary.ambiguous {
+            println!("  {}", relative(path));
+        }
+    }
+    if !summary.partial.is_empty() {
+        println!(
+            "partial detection, over budget, in {} files:",
+            summary.partial.len()
+        );
+        for path in &summary.partial {
+            println!("  {}", relative(path));
+        }
+    }
+    if !summary.missed.is_empty() {
+        println!(
+            "#[test] without detected tests in {} files:",
+            summary.missed.len()
+        );
+        for path in &summary.missed {
+            println!("  {}", relative(path));
+        }
+    }
+
+    if args.reconcile && !reconcile(&path, &files)? && args.strict {
+        status = ExitCode::FAILURE;
+    }
+
+    Ok(status)
+}
+
+/// Lists the tests of each target that has detected tests, caches the lists under
+/// the target directory and prints the differences. Returns whether all targets agree.
+fn reconcile(path: &Path, files: &[FileAnalysis]) -> Result<bool> {
+    let runner = load_runner(path)?;
+    let config = find_config(&runner, Context::Test, None)?;
+    let context = BuildContext::gather(config, Some(path))?;
+    let workspace_root = context
+        .workspace_root
+        .clone()
+        .ok_or_else(|| anyhow!("No workspace found for: {}", path.display()))?;
+    let target_dir = context.cargo.target_dir(&workspace_root);
+
+    let mut targets: Vec<(Runnable, Vec<String>)> = Vec::new();
+    let tests = files
+        .iter()
+        .flat_map(|file| &file.runnables)
+        .map(|found| &found.runnable)
+        .filter(|runnable| runnable.kind == RunnableKind::Test);
+    for test in tests {
+        let Some(function) = test.filter.clone() else {
+            continue;
+        };
+        match targets
+            .iter_mut()
+            .find(|(target, _)| target.package == test.package && target.target == test.target)
+        {
+            Some((_, detected)) => detected.push(function),
+            None => targets.push((
+                Runnable {
+                    package: test.package.clone(),
+                    target: test.target.clone(),
+                    ..Runnable::new(Context::Test)
+                },
+                vec![function],
+            )),
+        }
+    }
+
+    let mut clean = true;
+    println!();
+    for (target, detected) in targets {
+        let output = CommandBuilder::new(config)
+            .runnable(&target)
+            .file_path(path)
+            .context(&context)
+            // The listing parses libtest's output.
+            .auto_framework(false)
+            .args(["--", "--list", "--format", "terse"])
+            .build()?
+            .stderr(Stdio::null())
+            .output()?;
+        let name = format!(
+            "{} {}",
+            target.package.as_deref().unwrap_or("?"),
+            target
+                .target
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default()
+        );
+        if !output.status.success() {
+            println!("{}: listing tests failed", name);
+            clean = false;
+            continue;
+        }
+
+        let list = TestList::parse(
+            target.package.clone(),
+            target.target.clone(),
+            &String::from_utf8_lossy(&output.stdout),
+        );
+        list.save(&target_dir)?;
+        let reconciliation = list.reconcile(&detected);
+        if reconciliation.is_clean() {
+            println!("{}: {} tests, all detected", name, list.tests.len());
+            continue;
+        }
+
+        clean = false;
+        println!("{}: {} tests", name, list.tests.len());
+        for test in &reconciliation.missed {
+            println!("  missed  {}", test);
+        }
+        for test in &reconciliation.stale {
+            println!("  stale   {}", test);
+        }
+    }
+
+    Ok(clean)
+}
+
+/// The files under `dir` from the index CI built for the checked out commit, with
+/// `analyze --all --json` at the workspace root. `None` falls back to analyzing locally.
+pub(super) fn fetch_index(url: &str, dir: &Path) -> Option<Vec<FileAnalysis>> {
+    let canonical = dir.canonicalize().ok()?;
+    let root = find_workspace_root(&canonical).unwrap_or_else(|| canonical.clone());
+    let commit = head_commit(&root)?;
+
+    match RunnableIndex::fetch(url, &commit) {
+        Ok(index) => Some(
+            index?
+                .files_in(&root)
+                .into_iter()
+                .filter_map(|file| {
+                    let relative = file.path.strip_prefix(&canonical).ok()?.to_path_buf();
+                    Some(FileAnalysis {
+                        path: dir.join(relative),
+                        ..file
+                    })
+                })
+                .collect(),
+        ),
+        Err(e) => {
+            eprintln!(
+                "warning: could not download the index, analyzing locally: {}",
+                e
+            );
+            None
+        }
+    }
+}
+
+/// The files under `dir` from the stored index of its workspace, analyzing only those
+/// changed since it was stored. The refreshed index replaces the stored one when `dir`
+/// is the indexed root, a subdirectory would leave it partial.
+pub(super) fn indexed_within(
+    index: &WorkspaceIndex,
+    dir: &Path,
+    exclude: &[String],
+) -> Result<Vec<FileAnalysis>> {
+    let canonical = dir.canonicalize()?;
+    let (refreshed, analyzed) = index.refresh(&canonical, exclude)?;
+    if analyzed > 0 && refreshed.root == index.root {
+        save_index(&refreshed)?;
+    }
+    Ok(refreshed
+        .analyses()
+        .into_iter()
+        .map(|file| FileAnalysis {
+            path: file
+                .path
+                .strip_prefix(&canonical)
+                .map(|relative| dir.join(relative))
+                .unwrap_or(file.path),
+            ..file
+        })
+        .collect())
+}
+
+/// The line, kind and label of a runnable, labeled with the templates of the default
+/// config of its context.
+fn describe(found: &FoundRunnable, runner: Option<&CargoRunner>) -> String {
+    let runnable = &found.runnable;
+    let templates = runner
+        .and_then(|runner| find_config(runner, runnable.context, None).ok())
+        .and_then(|config| config.labels.as_ref());
+    let name = label(runnable, templates);
+    let name = match runnable.doc_test {
+        Some(mode) if mode != DocTestMode::Run => format!("{} ({})", name, mode),
+        _ => name,
+    };
+    format!(
+        "{:<5} {:<13} {}",
+        found.scope.start_line,
+        <&str>::from(runnable.kind),
+        name
+    )
+}
@@ -0,0 +1,38 @@
+use std::process::ExitCode;
+
+use anyhow::Result;
+use clap::Subcommand;
+use core::{clear_caches, CacheStats, StateDir};
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommands {
+    /// Print the number of entries and the size of each cache
+    Stats,
+    /// Delete the cached scopes and workspace indexes
+    Clear,
+}
+
+pub fn cache(command: CacheCommands) -> Result<ExitCode> {
+    let state = StateDir::open(StateDir::default_path()?)?;
+    match command {
+        CacheCommands::Stats => {
+            for stats in CacheStats::of(&state)? {
+                println!(
+                    "{:<7} {} entries, {:.1} KiB",
+                    stats.store,
+                    stats.files,
+                    stats.bytes as f64 / 1024.0
+                );
+            }
+        }
+        CacheCommands::Clear => {
+            let cleared = clear_caches(&state)?;
+            println!(
+                "cleared {} entries from {}",
+                cleared,
+                state.root().display()
+            );
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,46 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use core::{CommandBuilder, Context, ContextFinder, RunnableKind};
+
+use super::exec::{exit_code, find_config, load_runner};
+
+#[derive(Debug, Args)]
+pub struct CheckArgs {
+    /// Any file of the package to check
+    pub path: PathBuf,
+    /// Build config name to use instead of the default
+    #[arg(long)]
+    pub config: Option<String>,
+    /// Extra arguments passed to cargo, those after `--` go to clippy
+    #[arg(last = true)]
+    pub args: Vec<String>,
+}
+
+/// Runs `cargo check` or `cargo clippy` for the package of a file.
+pub fn check(args: CheckArgs, kind: RunnableKind) -> Result<ExitCode> {
+    let runner = load_runner(&args.path)?;
+    let config = find_config(&runner, Context::Build, args.config.as_deref())?;
+
+    let runnable = ContextFinder::new(&args.path)
+        .package_runnables()
+        .into_iter()
+        .find(|runnable| runnable.kind == kind)
+        .ok_or_else(|| {
+            anyhow!(
+                "No {} runnable for: {}",
+                <&str>::from(kind),
+                args.path.display()
+            )
+        })?;
+
+    let status = CommandBuilder::new(config)
+        .runnable(&runnable)
+        .file_path(&args.path)
+        .args(args.args)
+        .build()?
+        .status()?;
+
+    Ok(exit_code(status.code()))
+}
@@ -0,0 +1,127 @@
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use core::{fix_config, lint_config, CargoRunner, ConfigLayer, Migration};
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommands {
+    /// Migrate a config written in the legacy layout to the current one
+    Migrate(MigrateArgs),
+    /// Print the config files applying to a path in precedence order, with the keys
+    /// each defines and the ones later files override
+    Tree(TreeArgs),
+    /// Report keys that are deprecated or do nothing in the config files applying to a
+    /// path, failing when there are any
+    Lint(TreeArgs),
+    /// Rewrite the deprecated keys `config lint` reports in place, keeping comments
+    Fix(TreeArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct MigrateArgs {
+    /// Path of the config to migrate
+    pub path: PathBuf,
+    /// Overwrite the config in place (a backup is created first) instead of printing it
+    #[arg(long)]
+    pub write: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct TreeArgs {
+    /// File or directory the config applies to, the current directory by default
+    pub path: Option<PathBuf>,
+}
+
+pub fn config(command: ConfigCommands) -> Result<ExitCode> {
+    match command {
+        ConfigCommands::Migrate(args) => migrate(args),
+        ConfigCommands::Tree(args) => tree(args),
+        ConfigCommands::Lint(args) => lint(args),
+        ConfigCommands::Fix(args) => fix(args),
+    }
+}
+
+/// The config files applying to `path` in precedence order, the global one first.
+fn layer_paths(path: Option<PathBuf>) -> Result<Vec<PathBuf>> {
+    let path = match path {
+        Some(path) => path,
+        None => std::env::current_dir()?,
+    };
+    Ok(std::iter::once(CargoRunner::get_default_config_path()?)
+        .chain(CargoRunner::local_config_layers(&path))
+        .filter(|path| path.is_file())
+        .collect())
+}
+
+fn lint(args: TreeArgs) -> Result<ExitCode> {
+    let mut found = false;
+    for path in layer_paths(args.path)? {
+        for lint in lint_config(&fs::read_to_string(&path)?)? {
+            let fix = if lint.fixable { " (fixable)" } else { "" };
+            println!("{}: {}{}", path.display(), lint, fix);
+            found = true;
+        }
+    }
+
+    Ok(if found {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn fix(args: TreeArgs) -> Result<ExitCode> {
+    for path in layer_paths(args.path)? {
+        let content = fs::read_to_string(&path)?;
+        let (fixed, lints) = fix_config(&content)?;
+        if fixed != content {
+            CargoRunner::create_backup(&path);
+            fs::write(&path, fixed)?;
+        }
+        for lint in lints {
+            let status = if lint.fixable { "fixed" } else { "left" };
+            println!("{}: {} ({})", path.display(), lint, status);
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn tree(args: TreeArgs) -> Result<ExitCode> {
+    let layers = ConfigLayer::read_all(&layer_paths(args.path)?)?;
+    for (index, layer) in layers.iter().enumerate() {
+        println!("{}. {}", index + 1, layer.path.display());
+        for key in &layer.keys {
+            match &key.overridden_by {
+                Some(by) => println!(
+                    "     {} = {}  (overridden by {})",
+                    key.key,
+                    key.value,
+                    by.display()
+                ),
+                None => println!("     {} = {}", key.key, key.value),
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn migrate(args: MigrateArgs) -> Result<ExitCode> {
+    let content = fs::read_to_string(&args.path)?;
+    let migration: Migration = content.parse()?;
+
+    for warning in &migration.warnings {
+        eprintln!("warning: {}", warning);
+    }
+
+    if args.write {
+        CargoRunner::create_backup(&args.path);
+        migration.config.save(Some(&args.path))?;
+    } else {
+        print!("{}", toml::to_string_pretty(&migration.config)?);
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,73 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use anyhow::{bail, Result};
+use clap::Args;
+use core::{
+    command_line, BuildContext, CommandBuilder, Context, CoverageReport, Execution, PathTranslator,
+};
+
+use super::exec::{
+    exec_config, exit_code, find_runnable, load_runner, report, resolve_target, timeout, ExecArgs,
+};
+
+#[derive(Debug, Args)]
+pub struct CoverageArgs {
+    #[command(flatten)]
+    pub exec: ExecArgs,
+    /// Write an lcov tracefile to this path
+    #[arg(long, value_name = "PATH", conflicts_with = "html")]
+    pub lcov: Option<PathBuf>,
+    /// Write an HTML report to this directory
+    #[arg(long, value_name = "DIR")]
+    pub html: Option<PathBuf>,
+}
+
+/// Runs the tests at the target under cargo-llvm-cov, with the filter `exec` would use.
+pub fn coverage(mut args: CoverageArgs) -> Result<ExitCode> {
+    let alias_anchor = resolve_target(&mut args.exec.location)?;
+    let exec = &args.exec;
+    let runner = load_runner(&exec.location.target.path)?;
+
+    let runnable = find_runnable(&runner, &exec.location, alias_anchor, false)?;
+    if runnable.context != Context::Test {
+        bail!(
+            "coverage runs tests, the target is a {} runnable",
+            runnable.context
+        );
+    }
+    let config = &exec_config(&runner, runnable.context, exec)?;
+    let report_kind = match (args.lcov, args.html) {
+        (Some(path), _) => CoverageReport::Lcov(path),
+        (_, Some(dir)) => CoverageReport::Html(dir),
+        _ => CoverageReport::Summary,
+    };
+
+    let context = BuildContext::gather(config, Some(&exec.location.target.path))?;
+    let mut builder = CommandBuilder::new(config)
+        .runnable(&runnable)
+        .file_path(&exec.location.target.path)
+        .context(&context)
+        .auto_framework(!exec.no_auto)
+        .tool(exec.tool)
+        .coverage(report_kind)
+        .args(exec.args.iter().cloned());
+    if let Some(cwd) = &exec.cwd {
+        builder = builder.working_dir(cwd);
+    }
+    let mut command = builder.build()?;
+
+    if exec.dry_run {
+        println!("{}", command_line(&command));
+        return Ok(ExitCode::SUCCESS);
+    }
+    let invocation_dir = context.current_dir.clone();
+    let working_dir = builder
+        .resolve_working_dir()?
+        .unwrap_or_else(|| invocation_dir.clone());
+
+    let result = Execution::new(&mut command)
+        .translator(PathTranslator::new(&working_dir, &invocation_dir))
+        .timeout(timeout(config, exec))
+        .run()?;
+    Ok(exit_code(report(result)))
+}
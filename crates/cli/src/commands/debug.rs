@@ -0,0 +1,53 @@
+use std::{io::ErrorKind, process::ExitCode};
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use core::{label, Debugger};
+
+use super::exec::{
+    build_launch, debug_plan, exec_config, exit_code, find_runnable, load_runner, resolve_target,
+    ExecArgs,
+};
+
+#[derive(Debug, Args)]
+pub struct DebugArgs {
+    #[command(flatten)]
+    pub exec: ExecArgs,
+    /// lldb or gdb to launch the binary under rust-lldb or rust-gdb, dap to print a DAP
+    /// launch configuration instead. Defaults to lldb on macOS and gdb elsewhere
+    #[arg(long, value_parser = parse_debugger)]
+    pub debugger: Option<Debugger>,
+}
+
+fn parse_debugger(value: &str) -> Result<Debugger, String> {
+    Debugger::try_from(value)
+}
+
+/// Builds the runnable without running it and launches its binary under a debugger.
+pub fn debug(mut args: DebugArgs) -> Result<ExitCode> {
+    let alias_anchor = resolve_target(&mut args.exec.location)?;
+    let exec = &args.exec;
+    let runner = load_runner(&exec.location.target.path)?;
+    let runnable = find_runnable(&runner, &exec.location, alias_anchor, false)?;
+    let config = &exec_config(&runner, runnable.context, exec)?;
+    let launch = build_launch(&mut debug_plan(config, &runnable, exec)?, exec)?;
+
+    let debugger = args.debugger.unwrap_or_default();
+    if debugger == Debugger::Dap {
+        let configuration = launch.dap(label(&runnable, None), exec.cwd.clone());
+        println!("{}", serde_json::to_string_pretty(&configuration)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let wrapper = debugger.wrapper();
+    let mut command = launch.wrapped(&wrapper);
+    if let Some(cwd) = &exec.cwd {
+        command.current_dir(cwd);
+    }
+    let status = command.status().map_err(|e| match e.kind() {
+        ErrorKind::NotFound => anyhow!("`{}` not found, is it installed?", wrapper[0]),
+        _ => e.into(),
+    })?;
+
+    Ok(exit_code(status.code()))
+}
@@ -0,0 +1,104 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+};
+
+use anyhow::Result;
+use clap::Args;
+use core::{
+    analyze_within, diff_commands, label, BuildContext, CargoRunner, CommandBuilder,
+    DetectionBudget, FileAnalysis, Runnable,
+};
+
+use super::exec::{find_config, load_runner};
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// File or directory to sample runnables from, the current directory by default
+    pub path: Option<PathBuf>,
+    /// Git revision to read the workspace config layers at, or a config file replacing them
+    #[arg(long)]
+    pub against: String,
+    /// Compare at most this many runnables
+    #[arg(long, default_value_t = 50)]
+    pub limit: usize,
+}
+
+/// Builds the commands of the runnables under the path with the current config and
+/// the reference one, and prints how they differ. Fails when any of them differ.
+pub fn diff(args: DiffArgs) -> Result<ExitCode> {
+    let path = match args.path {
+        Some(path) => std::path::absolute(path)?,
+        None => std::env::current_dir()?,
+    };
+    let files = if path.is_dir() {
+        analyze_within(&path, &[], DetectionBudget::default())?
+    } else {
+        vec![FileAnalysis::new(&path)?]
+    };
+    let root = if path.is_dir() {
+        path.as_path()
+    } else {
+        path.parent().unwrap_or(&path)
+    };
+
+    let (mut compared, mut differing) = (0, 0);
+    // The layers of config depend on the directory, load them once per file.
+    let mut runners = None;
+    let sample = files
+        .iter()
+        .flat_map(|file| file.runnables.iter().map(move |found| (file, found)))
+        .take(args.limit);
+    for (file, found) in sample {
+        compared += 1;
+        let (current, reference) = match &runners {
+            Some((path, current, reference)) if *path == &file.path => (current, reference),
+            _ => {
+                let current = load_runner(&file.path)?;
+                let reference = CargoRunner::reference(&args.against, &file.path)?;
+                let (_, current, reference) = runners.insert((&file.path, current, reference));
+                (&*current, &*reference)
+            }
+        };
+        let before = build(reference, &found.runnable, &file.path)?;
+        let after = build(current, &found.runnable, &file.path)?;
+
+        let changes = diff_commands(&before, &after);
+        if changes.is_empty() {
+            continue;
+        }
+        differing += 1;
+        let display = file.path.strip_prefix(root).unwrap_or(&file.path);
+        println!(
+            "{}:{} {}",
+            display.display(),
+            found.scope.start_line,
+            label(&found.runnable, None)
+        );
+        for change in changes {
+            for line in change.to_string().lines() {
+                println!("  {}", line);
+            }
+        }
+    }
+
+    println!(
+        "{} of {} commands differ from {}",
+        differing, compared, args.against
+    );
+    match differing {
+        0 => Ok(ExitCode::SUCCESS),
+        _ => Ok(ExitCode::FAILURE),
+    }
+}
+
+/// The command the default config for the context of `runnable` builds.
+fn build(runner: &CargoRunner, runnable: &Runnable, path: &Path) -> Result<Command> {
+    let config = find_config(runner, runnable.context, None)?;
+    let context = BuildContext::gather(config, Some(path))?;
+    Ok(CommandBuilder::new(config)
+        .runnable(runnable)
+        .file_path(path)
+        .context(&context)
+        .build()?)
+}
@@ -0,0 +1,902 @@
+use std::{
+    fs,
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, Stdio},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use core::{
+    candidates, command_line, find_tagged, shell_script, shell_script_all, Anchor, ArgStrategy,
+    BuildContext, CargoRunner, CommandBuilder, Config, Context, ContextFinder, CursorStrategy,
+    DebugLaunch, DebugPlan, EnvPolicy, Execution, ExecutionResult, FileTarget, MemcheckTool,
+    PathId, PathTranslator, Runnable, ScopeCache, Shard, StateDir, TestFramework, TestList,
+};
+
+/// The location to find a runnable at, shared by the commands that resolve one.
+#[derive(Debug, Args)]
+pub struct Location {
+    /// File to build the command for: `path[:line[:column]]`, a Windows path, a `file://` URI
+    /// or `@name` for an alias
+    pub target: FileTarget,
+    /// Context to use: run, build, test or bench, detected from the target when omitted
+    #[arg(long)]
+    pub context: Option<String>,
+    /// Read the file contents from stdin, e.g. an unsaved editor buffer
+    #[arg(long)]
+    pub stdin_source: bool,
+    /// Scope path the target line pointed at when it was analyzed e.g. `tests::it_works`,
+    /// used to re-map the line if the file changed since
+    #[arg(long)]
+    pub anchor: Option<String>,
+    /// How a line between runnables picks one: enclosing, nearest or nearest-above,
+    /// overrides `cursor_strategy` from config
+    #[arg(long, value_parser = parse_cursor_strategy)]
+    pub cursor_strategy: Option<CursorStrategy>,
+}
+
+/// Cargo build flags, overriding those of the config.
+#[derive(Debug, Default, Args)]
+pub struct BuildArgs {
+    /// Comma separated features to build with, overrides `features` from config
+    #[arg(long, value_delimiter = ',')]
+    pub features: Vec<String>,
+    /// Build with every feature of the package, overrides `all_features` from config
+    #[arg(long)]
+    pub all_features: bool,
+    /// Build without the default features of the package, overrides `no_default_features`
+    /// from config
+    #[arg(long)]
+    pub no_default_features: bool,
+    /// Target triple to build for e.g. `x86_64-unknown-linux-musl`, overrides `target` from
+    /// config and overrides
+    #[arg(long = "target", value_name = "TRIPLE")]
+    pub target_triple: Option<String>,
+}
+
+impl BuildArgs {
+    /// Whether no build flag was passed.
+    pub(super) fn is_empty(&self) -> bool {
+        self.features.is_empty()
+            && !self.all_features
+            && !self.no_default_features
+            && self.target_triple.is_none()
+    }
+
+    fn apply(&self, config: &Config) -> Config {
+        let mut config = config.clone();
+        if !self.features.is_empty() {
+            config.features = Some(self.features.clone());
+        }
+        if self.all_features {
+            config.all_features = Some(true);
+        }
+        if self.no_default_features {
+            config.no_default_features = Some(true);
+        }
+        if let Some(target) = &self.target_triple {
+            config.target = Some(target.clone());
+            for matched in config.overrides.iter_mut().flatten() {
+                matched.target = None;
+            }
+        }
+        config
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ExecArgs {
+    #[command(flatten)]
+    pub location: Location,
+    #[command(flatten)]
+    pub build: BuildArgs,
+    /// Config name to use instead of the default for the context
+    #[arg(long)]
+    pub config: Option<String>,
+    /// Directory to run the command in, overrides `working_dir` from config
+    #[arg(long)]
+    pub cwd: Option<PathBuf>,
+    /// Run the tests tagged with this tag found under the target file or directory
+    #[arg(long)]
+    pub tag: Option<String>,
+    /// Build the target without running it and print the binary and args to launch under a debugger
+    #[arg(long, conflicts_with = "emit_script")]
+    pub debug: bool,
+    /// Run the binary of the target under valgrind memcheck
+    #[arg(long, conflicts_with_all = ["debug", "heaptrack", "emit_script"])]
+    pub valgrind: bool,
+    /// Run the binary of the target under heaptrack
+    #[arg(long, conflicts_with_all = ["debug", "emit_script"])]
+    pub heaptrack: bool,
+    /// Print why the scopes around the line gave no runnable, and the defaults primed from the
+    /// package's dev-dependencies, before running
+    #[arg(long)]
+    pub explain: bool,
+    /// Build without incremental compilation or `RUSTC_WRAPPER` e.g. sccache, for timing runs
+    #[arg(long)]
+    pub cold: bool,
+    /// Run tests with libtest even when `framework_test = "auto"` finds cargo-nextest
+    #[arg(long)]
+    pub no_auto: bool,
+    /// Tool running the tests: libtest, nextest, auto or miri, overrides `framework_test`
+    /// from config
+    #[arg(long, value_parser = parse_test_framework)]
+    pub tool: Option<TestFramework>,
+    /// Run only shard `i` of `n` of the tests e.g. `--shard 2/4`, to split a run across jobs
+    #[arg(long, value_parser = parse_shard, conflicts_with_all = ["tag", "debug"])]
+    pub shard: Option<Shard>,
+    /// Kill the run after this many seconds, overrides `timeout_secs` from config
+    #[arg(long, value_name = "SECS")]
+    pub timeout: Option<u64>,
+    /// Print the commands instead of running them, the build step for `--debug`,
+    /// `--valgrind` and `--heaptrack` and the listing of the tests for a libtest `--shard`
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Write a shell script reproducing the run, with its env and working directory
+    #[arg(long, value_name = "PATH")]
+    pub emit_script: Option<PathBuf>,
+    /// Print every command that could run the target as JSON, with why each would be picked
+    #[arg(long, conflicts_with_all = ["tag", "debug", "shard", "emit_script"])]
+    pub all_candidates: bool,
+    /// Extra arguments passed to the command
+    #[arg(last = true)]
+    pub args: Vec<String>,
+}
+
+fn parse_shard(value: &str) -> Result<Shard, String> {
+    Shard::try_from(value)
+}
+
+fn parse_test_framework(value: &str) -> Result<TestFramework, String> {
+    TestFramework::try_from(value)
+}
+
+fn parse_cursor_strategy(value: &str) -> Result<CursorStrategy, String> {
+    CursorStrategy::try_from(value)
+}
+
+pub fn exec(mut args: ExecArgs) -> Result<ExitCode> {
+    let alias_anchor = resolve_target(&mut args.location)?;
+    let runner = load_runner(&args.location.target.path)?;
+
+    if let Some(tag) = &args.tag {
+        return exec_tagged(&runner, tag, &args);
+    }
+
+    let runnable = find_runnable(&runner, &args.location, alias_anchor, args.explain)?;
+    if args.all_candidates {
+        let candidates = candidates(&runner, &runnable, &args.location.target.path)?;
+        println!("{}", serde_json::to_string_pretty(&candidates)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.debug {
+        let config = &exec_config(&runner, runnable.context, &args)?;
+        return exec_debug(config, &runnable, &args);
+    }
+    let memcheck = match (args.valgrind, args.heaptrack) {
+        (true, _) => Some(MemcheckTool::Valgrind),
+        (_, true) => Some(MemcheckTool::Heaptrack),
+        _ => None,
+    };
+    if let Some(tool) = memcheck {
+        let config = &exec_config(&runner, runnable.context, &args)?;
+        return exec_memcheck(&runnable, config, tool, &args);
+    }
+    if let Some(shard) = args.shard {
+        return exec_sharded(&runner, &runnable, shard, &args);
+    }
+    let config = &exec_config(&runner, runnable.context, &args)?;
+    let timeout = timeout(config, &args);
+
+    let context = BuildContext::gather(config, Some(&args.location.target.path))?;
+    let mut builder = CommandBuilder::new(config)
+        .runnable(&runnable)
+        .file_path(&args.location.target.path)
+        .context(&context)
+        .cold(args.cold)
+        .auto_framework(!args.no_auto)
+        .tool(args.tool)
+        .args(args.args);
+    if let Some(cwd) = args.cwd {
+        builder = builder.working_dir(cwd);
+    }
+
+    warn_stale_filter(&runnable, &context)?;
+    if args.explain {
+        for hint in builder.hints()? {
+            eprintln!("heuristic: {}", hint);
+        }
+    }
+    let mut command = builder.build()?;
+    // nextest skips the doc tests of the module, they run afterwards with cargo.
+    let doc_tests = builder.doc_tests_fallback()?;
+
+    let invocation_dir = context.current_dir.clone();
+    let working_dir = builder
+        .resolve_working_dir()?
+        .unwrap_or_else(|| invocation_dir.clone());
+    if let Some(path) = &args.emit_script {
+        let script = match &doc_tests {
+            Some(doc_tests) => {
+                shell_script_all([&command, doc_tests], &working_dir, clean_env(config))
+            }
+            None => shell_script(&command, &working_dir, clean_env(config)),
+        };
+        write_script(path, &script)?;
+    }
+    if args.dry_run {
+        for command in std::iter::once(&command).chain(&doc_tests) {
+            println!("{}", command_line(command));
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+    let translator = PathTranslator::new(&working_dir, &invocation_dir);
+
+    let result = report(
+        Execution::new(&mut command)
+            .translator(translator.clone())
+            .timeout(timeout)
+            .run()?,
+    );
+    match (result, doc_tests) {
+        (Some(0), Some(mut doc_tests)) => Ok(exit_code(report(
+            Execution::new(&mut doc_tests)
+                .translator(translator)
+                .timeout(timeout)
+                .run()?,
+        ))),
+        (code, _) => Ok(exit_code(code)),
+    }
+}
+
+/// The `--timeout` of the run, else the `timeout_secs` of the config.
+pub(super) fn timeout(config: &Config, args: &ExecArgs) -> Option<Duration> {
+    args.timeout
+        .or(config.timeout_secs)
+        .map(Duration::from_secs)
+}
+
+/// The exit code of a run, telling when it was killed for timing out.
+pub(super) fn report(result: ExecutionResult) -> Option<i32> {
+    if result.timed_out {
+        eprintln!(
+            "timed out after {:.1}s, the run was killed",
+            result.duration.as_secs_f64()
+        );
+    }
+    result.code
+}
+
+/// Whether the env policy of `config` runs commands without the parent environment.
+fn clean_env(config: &Config) -> bool {
+    config.env_policy.clone().unwrap_or_default() != EnvPolicy::Inherit
+}
+
+/// Writes an executable script, telling where.
+fn write_script(path: &Path, script: &str) -> Result<()> {
+    fs::write(path, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    eprintln!("script written to {}", path.display());
+    Ok(())
+}
+
+/// Loads the user config with the `.cargo-runner.toml` fragments that apply to `path`.
+pub(super) fn load_runner(path: &Path) -> Result<CargoRunner> {
+    let mut runner = CargoRunner::init()?;
+    // Config layers are found from the workspace root, which a relative path can't reach.
+    runner.merge_local_configs(&std::path::absolute(path)?)?;
+    Ok(runner)
+}
+
+/// The on-disk cache of scanned scopes, `None` when the state directory can't be opened.
+pub(super) fn scope_cache() -> Option<ScopeCache> {
+    let state = StateDir::open(StateDir::default_path().ok()?).ok()?;
+    ScopeCache::open(&state).ok()
+}
+
+/// Replaces an `@name` target with the target of the alias and makes the path of the
+/// target its [PathId], so a file reached through a symlink finds the config, caches
+/// and overrides of the file it links to. Returns the anchor of the alias.
+pub(super) fn resolve_target(location: &mut Location) -> Result<Option<Anchor>> {
+    let alias = location
+        .target
+        .path
+        .to_str()
+        .and_then(|path| path.strip_prefix('@'));
+    let anchor = match alias {
+        Some(name) => {
+            let (target, anchor) = super::alias::resolve(name)?;
+            location.target = target;
+            anchor
+        }
+        None => None,
+    };
+
+    location.target.path = PathId::new(&location.target.path).into();
+    Ok(anchor)
+}
+
+/// Finds the runnable at `location` with the settings of `runner`, re-binding the line
+/// through the anchor if any. With `explain`, prints to stderr why the scopes around
+/// the line were passed over.
+pub(super) fn find_runnable(
+    runner: &CargoRunner,
+    location: &Location,
+    alias_anchor: Option<Anchor>,
+    explain: bool,
+) -> Result<Runnable> {
+    let context = location
+        .context
+        .as_deref()
+        .map_or(Context::Test, Context::from);
+    let config = find_config(runner, context, None).ok();
+    let strategy = location
+        .cursor_strategy
+        .or_else(|| config.as_ref()?.cursor_strategy);
+    let mut finder =
+        ContextFinder::new(&location.target.path).cursor_strategy(strategy.unwrap_or_default());
+    if let Some(order) = config
+        .as_ref()
+        .and_then(|config| config.scope_order.as_ref())
+    {
+        finder = finder.scope_order(order);
+    }
+    if let Some(cache) = scope_cache() {
+        finder = finder.scope_cache(cache);
+    }
+    if location.stdin_source {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source)?;
+        finder = finder.source(source);
+    }
+
+    let anchor = location
+        .anchor
+        .as_ref()
+        .map(|anchor| Anchor::new(anchor, location.target.line.unwrap_or(1) as usize))
+        .or(alias_anchor);
+    let line = match &anchor {
+        Some(anchor) => {
+            let line = finder
+                .resolve_anchor(anchor)?
+                .ok_or_else(|| anyhow!("No scope matching anchor: {}", anchor.path))?;
+            Some(line)
+        }
+        None => location.target.line,
+    };
+    let runnable = finder.find(line)?;
+    if explain {
+        for rejection in finder.explain(line.unwrap_or(1))? {
+            let scope = &rejection.scope;
+            eprintln!(
+                "considered {} `{}` (lines {}-{}): {}",
+                <&str>::from(scope.kind),
+                scope.path(),
+                scope.start_line,
+                scope.end_line,
+                rejection.reason
+            );
+        }
+    }
+
+    let context = match &location.context {
+        Some(context) => Context::from(context.as_str()),
+        None => runnable.context,
+    };
+
+    Ok(Runnable {
+        context,
+        ..runnable
+    })
+}
+
+/// Builds the runnable with `--no-run` and prints how to launch it as JSON.
+fn exec_debug(config: &Config, runnable: &Runnable, args: &ExecArgs) -> Result<ExitCode> {
+    let mut plan = debug_plan(config, runnable, args)?;
+    if args.dry_run {
+        println!("{}", command_line(&plan.build));
+        return Ok(ExitCode::SUCCESS);
+    }
+    let launch = build_launch(&mut plan, args)?;
+    println!("{}", serde_json::to_string_pretty(&launch)?);
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Builds the runnable with `--no-run` and runs its binary under a memory checker.
+fn exec_memcheck(
+    runnable: &Runnable,
+    config: &Config,
+    tool: MemcheckTool,
+    args: &ExecArgs,
+) -> Result<ExitCode> {
+    let memcheck = config.memcheck.clone().unwrap_or_default();
+
+    let mut plan = debug_plan(config, runnable, args)?;
+    if runnable.context == Context::Test {
+        plan.binary_args.extend(memcheck.test_args(tool));
+    }
+    let wrapper = memcheck.wrapper(tool);
+    if args.dry_run {
+        println!("{}", command_line(&plan.build));
+        eprintln!("then `{}` runs the built binary", wrapper.join(" "));
+        return Ok(ExitCode::SUCCESS);
+    }
+    let launch = build_launch(&mut plan, args)?;
+
+    let mut command = launch.wrapped(&wrapper);
+    if let Some(cwd) = &args.cwd {
+        command.current_dir(cwd);
+    }
+    let status = command.status().map_err(|e| match e.kind() {
+        ErrorKind::NotFound => anyhow!("`{}` not found, is it installed?", wrapper[0]),
+        _ => e.into(),
+    })?;
+
+    Ok(exit_code(status.code()))
+}
+
+/// The [DebugPlan] of the runnable: the command `exec` runs with `config`, built with
+/// `--no-run` so its binary can be launched directly.
+pub(super) fn debug_plan(
+    config: &Config,
+    runnable: &Runnable,
+    args: &ExecArgs,
+) -> Result<DebugPlan> {
+    let context = BuildContext::gather(config, Some(&args.location.target.path))?;
+    let mut builder = CommandBuilder::new(config)
+        .runnable(runnable)
+        .file_path(&args.location.target.path)
+        .context(&context)
+        .cold(args.cold)
+        .args(args.args.iter().cloned());
+    if let Some(cwd) = &args.cwd {
+        builder = builder.working_dir(cwd);
+    }
+    Ok(builder.debug_plan()?)
+}
+
+/// Runs the build step of `plan` and finds the binary it produced.
+pub(super) fn build_launch(plan: &mut DebugPlan, args: &ExecArgs) -> Result<DebugLaunch> {
+    let output = plan.build.stderr(Stdio::inherit()).output()?;
+    if !output.status.success() {
+        return Err(anyhow!("Build failed for: {}", args.location.target));
+    }
+
+    plan.launch(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| anyhow!("No executable was built for: {}", args.location.target))
+}
+
+/// Runs the tests tagged with `tag`, one command per cargo target.
+fn exec_tagged(runner: &CargoRunner, tag: &str, args: &ExecArgs) -> Result<ExitCode> {
+    let context = match &args.location.context {
+        Some(context) => Context::from(context.as_str()),
+        None => Context::Test,
+    };
+    let config = &exec_config(runner, context, args)?;
+    let globs = config
+        .tags
+        .as_ref()
+        .and_then(|tags| tags.get(tag))
+        .cloned()
+        .unwrap_or_default();
+
+    let groups = find_tagged(&args.location.target.path, tag, &globs)?;
+    if groups.is_empty() {
+        return Err(anyhow!("No tests tagged: {}", tag));
+    }
+
+    let mut commands = Vec::new();
+    for group in groups {
+        let runnable = Runnable {
+            context,
+            ..group.runnable
+        };
+        let mut builder = CommandBuilder::new(config)
+            .runnable(&runnable)
+            .file_path(&args.location.target.path)
+            .filters(group.filters)
+            .cold(args.cold)
+            .auto_framework(!args.no_auto)
+            .tool(args.tool)
+            .args(args.args.iter().cloned());
+        if let Some(cwd) = &args.cwd {
+            builder = builder.working_dir(cwd);
+        }
+        commands.push(builder.build()?);
+    }
+    if let Some(path) = &args.emit_script {
+        let script = shell_script_all(&commands, &std::env::current_dir()?, clean_env(config));
+        write_script(path, &script)?;
+    }
+    if args.dry_run {
+        for command in &commands {
+            println!("{}", command_line(command));
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut code = 0;
+    for mut command in commands {
+        let result = Execution::new(&mut command)
+            .timeout(timeout(config, args))
+            .run()?;
+        let result_code = report(result);
+        if result_code != Some(0) && code == 0 {
+            code = result_code.unwrap_or(1);
+        }
+    }
+
+    Ok(exit_code(Some(code)))
+}
+
+/// Warns when the filter of the runnable matches no test in the list cached by
+/// `analyze --reconcile`, e.g. a test detected in a `macro_rules!` body.
+fn warn_stale_filter(runnable: &Runnable, context: &BuildContext) -> Result<()> {
+    let (Some(filter), Some(workspace_root)) = (&runnable.filter, &context.workspace_root) else {
+        return Ok(());
+    };
+    let target_dir = context.cargo.target_dir(workspace_root);
+    let list = TestList::load(
+        &target_dir,
+        runnable.package.as_deref(),
+        runnable.target.as_ref(),
+    )?;
+    if list.is_some_and(|list| !list.matches(filter, runnable.exact)) {
+        eprintln!(
+            "warning: `{}` matches no test listed by the last `analyze --reconcile`",
+            filter
+        );
+    }
+    Ok(())
+}
+
+/// Runs one shard of the tests of the runnable: nextest partitions the run itself,
+/// for libtest the tests are listed and those of the shard passed as exact filters.
+fn exec_sharded(
+    runner: &CargoRunner,
+    runnable: &Runnable,
+    shard: Shard,
+    args: &ExecArgs,
+) -> Result<ExitCode> {
+    let config = &exec_config(runner, runnable.context, args)?;
+    let context = BuildContext::gather(config, Some(&args.location.target.path))?;
+    let builder = |runnable| {
+        let mut builder = CommandBuilder::new(config)
+            .runnable(runnable)
+            .file_path(&args.location.target.path)
+            .context(&context)
+            .cold(args.cold)
+            .auto_framework(!args.no_auto)
+            .tool(args.tool)
+            .args(args.args.iter().cloned());
+        if let Some(cwd) = &args.cwd {
+            builder = builder.working_dir(cwd);
+        }
+        builder
+    };
+
+    match builder(runnable).arg_strategy() {
+        Some(ArgStrategy::Libtest) => {}
+        Some(ArgStrategy::Nextest) => {
+            let mut command = builder(runnable).shard(shard).build()?;
+            if let Some(path) = &args.emit_script {
+                let working_dir = command
+                    .get_current_dir()
+                    .unwrap_or(&context.current_dir)
+                    .to_path_buf();
+                write_script(
+                    path,
+                    &shell_script(&command, &working_dir, clean_env(config)),
+                )?;
+            }
+            if args.dry_run {
+                println!("{}", command_line(&command));
+                return Ok(ExitCode::SUCCESS);
+            }
+            let result = Execution::new(&mut command)
+                .timeout(timeout(config, args))
+                .run()?;
+            return Ok(exit_code(report(result)));
+        }
+        _ => return Err(anyhow!("Config '{}' can't run shards", config.name)),
+    }
+
+    // Binary args go after the user's own `--` if they gave one.
+    let list = if args.args.iter().any(|arg| arg == "--") {
+        vec!["--list", "--format", "terse"]
+    } else {
+        vec!["--", "--list", "--format", "terse"]
+    };
+    let mut list = builder(runnable).args(list).build()?;
+    if args.dry_run && args.emit_script.is_some() {
+        return Err(anyhow!(
+            "--emit-script needs the tests of a libtest shard, which --dry-run doesn't list"
+        ));
+    }
+    if args.dry_run {
+        println!("{}", command_line(&list));
+        eprintln!(
+            "then the tests of shard {} it lists run, passed as exact filters",
+            shard
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+    let output = list.stderr(Stdio::inherit()).output()?;
+    if !output.status.success() {
+        return Ok(exit_code(output.status.code()));
+    }
+
+    let sharded = Runnable {
+        exact: true,
+        ..runnable.clone()
+    };
+    let listed = String::from_utf8_lossy(&output.stdout);
+    let commands = shard_commands(builder, &sharded, shard, &listed, SHARD_BATCH_BYTES)?;
+    if commands.is_empty() {
+        eprintln!("shard {} has no tests", shard);
+        return Ok(ExitCode::SUCCESS);
+    }
+    if let Some(path) = &args.emit_script {
+        let script = shell_script_all(&commands, &context.current_dir, clean_env(config));
+        write_script(path, &script)?;
+    }
+
+    let mut code = 0;
+    for mut command in commands {
+        let result = Execution::new(&mut command)
+            .timeout(timeout(config, args))
+            .run()?;
+        let result_code = report(result);
+        if result_code != Some(0) && code == 0 {
+            code = result_code.unwrap_or(1);
+        }
+    }
+    Ok(exit_code(Some(code)))
+}
+
+/// Bytes of test names one run of a libtest shard passes, well under the command line
+/// limit of every OS (32 KiB on Windows).
+const SHARD_BATCH_BYTES: usize = 16 * 1024;
+
+/// The commands running the tests of `shard` in `listed`, the libtest listing of
+/// `sharded`, as exact filters in batches of [Shard::batches].
+fn shard_commands<'a>(
+    builder: impl Fn(&'a Runnable) -> CommandBuilder<'a>,
+    sharded: &'a Runnable,
+    shard: Shard,
+    listed: &str,
+    max_bytes: usize,
+) -> Result<Vec<Command>> {
+    Shard::batches(shard.select(listed), max_bytes)
+        .into_iter()
+        .map(|names| Ok(builder(sharded).filters(names).build()?))
+        .collect()
+}
+
+pub(super) fn find_config<'a>(
+    runner: &'a CargoRunner,
+    context: Context,
+    name: Option<&str>,
+) -> Result<&'a Config> {
+    let name = name
+        .or_else(|| runner.get_default(context))
+        .ok_or_else(|| anyhow!("No default config for context: {}", context))?;
+    runner
+        .find(context, name)
+        .ok_or_else(|| anyhow!("Config '{}' not found for context: {}", name, context))
+}
+
+/// The config `exec` runs `context` with: the one given with `--config`, else the
+/// default, with the build flags of `args` on top.
+pub(super) fn exec_config(
+    runner: &CargoRunner,
+    context: Context,
+    args: &ExecArgs,
+) -> Result<Config> {
+    let config = find_config(runner, context, args.config.as_deref())?;
+    Ok(args.build.apply(config))
+}
+
+pub(super) fn exit_code(code: Option<i32>) -> ExitCode {
+    ExitCode::from(code.unwrap_or(1) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use core::{RunnableKind, TargetKind};
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        exec: ExecArgs,
+    }
+
+    #[test]
+    fn test_dry_run_every_branch() {
+        super::super::isolate_home();
+        let package = tempfile::tempdir().unwrap();
+        fs::write(
+            package.path().join("Cargo.toml"),
+            "[package]\nname = \"dry\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        fs::create_dir(package.path().join("src")).unwrap();
+        let lib = package.path().join("src/lib.rs");
+        fs::write(
+            &lib,
+            "#[cfg(test)]\nmod tests {\n    #[test]\n    #[cfg_attr(any(), test_tag = \"smoke\")]\n    fn it_works() {}\n}\n",
+        )
+        .unwrap();
+
+        // Spawning anything fails in a missing directory, so only printing succeeds.
+        let target = format!("{}:5", lib.display());
+        for flags in [
+            &[][..],
+            &["--tag", "smoke"],
+            &["--debug"],
+            &["--valgrind"],
+            &["--shard", "1/2"],
+        ] {
+            let mut argv = vec!["exec", &target, "--dry-run", "--cwd", "/nonexistent/dir"];
+            argv.extend(flags);
+            let code = exec(Cli::parse_from(argv).exec);
+            assert!(
+                matches!(code, Ok(code) if code == ExitCode::SUCCESS),
+                "{:?}: {:?}",
+                flags,
+                code
+            );
+        }
+
+        // So does writing the script of the commands.
+        let script = package.path().join("run.sh").display().to_string();
+        for (flags, expected) in [
+            (&[][..], "--exact"),
+            (&["--tag", "smoke"], "--exact"),
+            (&["--shard", "1/2", "--tool", "nextest"], "--partition"),
+        ] {
+            let mut argv = vec!["exec", &target, "--dry-run", "--cwd", "/nonexistent/dir"];
+            argv.extend(["--emit-script", &script]);
+            argv.extend(flags);
+            exec(Cli::parse_from(argv).exec).unwrap();
+            let written = fs::read_to_string(&script).unwrap();
+            assert!(written.contains("cd /nonexistent/dir\n"), "{}", written);
+            assert!(written.contains(expected), "{:?}: {}", flags, written);
+            fs::remove_file(&script).unwrap();
+        }
+        let mut argv = vec!["exec", &target, "--dry-run", "--emit-script", &script];
+        argv.extend(["--shard", "1/2", "--tool", "libtest"]);
+        assert!(exec(Cli::parse_from(argv).exec).is_err());
+        for flag in ["--debug", "--valgrind", "--heaptrack", "--all-candidates"] {
+            let argv = ["exec", &target, "--emit-script", &script, flag];
+            assert!(Cli::try_parse_from(argv).is_err(), "{}", flag);
+        }
+    }
+
+    #[test]
+    fn test_memcheck_plan_keeps_build_args() {
+        super::super::isolate_home();
+        let package = tempfile::tempdir().unwrap();
+        fs::write(
+            package.path().join("Cargo.toml"),
+            "[package]\nname = \"leaky\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        fs::create_dir(package.path().join("src")).unwrap();
+        let lib = package.path().join("src/lib.rs");
+        fs::write(
+            &lib,
+            "#[cfg(test)]\nmod tests {\n    #[test]\n    fn it_works() {}\n}\n",
+        )
+        .unwrap();
+
+        let target = format!("{}:4", lib.display());
+        let argv = [
+            "exec",
+            &target,
+            "--valgrind",
+            "--features",
+            "fast",
+            "--target",
+            "x86_64-unknown-linux-musl",
+        ];
+        let args = Cli::parse_from(argv).exec;
+        let runner = load_runner(&args.location.target.path).unwrap();
+        let runnable = find_runnable(&runner, &args.location, None, false).unwrap();
+        let config = exec_config(&runner, runnable.context, &args).unwrap();
+
+        let plan = debug_plan(&config, &runnable, &args).unwrap();
+        let build = command_line(&plan.build);
+        assert!(
+            build.starts_with("cargo test --no-run -p leaky --lib"),
+            "{}",
+            build
+        );
+        assert!(build.contains("--features fast"), "{}", build);
+        assert!(
+            build.contains("--target x86_64-unknown-linux-musl"),
+            "{}",
+            build
+        );
+        assert_eq!(plan.binary_args[0], "tests::it_works");
+    }
+
+    #[test]
+    fn test_shard_commands() {
+        let config = Config {
+            name: "default".to_string(),
+            sub_command: Some("test".to_string()),
+            ..Default::default()
+        };
+        let context = BuildContext::default();
+        let sharded = Runnable {
+            kind: RunnableKind::ModuleTests,
+            package: Some("demo".to_string()),
+            target: Some(TargetKind::Lib),
+            exact: true,
+            ..Runnable::new(Context::Test)
+        };
+        let builder = |runnable| {
+            CommandBuilder::new(&config)
+                .runnable(runnable)
+                .context(&context)
+        };
+        let listed = "math::tests::add: test\nmath::tests::sub: test\nparse::tests::int: test\n\
+                      bench_parse: benchmark\n\n3 tests, 1 benchmark\n";
+
+        let all = Shard { index: 1, count: 1 };
+        let lines = |max_bytes| -> Vec<String> {
+            shard_commands(builder, &sharded, all, listed, max_bytes)
+                .unwrap()
+                .iter()
+                .map(command_line)
+                .collect()
+        };
+        assert_eq!(
+            lines(SHARD_BATCH_BYTES),
+            vec![
+                "cargo test -p demo --lib -- math::tests::add math::tests::sub \
+                 parse::tests::int --exact"
+            ]
+        );
+        assert_eq!(
+            lines(40),
+            vec![
+                "cargo test -p demo --lib -- math::tests::add math::tests::sub --exact",
+                "cargo test -p demo --lib -- parse::tests::int --exact",
+            ]
+        );
+
+        // The shards of a listing split its tests between them.
+        let mut names: Vec<String> = (1..=3)
+            .flat_map(|index| {
+                let shard = Shard { index, count: 3 };
+                shard_commands(builder, &sharded, shard, listed, SHARD_BATCH_BYTES).unwrap()
+            })
+            .flat_map(|command| {
+                command
+                    .get_args()
+                    .skip_while(|arg| *arg != "--")
+                    .skip(1)
+                    .map(|arg| arg.to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|arg| arg != "--exact")
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["math::tests::add", "math::tests::sub", "parse::tests::int"]
+        );
+    }
+}
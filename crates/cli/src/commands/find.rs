@@ -0,0 +1,108 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use core::{label, search};
+use serde_json::json;
+
+use super::{
+    exec::{find_config, load_runner, BuildArgs},
+    run::{exec_found, workspace_files},
+};
+
+#[derive(Debug, Args)]
+pub struct FindArgs {
+    /// Characters of the qualified name to look for, in order e.g. `parse err`
+    pub query: String,
+    /// Workspace to search, the one of the current directory by default
+    #[arg(long)]
+    pub root: Option<PathBuf>,
+    /// Number of matches to print
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+    /// Run the best match instead of printing the matches
+    #[arg(long)]
+    pub run: bool,
+    /// Print the matches as JSON
+    #[arg(long, conflicts_with = "run")]
+    pub json: bool,
+    /// Config name to use instead of the default for the context, with `--run`
+    #[arg(long, requires = "run")]
+    pub config: Option<String>,
+    /// Print the command of the best match instead of running it
+    #[arg(long, requires = "run")]
+    pub dry_run: bool,
+    /// Extra arguments passed to the command, with `--run`
+    #[arg(last = true)]
+    pub args: Vec<String>,
+}
+
+/// Fuzzy searches the runnables of the workspace by qualified name.
+pub fn find(args: FindArgs) -> Result<ExitCode> {
+    let files = workspace_files(args.root.clone())?;
+    let hits = search(&files, &args.query);
+
+    if args.run {
+        let hit = hits
+            .first()
+            .ok_or_else(|| anyhow!("No runnable matches: {}", args.query))?;
+        eprintln!("running {}", hit.name);
+        return exec_found(
+            hit.file,
+            hit.found,
+            args.config,
+            args.dry_run,
+            false,
+            BuildArgs::default(),
+            args.args,
+        );
+    }
+
+    let runner = files.first().and_then(|file| load_runner(&file.path).ok());
+    let rows: Vec<_> = hits
+        .iter()
+        .take(args.limit)
+        .map(|hit| {
+            let runnable = &hit.found.runnable;
+            let templates = runner
+                .as_ref()
+                .and_then(|runner| find_config(runner, runnable.context, None).ok())
+                .and_then(|config| config.labels.as_ref());
+            (
+                hit,
+                label(runnable, templates),
+                format!("{}:{}", hit.file.path.display(), hit.found.scope.start_line),
+            )
+        })
+        .collect();
+
+    if args.json {
+        let matches: Vec<_> = rows
+            .iter()
+            .map(|(hit, label, location)| {
+                json!({
+                    "name": hit.name,
+                    "label": label,
+                    "kind": hit.found.runnable.kind,
+                    "location": location,
+                    "score": hit.score,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&matches)?);
+    } else {
+        for (hit, label, location) in &rows {
+            println!(
+                "{:<40} {:<13} {}",
+                label,
+                <&str>::from(hit.found.runnable.kind),
+                location
+            );
+        }
+    }
+
+    Ok(match hits.is_empty() {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    })
+}
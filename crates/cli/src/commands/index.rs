@@ -0,0 +1,62 @@
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::Result;
+use clap::Args;
+use core::{find_workspace_root, PathId, StateDir, WorkspaceIndex};
+
+#[derive(Debug, Args)]
+pub struct IndexArgs {
+    /// Directory to index, the workspace of the current directory by default
+    pub path: Option<PathBuf>,
+    /// Glob of paths to skip, relative to the directory
+    #[arg(long)]
+    pub exclude: Vec<String>,
+    /// Analyze every file again instead of only the changed ones
+    #[arg(long)]
+    pub rebuild: bool,
+}
+
+/// Analyzes every Rust file of the workspace and stores the runnables found, for
+/// `analyze --all` and the LSP server to reuse. Files that didn't change since the
+/// last run keep their analysis.
+pub fn index(args: IndexArgs) -> Result<ExitCode> {
+    let root = match args.path {
+        Some(path) => PathId::new(path).into(),
+        None => {
+            let current_dir: PathBuf = PathId::new(std::env::current_dir()?).into();
+            find_workspace_root(&current_dir).unwrap_or(current_dir)
+        }
+    };
+
+    let state = StateDir::open(StateDir::default_path()?)?;
+    let previous = match args.rebuild {
+        true => None,
+        false => WorkspaceIndex::load(&state, &root)?,
+    };
+    let (index, analyzed) = previous.unwrap_or_default().refresh(&root, &args.exclude)?;
+    let path = index.save(&state)?;
+    println!(
+        "indexed {} files, {} analyzed, into {}",
+        index.files.len(),
+        analyzed,
+        path.display()
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Stores `index` in place of the previous index of its workspace.
+pub(super) fn save_index(index: &WorkspaceIndex) -> Result<PathBuf> {
+    let state = StateDir::open(StateDir::default_path()?)?;
+    Ok(index.save(&state)?)
+}
+
+/// The stored index of the workspace `path` belongs to, `None` if it was never indexed.
+pub(super) fn load_index(path: &Path) -> Option<WorkspaceIndex> {
+    let root = find_workspace_root(&PathId::new(path))?;
+    let state = StateDir::open(StateDir::default_path().ok()?).ok()?;
+    WorkspaceIndex::load(&state, &root).ok().flatten()
+}
@@ -0,0 +1,66 @@
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use core::{find_workspace_root, CargoRunner};
+
+/// Team configs shipped with the binary, by name.
+const PRESETS: &[(&str, &str)] = &[
+    (
+        "embedded",
+        include_str!("../config/templates/embedded.toml"),
+    ),
+    (
+        "wasm-frontend",
+        include_str!("../config/templates/wasm-frontend.toml"),
+    ),
+    (
+        "sqlx-service",
+        include_str!("../config/templates/sqlx-service.toml"),
+    ),
+];
+
+#[derive(Debug, Args)]
+pub struct InitArgs {
+    /// Scaffold the committed team config `.cargo-runner/` at the workspace root instead
+    #[arg(long)]
+    pub team: bool,
+    /// Scaffold the team config from a preset: embedded, wasm-frontend or sqlx-service
+    #[arg(long, value_parser = parse_preset)]
+    pub preset: Option<&'static str>,
+}
+
+fn parse_preset(value: &str) -> Result<&'static str, String> {
+    PRESETS
+        .iter()
+        .find(|(name, _)| *name == value)
+        .map(|(_, template)| *template)
+        .ok_or_else(|| {
+            let names: Vec<_> = PRESETS.iter().map(|(name, _)| *name).collect();
+            format!(
+                "unknown preset `{}`, expected one of: {}",
+                value,
+                names.join(", ")
+            )
+        })
+}
+
+pub fn init(args: InitArgs) -> Result<ExitCode> {
+    if args.team || args.preset.is_some() {
+        let current_dir = std::env::current_dir()?;
+        let root = find_workspace_root(&current_dir)
+            .ok_or_else(|| anyhow!("No workspace found for: {}", current_dir.display()))?;
+        let created = match args.preset {
+            Some(template) => CargoRunner::init_team_from(&root, template)?,
+            None => CargoRunner::init_team(&root)?,
+        };
+        for path in created {
+            println!("created {}", path.display());
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let config = CargoRunner::init()?;
+    println!("{:#?}", config);
+    Ok(ExitCode::SUCCESS)
+}
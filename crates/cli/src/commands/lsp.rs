@@ -0,0 +1,409 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    str::FromStr,
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Result};
+use core::{
+    find_workspace_root, label, read_source, CargoRunner, Context, ContextFinder, FileTarget,
+    FoundRunnable, ParsedSource, PathId, TextEdit, WorkspaceIndex,
+};
+use serde_json::{json, Value};
+
+use super::{
+    exec::{find_config, load_runner, scope_cache},
+    index::load_index,
+};
+
+/// An open document, scanned as edits come in, and the lenses computed for its
+/// current text.
+struct Document {
+    source: ParsedSource,
+    lenses: Option<Value>,
+}
+
+/// The config of a directory with the modification times of the files it was loaded
+/// from, loaded again once one of them changes.
+struct LoadedRunner {
+    layers: Vec<(PathBuf, Option<SystemTime>)>,
+    runner: CargoRunner,
+}
+
+/// Serves code lenses for the runnables of open documents over the Language Server
+/// Protocol on stdin/stdout, keeping documents and their lenses in memory between
+/// requests instead of spawning a process per file.
+pub fn lsp() -> Result<ExitCode> {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    serve(&mut stdin.lock(), &mut stdout.lock())
+}
+
+fn serve(input: &mut impl BufRead, output: &mut impl Write) -> Result<ExitCode> {
+    let mut documents: HashMap<String, Document> = HashMap::new();
+    let mut runners: HashMap<String, LoadedRunner> = HashMap::new();
+    // The stored index of each workspace, `None` for those never indexed.
+    let mut indexes: HashMap<PathBuf, Option<WorkspaceIndex>> = HashMap::new();
+    let mut shutdown = false;
+
+    while let Some(body) = read_message(input)? {
+        let message: Value = match serde_json::from_slice(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                // The id of a message that doesn't parse is unknown, JSON-RPC answers null.
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": -32700, "message": format!("parse error: {}", e) },
+                });
+                write_message(output, &response)?;
+                continue;
+            }
+        };
+        let id = message.get("id").cloned();
+        let method = message
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    // Incremental document sync.
+                    "textDocumentSync": 2,
+                    "codeLensProvider": { "resolveProvider": false },
+                },
+                "serverInfo": { "name": "cargo-runner", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                shutdown = true;
+                Some(Value::Null)
+            }
+            "exit" => break,
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                if let (Some(uri), Some(text)) =
+                    (document["uri"].as_str(), document["text"].as_str())
+                {
+                    documents.insert(
+                        uri.to_string(),
+                        Document {
+                            source: ParsedSource::new(text),
+                            lenses: None,
+                        },
+                    );
+                }
+                None
+            }
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let edits =
+                    serde_json::from_value::<Vec<TextEdit>>(params["contentChanges"].clone());
+                if let (Some(document), Ok(edits)) = (documents.get_mut(uri), edits) {
+                    document.source.edit(&edits);
+                    document.lenses = None;
+                }
+                None
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                documents.remove(uri);
+                None
+            }
+            "textDocument/codeLens" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                Some(
+                    match code_lenses(uri, &mut documents, &mut runners, &mut indexes) {
+                        Ok(lenses) => lenses,
+                        Err(e) => {
+                            eprintln!("cargo-runner lsp: {}: {}", uri, e);
+                            json!([])
+                        }
+                    },
+                )
+            }
+            _ => None,
+        };
+
+        // Notifications get no response, unknown requests get `MethodNotFound`.
+        if let Some(id) = id {
+            let response = match result {
+                Some(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                None => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": -32601, "message": format!("unknown method: {}", method) },
+                }),
+            };
+            write_message(output, &response)?;
+        }
+    }
+
+    Ok(match shutdown {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    })
+}
+
+/// The lenses of `uri`, computed once per version of its text and of the config.
+fn code_lenses(
+    uri: &str,
+    documents: &mut HashMap<String, Document>,
+    runners: &mut HashMap<String, LoadedRunner>,
+    indexes: &mut HashMap<PathBuf, Option<WorkspaceIndex>>,
+) -> Result<Value> {
+    let path: PathBuf = PathId::new(FileTarget::from_str(uri)?.path).into();
+    let dir = path.parent().unwrap_or(&path).to_string_lossy().to_string();
+    let layers = config_layers(&path)?;
+    if runners
+        .get(&dir)
+        .is_none_or(|loaded| loaded.layers != layers)
+    {
+        let runner = load_runner(&path)?;
+        runners.insert(dir.clone(), LoadedRunner { layers, runner });
+        // Any config layer may be shared with other directories.
+        for document in documents.values_mut() {
+            document.lenses = None;
+        }
+    }
+    let runner = &runners[&dir].runner;
+
+    let (source, scanned) = match documents.get(uri) {
+        Some(document) => match &document.lenses {
+            Some(lenses) => return Ok(lenses.clone()),
+            None => (
+                document.source.text().to_string(),
+                Some(document.source.scopes().to_vec()),
+            ),
+        },
+        None => (read_source(&path)?, None),
+    };
+
+    let disabled = find_config(runner, Context::Test, None)
+        .ok()
+        .and_then(|config| config.detectors.as_ref())
+        .map(|detectors| detectors.disabled.clone())
+        .unwrap_or_default();
+
+    // The index answers for files unchanged since `cargo runner index` ran.
+    let index = find_workspace_root(&path).and_then(|root| {
+        indexes
+            .entry(root)
+            .or_insert_with(|| load_index(&path))
+            .as_ref()
+    });
+    let runnables = match index.and_then(|index| index.fresh(&path, &source)) {
+        Some(analysis) => analysis
+            .runnables
+            .iter()
+            .filter(|found| !disabled.contains(&found.runnable.kind))
+            .cloned()
+            .collect(),
+        None => {
+            let mut finder = ContextFinder::new(&path).source(source).disabled(&disabled);
+            if let Some(scopes) = scanned {
+                finder = finder.scanned(scopes);
+            } else if let Some(cache) = scope_cache() {
+                finder = finder.scope_cache(cache);
+            }
+            finder.runnables()?
+        }
+    };
+
+    let mut lenses: Vec<Value> = runnables
+        .iter()
+        .map(|found| lens(uri, found, runner))
+        .collect();
+    for declared in runner.user_runnables(&path) {
+        lenses.push(json!({
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 0, "character": 0 },
+            },
+            "command": {
+                "title": declared.name,
+                "command": "cargo-runner.run",
+                "arguments": [{ "uri": uri, "name": declared.name }],
+            },
+        }));
+    }
+    let lenses = Value::Array(lenses);
+    if let Some(document) = documents.get_mut(uri) {
+        document.lenses = Some(lenses.clone());
+    }
+    Ok(lenses)
+}
+
+fn lens(uri: &str, found: &FoundRunnable, runner: &CargoRunner) -> Value {
+    let runnable = &found.runnable;
+    let templates = find_config(runner, runnable.context, None)
+        .ok()
+        .and_then(|config| config.labels.as_ref());
+    let line = found.scope.lens_line().saturating_sub(1);
+    json!({
+        "range": {
+            "start": { "line": line, "character": 0 },
+            "end": { "line": line, "character": 0 },
+        },
+        "command": {
+            "title": label(runnable, templates),
+            "command": "cargo-runner.exec",
+            "arguments": [{
+                "uri": uri,
+                "line": found.scope.start_line,
+                "runnable": runnable,
+            }],
+        },
+    })
+}
+
+/// The config files [load_runner] reads for `path` with their modification times.
+fn config_layers(path: &Path) -> Result<Vec<(PathBuf, Option<SystemTime>)>> {
+    let path = std::path::absolute(path)?;
+    Ok(std::iter::once(CargoRunner::get_default_config_path()?)
+        .chain(CargoRunner::local_config_layers(&path))
+        .map(|layer| {
+            let modified = fs::metadata(&layer).and_then(|meta| meta.modified()).ok();
+            (layer, modified)
+        })
+        .collect())
+}
+
+/// Reads the body of one `Content-Length` framed message, `None` at the end of the input.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| anyhow!("message without a Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn framed(messages: &[&str]) -> Vec<u8> {
+        messages
+            .iter()
+            .flat_map(|body| format!("Content-Length: {}\r\n\r\n{}", body.len(), body).into_bytes())
+            .collect()
+    }
+
+    fn responses(output: Vec<u8>) -> Vec<Value> {
+        let mut output = Cursor::new(output);
+        std::iter::from_fn(|| read_message(&mut output).unwrap())
+            .map(|body| serde_json::from_slice(&body).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_message_framing() {
+        let message = json!({ "jsonrpc": "2.0", "id": 1, "result": { "text": "é\r\n" } });
+        let mut output = Vec::new();
+        write_message(&mut output, &message).unwrap();
+        write_message(&mut output, &Value::Null).unwrap();
+        assert_eq!(responses(output), vec![message, Value::Null]);
+    }
+
+    #[test]
+    fn test_code_lens_of_open_document() {
+        super::super::isolate_home();
+        let package = tempfile::tempdir().unwrap();
+        fs::write(
+            package.path().join("Cargo.toml"),
+            "[package]\nname = \"lens\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        // Only the editor has the file, it was never saved.
+        let uri = format!("file://{}/src/lib.rs", package.path().display());
+        let open = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": {
+                "uri": uri,
+                "text": "#[cfg(test)]\nmod tests {\n    #[test]\n    fn it_works() {}\n}\n",
+            } },
+        });
+        let lens = |id: u32| {
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "textDocument/codeLens",
+                "params": { "textDocument": { "uri": uri } },
+            })
+            .to_string()
+        };
+        let titles = |response: &Value| -> Vec<String> {
+            response["result"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|lens| lens["command"]["title"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let mut output = Vec::new();
+        let input = framed(&["{\"jsonrpc\": ", &open.to_string(), &lens(1)]);
+        serve(&mut Cursor::new(input), &mut output).unwrap();
+        let first = responses(output);
+        assert_eq!(first[0]["error"]["code"], -32700);
+        assert_eq!(first[0]["id"], Value::Null);
+        assert_eq!(first[1]["id"], 1);
+        assert!(titles(&first[1])
+            .iter()
+            .any(|title| title.contains("it_works")));
+
+        // A config written while serving relabels the open document.
+        let mut documents = HashMap::new();
+        let mut runners = HashMap::new();
+        let mut indexes = HashMap::new();
+        let text = open["params"]["textDocument"]["text"].as_str().unwrap();
+        documents.insert(
+            uri.clone(),
+            Document {
+                source: ParsedSource::new(text),
+                lenses: None,
+            },
+        );
+        let mut lenses = || code_lenses(&uri, &mut documents, &mut runners, &mut indexes);
+        let before = lenses().unwrap();
+        fs::write(
+            package.path().join(".cargo-runner.toml"),
+            "[[test.config]]\nname = \"default\"\nlabels = { test = \"Check {path}\" }\n",
+        )
+        .unwrap();
+        let after = lenses().unwrap();
+        assert_ne!(before, after);
+        assert!(after.to_string().contains("Check tests::it_works"));
+    }
+}
@@ -0,0 +1,171 @@
+use std::process::{Command, ExitCode};
+
+use anyhow::{anyhow, Result};
+use core::{command_line, CommandBuilder, Config, Execution, Runnable};
+
+use super::exec::{
+    exec_config, exit_code, find_runnable, load_runner, report, resolve_target, timeout, ExecArgs,
+};
+
+/// Runs the runnable at the target once per cell of the config's matrix and
+/// prints a table of the results.
+pub fn matrix(mut args: ExecArgs) -> Result<ExitCode> {
+    let unsupported = [
+        (args.tag.is_some(), "--tag"),
+        (args.shard.is_some(), "--shard"),
+        (args.debug, "--debug"),
+        (args.valgrind, "--valgrind"),
+        (args.heaptrack, "--heaptrack"),
+        (args.emit_script.is_some(), "--emit-script"),
+        (args.all_candidates, "--all-candidates"),
+    ];
+    if let Some((_, flag)) = unsupported.iter().find(|(set, _)| *set) {
+        return Err(anyhow!("{} can't be used with matrix", flag));
+    }
+
+    let alias_anchor = resolve_target(&mut args.location)?;
+    let runner = load_runner(&args.location.target.path)?;
+
+    let runnable = find_runnable(&runner, &args.location, alias_anchor, args.explain)?;
+    let config = &exec_config(&runner, runnable.context, &args)?;
+    let cells = cell_commands(config, &runnable, &args)?;
+
+    if args.dry_run {
+        for (label, _, command) in &cells {
+            println!("{}: {}", label, command_line(command));
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut results = Vec::new();
+    for (label, cell_config, mut command) in cells {
+        eprintln!("==> {}", label);
+        let result = Execution::new(&mut command)
+            .timeout(timeout(&cell_config, &args))
+            .run()?;
+        let duration = result.duration;
+        results.push((label, report(result) == Some(0), duration));
+    }
+
+    let width = results
+        .iter()
+        .map(|(label, _, _)| label.len())
+        .max()
+        .unwrap_or_default();
+    println!();
+    for (label, success, elapsed) in &results {
+        let result = if *success { "ok" } else { "FAILED" };
+        println!(
+            "{:<width$}  {:<6}  {:.1}s",
+            label,
+            result,
+            elapsed.as_secs_f64()
+        );
+    }
+
+    let failed = results.iter().any(|(_, success, _)| !success);
+    Ok(exit_code(Some(failed as i32)))
+}
+
+/// The label, config and command of each cell of the matrix of `config`.
+fn cell_commands(
+    config: &Config,
+    runnable: &Runnable,
+    args: &ExecArgs,
+) -> Result<Vec<(String, Config, Command)>> {
+    let matrix = config
+        .matrix
+        .as_ref()
+        .ok_or_else(|| anyhow!("Config '{}' has no matrix", config.name))?;
+
+    matrix
+        .cells()
+        .into_iter()
+        .map(|cell| {
+            let cell_config = cell.config(config);
+            let mut builder = CommandBuilder::new(&cell_config)
+                .runnable(runnable)
+                .file_path(&args.location.target.path)
+                .args(cell.cargo_args())
+                .cold(args.cold)
+                .auto_framework(!args.no_auto)
+                .tool(args.tool)
+                .args(args.args.iter().cloned());
+            if let Some(toolchain) = &cell.toolchain {
+                builder = builder.toolchain(toolchain);
+            }
+            if let Some(cwd) = &args.cwd {
+                builder = builder.working_dir(cwd);
+            }
+            let command = builder.build()?;
+            Ok((cell.label(), cell_config, command))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use core::{Context, TargetKind};
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        exec: ExecArgs,
+    }
+
+    #[test]
+    fn test_matrix_cells_replace_features() {
+        let config: Config = toml::from_str(
+            r#"
+            name = "default"
+            sub_command = "test"
+            features = ["fast"]
+            matrix = { features = ["all", "serde"] }
+            "#,
+        )
+        .unwrap();
+        let runnable = Runnable {
+            package: Some("demo".to_string()),
+            target: Some(TargetKind::Lib),
+            ..Runnable::new(Context::Test)
+        };
+        let args = Cli::parse_from(["matrix", "src/lib.rs", "--cwd", "/nonexistent"]).exec;
+
+        let lines: Vec<(String, String)> = cell_commands(&config, &runnable, &args)
+            .unwrap()
+            .iter()
+            .map(|(label, _, command)| (label.clone(), command_line(command)))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (
+                    "all".to_string(),
+                    "cargo test -p demo --lib --all-features".to_string()
+                ),
+                (
+                    "serde".to_string(),
+                    "cargo test -p demo --lib --features serde".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matrix_rejects_single_run_flags() {
+        super::super::isolate_home();
+        for flag in [
+            &["--tag", "smoke"][..],
+            &["--debug"],
+            &["--emit-script", "run.sh"],
+        ] {
+            let mut argv = vec!["matrix", "/nonexistent/src/lib.rs"];
+            argv.extend(flag);
+            let error = matrix(Cli::parse_from(argv).exec).unwrap_err();
+            assert!(error.to_string().contains(flag[0]), "{}", error);
+        }
+    }
+}
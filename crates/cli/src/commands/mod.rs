@@ -0,0 +1,60 @@
+mod analyze;
+mod alias;
+mod cache;
+mod check;
+mod config;
+mod coverage;
+mod debug;
+mod diff;
+mod exec;
+mod find;
+mod index;
+mod init;
+mod lsp;
+mod matrix;
+mod profile;
+mod profile_self;
+mod run;
+mod state;
+mod watch;
+mod which;
+
+pub use analyze::{analyze, AnalyzeArgs};
+pub use alias::{alias, AliasCommands};
+pub use cache::{cache, CacheCommands};
+pub use check::{check, CheckArgs};
+pub use config::{config, ConfigCommands};
+pub use coverage::{coverage, CoverageArgs};
+pub use debug::{debug, DebugArgs};
+pub use diff::{diff, DiffArgs};
+pub use exec::{exec, ExecArgs};
+pub use find::{find, FindArgs};
+pub use index::{index, IndexArgs};
+pub use init::{init, InitArgs};
+pub use lsp::lsp;
+pub use matrix::matrix;
+pub use profile::{profile, ProfileArgs};
+pub use profile_self::{profile_self, ProfileSelfArgs};
+pub use run::{run, RunArgs};
+pub use state::{state, StateCommands};
+pub use watch::{watch, WatchArgs};
+pub use which::{which, WhichArgs};
+
+/// Points `HOME` and the state directory at a scratch directory, once per test binary,
+/// so tests never read or write the user's config.
+#[cfg(test)]
+pub(crate) fn isolate_home() {
+    static ONCE: std::sync::Once = std::sync::Once::new();
+    ONCE.call_once(|| {
+        let home = tempfile::tempdir().unwrap().into_path();
+        if let Some(user) = dirs::home_dir() {
+            for (key, dir) in [("CARGO_HOME", ".cargo"), ("RUSTUP_HOME", ".rustup")] {
+                if std::env::var_os(key).is_none() {
+                    std::env::set_var(key, user.join(dir));
+                }
+            }
+        }
+        std::env::set_var("HOME", &home);
+        std::env::set_var("CARGO_RUNNER_STATE_DIR", home.join("state"));
+    });
+}
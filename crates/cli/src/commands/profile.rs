@@ -0,0 +1,83 @@
+use std::{fs, io::ErrorKind, process::ExitCode};
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use core::{BuildContext, Profiler, Runnable, TargetKind};
+
+use super::exec::{
+    build_launch, debug_plan, exec_config, exit_code, find_runnable, load_runner, resolve_target,
+    ExecArgs,
+};
+
+#[derive(Debug, Args)]
+pub struct ProfileArgs {
+    #[command(flatten)]
+    pub exec: ExecArgs,
+    /// Profiler to use: flamegraph, samply or perf, defaults to `profiler` from config
+    #[arg(long, value_parser = parse_profiler)]
+    pub profiler: Option<Profiler>,
+}
+
+fn parse_profiler(value: &str) -> Result<Profiler, String> {
+    Profiler::try_from(value)
+}
+
+/// Builds the runnable in release mode with debuginfo and runs the binary under a profiler.
+pub fn profile(mut args: ProfileArgs) -> Result<ExitCode> {
+    let alias_anchor = resolve_target(&mut args.exec.location)?;
+    let exec = &args.exec;
+    let runner = load_runner(&exec.location.target.path)?;
+
+    let runnable = find_runnable(&runner, &exec.location, alias_anchor, false)?;
+    let config = &exec_config(&runner, runnable.context, exec)?;
+    let profiler = args.profiler.or(config.profiler).unwrap_or_default();
+
+    let launch = build_launch(&mut debug_plan(config, &runnable, exec)?.release(), exec)?;
+
+    let context = BuildContext::gather(config, Some(&exec.location.target.path))?;
+    let workspace_root = context
+        .workspace_root
+        .as_deref()
+        .ok_or_else(|| anyhow!("No workspace found for: {}", exec.location.target))?;
+    let profiles = context.cargo.target_dir(workspace_root).join("profiles");
+    fs::create_dir_all(&profiles)?;
+    let profile = profiles.join(format!(
+        "{}.{}",
+        profile_name(&runnable),
+        profiler.extension()
+    ));
+
+    let mut command = launch.wrapped(&profiler.wrapper(&profile));
+    if let Some(cwd) = &exec.cwd {
+        command.current_dir(cwd);
+    }
+    let status = command.status().map_err(|e| match e.kind() {
+        ErrorKind::NotFound => anyhow!("`{}` not found, is it installed?", <&str>::from(profiler)),
+        _ => e.into(),
+    })?;
+    if status.success() {
+        eprintln!("profile written to {}", profile.display());
+    }
+
+    Ok(exit_code(status.code()))
+}
+
+fn profile_name(runnable: &Runnable) -> String {
+    let name = match (&runnable.function, &runnable.target) {
+        (Some(function), _) if function != "main" => function.clone(),
+        (
+            _,
+            Some(
+                TargetKind::Bin(name)
+                | TargetKind::Example(name)
+                | TargetKind::Test(name)
+                | TargetKind::Bench(name),
+            ),
+        ) => name.clone(),
+        _ => runnable
+            .package
+            .clone()
+            .unwrap_or_else(|| "profile".to_string()),
+    };
+    name.replace("::", "-")
+}
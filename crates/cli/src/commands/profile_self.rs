@@ -0,0 +1,34 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::trace;
+
+#[derive(Debug, Args)]
+pub struct ProfileSelfArgs {
+    /// Where to write the trace
+    #[arg(long, value_name = "PATH", default_value = "cargo-runner-trace.json")]
+    pub output: PathBuf,
+    /// The cargo-runner command to profile e.g. `analyze --all`
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    pub command: Vec<String>,
+}
+
+/// Runs `command` through `run` while recording the spans of the pipeline, then
+/// writes them as a Chrome trace, also when the command failed.
+pub fn profile_self(
+    args: ProfileSelfArgs,
+    run: impl FnOnce(&[String]) -> Result<ExitCode>,
+) -> Result<ExitCode> {
+    trace::record();
+    let result = run(&args.command);
+
+    let spans = trace::write(&args.output)?;
+    eprintln!(
+        "trace of {} spans written to {}, open it in https://ui.perfetto.dev",
+        spans,
+        args.output.display()
+    );
+    result
+}
@@ -0,0 +1,176 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use core::{
+    command_line, find_by_name, find_workspace_root, qualified_name, Execution, FileAnalysis,
+    FileTarget, FoundRunnable, PathId, WorkspaceIndex,
+};
+
+use super::{
+    analyze::{fetch_index, indexed_within},
+    exec::{exec, exit_code, load_runner, report, BuildArgs, ExecArgs, Location},
+    index::load_index,
+};
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// Fully qualified name of the test, bench or module e.g. `my_crate::tests::it_works`,
+    /// its path in the crate e.g. `tests::it_works`, or the name of a runnable declared
+    /// in config
+    #[arg(long)]
+    pub name: String,
+    /// Workspace to search, the one of the current directory by default
+    #[arg(long)]
+    pub root: Option<PathBuf>,
+    /// Config name to use instead of the default for the context
+    #[arg(long)]
+    pub config: Option<String>,
+    /// Print the command instead of running it
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Print every command that could run the runnable as JSON, with why each would be picked
+    #[arg(long)]
+    pub all_candidates: bool,
+    #[command(flatten)]
+    pub build: BuildArgs,
+    /// Extra arguments passed to the command
+    #[arg(last = true)]
+    pub args: Vec<String>,
+}
+
+/// Runs a runnable found by name in the workspace index, with the command `exec`
+/// builds for its file and line.
+pub fn run(args: RunArgs) -> Result<ExitCode> {
+    let root = match &args.root {
+        Some(root) => root.clone(),
+        None => std::env::current_dir()?,
+    };
+    if let Some(declared) = load_runner(&root)?.user_runnable(&args.name) {
+        if args.all_candidates {
+            return Err(anyhow!(
+                "Runnable '{}' is declared in config, its command is the only candidate",
+                declared.name
+            ));
+        }
+        if !args.build.is_empty() {
+            return Err(anyhow!(
+                "Runnable '{}' is declared in config, build flags don't apply to its command",
+                declared.name
+            ));
+        }
+        let mut command = declared
+            .command(&root)
+            .ok_or_else(|| anyhow!("Runnable '{}' has no command", declared.name))?;
+        command.args(&args.args);
+        if args.dry_run {
+            println!("{}", command_line(&command));
+            return Ok(ExitCode::SUCCESS);
+        }
+        return Ok(exit_code(report(Execution::new(&mut command).run()?)));
+    }
+
+    let files = workspace_files(args.root)?;
+
+    let found = find_by_name(&files, &args.name);
+    let (file, found) = match found.as_slice() {
+        [found] => *found,
+        [] => return Err(anyhow!("No runnable named: {}", args.name)),
+        matches => {
+            let names: Vec<String> = matches
+                .iter()
+                .map(|(file, found)| {
+                    let name = qualified_name(&found.runnable).unwrap_or_default();
+                    format!(
+                        "  {} ({}:{})",
+                        name,
+                        file.path.display(),
+                        found.scope.start_line
+                    )
+                })
+                .collect();
+            return Err(anyhow!(
+                "{} runnables named {}, use the qualified name:\n{}",
+                matches.len(),
+                args.name,
+                names.join("\n")
+            ));
+        }
+    };
+
+    exec_found(
+        file,
+        found,
+        args.config,
+        args.dry_run,
+        args.all_candidates,
+        args.build,
+        args.args,
+    )
+}
+
+/// The analysis of the workspace at `root`, or of the current directory, from the index
+/// at `CARGO_RUNNER_INDEX_URL` when it has one for the checked out commit. Otherwise
+/// from the stored index, created on the first run, analyzing only the changed files.
+pub(super) fn workspace_files(root: Option<PathBuf>) -> Result<Vec<FileAnalysis>> {
+    let root = match root {
+        Some(root) => root,
+        None => {
+            let current_dir = std::env::current_dir()?;
+            find_workspace_root(&current_dir).unwrap_or(current_dir)
+        }
+    };
+    if let Some(files) = std::env::var("CARGO_RUNNER_INDEX_URL")
+        .ok()
+        .and_then(|url| fetch_index(&url, &root))
+    {
+        return Ok(files);
+    }
+    let index = load_index(&root).unwrap_or_else(|| WorkspaceIndex {
+        root: PathId::new(&root).into(),
+        ..Default::default()
+    });
+    indexed_within(&index, &root, &[])
+}
+
+/// Runs `found` through `exec` at its file and line.
+pub(super) fn exec_found(
+    file: &FileAnalysis,
+    found: &FoundRunnable,
+    config: Option<String>,
+    dry_run: bool,
+    all_candidates: bool,
+    build: BuildArgs,
+    args: Vec<String>,
+) -> Result<ExitCode> {
+    let target = FileTarget {
+        line: Some(found.scope.start_line as u32),
+        ..FileTarget::new(&file.path)
+    };
+    exec(ExecArgs {
+        location: Location {
+            target,
+            context: Some(found.runnable.context.to_string()),
+            stdin_source: false,
+            anchor: None,
+            cursor_strategy: None,
+        },
+        build,
+        config,
+        cwd: None,
+        tag: None,
+        debug: false,
+        valgrind: false,
+        heaptrack: false,
+        explain: false,
+        cold: false,
+        no_auto: false,
+        tool: None,
+        timeout: None,
+        shard: None,
+        dry_run,
+        emit_script: None,
+        all_candidates,
+        args,
+    })
+}
@@ -0,0 +1,28 @@
+use std::process::ExitCode;
+
+use anyhow::Result;
+use clap::Subcommand;
+use core::{StateDir, STATE_VERSION};
+
+#[derive(Debug, Subcommand)]
+pub enum StateCommands {
+    /// Print the state directory and its layout version
+    Path,
+    /// Delete the caches, history and other stores of the state directory
+    Reset,
+}
+
+pub fn state(command: StateCommands) -> Result<ExitCode> {
+    let path = StateDir::default_path()?;
+    match command {
+        StateCommands::Path => {
+            let state = StateDir::open(&path)?;
+            println!("{} (version {})", state.root().display(), STATE_VERSION);
+        }
+        StateCommands::Reset => {
+            let state = StateDir::reset(&path)?;
+            println!("reset {}", state.root().display());
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
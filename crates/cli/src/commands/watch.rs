@@ -0,0 +1,85 @@
+use std::process::{Child, ExitCode};
+
+use anyhow::Result;
+use clap::Args;
+use core::{
+    command_line, find_package_root, BuildContext, CommandBuilder, Config, Runnable, Watcher,
+};
+
+use super::exec::{find_config, find_runnable, load_runner, resolve_target, Location};
+
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    #[command(flatten)]
+    pub location: Location,
+    /// Config name to use instead of the default for the context
+    #[arg(long)]
+    pub config: Option<String>,
+    /// Extra arguments passed to the command
+    #[arg(last = true)]
+    pub args: Vec<String>,
+}
+
+/// Runs the command `exec` builds for the target, then runs it again whenever a file of
+/// the workspace changes, stopping the previous run first if it is still going.
+pub fn watch(mut args: WatchArgs) -> Result<ExitCode> {
+    let alias_anchor = resolve_target(&mut args.location)?;
+    let path = args.location.target.path.clone();
+    let runner = load_runner(&path)?;
+    let runnable = find_runnable(&runner, &args.location, alias_anchor, false)?;
+    let config = find_config(&runner, runnable.context, args.config.as_deref())?;
+
+    let context = BuildContext::gather(config, Some(&path))?;
+    let root = context
+        .workspace_root
+        .clone()
+        .or_else(|| find_package_root(&path))
+        .unwrap_or_else(|| context.current_dir.clone());
+    let watcher = Watcher::new(&root, config.watch.as_ref())?;
+    eprintln!("watching {}", root.display());
+
+    loop {
+        let mut child = spawn(config, &runnable, &args)?;
+        let changed = watcher.next_change();
+        stop(&mut child)?;
+
+        let changed = changed?;
+        let first = changed[0].strip_prefix(&root).unwrap_or(&changed[0]);
+        match changed.len() {
+            1 => eprintln!("{} changed, running again", first.display()),
+            n => eprintln!(
+                "{} and {} more changed, running again",
+                first.display(),
+                n - 1
+            ),
+        }
+    }
+}
+
+/// Builds the command afresh, so changes to the environment it captures are picked up.
+fn spawn(config: &Config, runnable: &Runnable, args: &WatchArgs) -> Result<Child> {
+    let path = &args.location.target.path;
+    let context = BuildContext::gather(config, Some(path))?;
+    let mut command = CommandBuilder::new(config)
+        .runnable(runnable)
+        .file_path(path)
+        .context(&context)
+        .args(args.args.clone())
+        .build()?;
+
+    eprintln!("$ {}", command_line(&command));
+    Ok(command.spawn()?)
+}
+
+/// Kills `child` if it is still running and reaps it, reporting how it ended.
+fn stop(child: &mut Child) -> Result<()> {
+    match child.try_wait()? {
+        Some(status) => eprintln!("{}", status),
+        None => {
+            child.kill()?;
+            child.wait()?;
+            eprintln!("stopped the previous run");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use core::{label, Runnable};
+
+use super::exec::{find_config, find_runnable, load_runner, resolve_target, Location};
+
+#[derive(Debug, Args)]
+pub struct WhichArgs {
+    #[command(flatten)]
+    pub location: Location,
+    /// Print only the value of this field: package, target, kind, module, function, filter,
+    /// doc_test or label
+    #[arg(long)]
+    pub field: Option<String>,
+    /// Print why the scopes around the line gave no runnable of their own
+    #[arg(long)]
+    pub explain: bool,
+}
+
+/// Prints the runnable found at a location without building a command.
+pub fn which(mut args: WhichArgs) -> Result<ExitCode> {
+    let alias_anchor = resolve_target(&mut args.location)?;
+    let runner = load_runner(&args.location.target.path)?;
+    let runnable = find_runnable(&runner, &args.location, alias_anchor, args.explain)?;
+    let templates = find_config(&runner, runnable.context, None)
+        .ok()
+        .and_then(|config| config.labels.as_ref());
+    let mut fields = fields(&runnable);
+    fields.push(("label", Some(label(&runnable, templates))));
+
+    match &args.field {
+        Some(field) => {
+            let (_, value) = fields
+                .iter()
+                .find(|(name, _)| name == field)
+                .ok_or_else(|| anyhow!("Unknown field: {}", field))?;
+            println!("{}", value.as_deref().unwrap_or_default());
+        }
+        None => {
+            for (name, value) in &fields {
+                if let Some(value) = value {
+                    println!("{}: {}", name, value);
+                }
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn fields(runnable: &Runnable) -> Vec<(&'static str, Option<String>)> {
+    let module = match &runnable.function {
+        Some(function) => function
+            .rsplit_once("::")
+            .map(|(module, _)| module.to_string()),
+        None => runnable.filter.clone(),
+    };
+
+    vec![
+        ("package", runnable.package.clone()),
+        ("target", runnable.target.as_ref().map(ToString::to_string)),
+        ("kind", Some(<&str>::from(runnable.kind).to_string())),
+        ("module", module),
+        ("function", runnable.function.clone()),
+        ("filter", runnable.filter.clone()),
+        ("doc_test", runnable.doc_test.map(|mode| mode.to_string())),
+    ]
+}
@@ -0,0 +1,104 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs, panic,
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use core::BugReport;
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{filter::LevelFilter, layer::Context, prelude::*, Layer};
+
+/// Number of tracing events kept for a bug report.
+const RECENT_EVENTS: usize = 64;
+
+static EVENTS: OnceLock<Mutex<VecDeque<String>>> = OnceLock::new();
+
+fn events() -> &'static Mutex<VecDeque<String>> {
+    EVENTS.get_or_init(|| Mutex::new(VecDeque::with_capacity(RECENT_EVENTS)))
+}
+
+/// Keeps the last [RECENT_EVENTS] tracing events at debug level and above in memory,
+/// without printing them.
+struct RecentEvents;
+
+impl<S: Subscriber> Layer<S> for RecentEvents {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut line = format!("{} {}:", metadata.level(), metadata.target());
+        event.record(&mut FieldWriter(&mut line));
+
+        let mut events = events().lock().unwrap_or_else(|e| e.into_inner());
+        if events.len() == RECENT_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(line);
+    }
+}
+
+struct FieldWriter<'a>(&'a mut String);
+
+impl Visit for FieldWriter<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        let _ = match field.name() {
+            "message" => write!(self.0, " {:?}", value),
+            name => write!(self.0, " {}={:?}", name, value),
+        };
+    }
+}
+
+/// Records tracing events and, on a panic, writes a redacted [BugReport] to the temp
+/// directory and prints its path after the default panic message.
+pub fn install() {
+    let _ = tracing_subscriber::registry()
+        .with(RecentEvents.with_filter(LevelFilter::DEBUG))
+        .with(crate::trace::SpanTrace)
+        .try_init();
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panic".to_string());
+        let args: Vec<String> = std::env::args().collect();
+        let recent = events()
+            .lock()
+            .map(|events| events.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let report = BugReport::new(
+            env!("CARGO_PKG_VERSION"),
+            &args,
+            &message,
+            info.location().map(ToString::to_string),
+        )
+        .events(recent);
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let path = std::env::temp_dir().join(format!(
+            "cargo-runner-crash-{}-{}.json",
+            timestamp,
+            std::process::id()
+        ));
+        let written = serde_json::to_string_pretty(&report)
+            .ok()
+            .and_then(|json| fs::write(&path, json).ok());
+        if written.is_some() {
+            eprintln!(
+                "cargo-runner crashed, please attach this bug report to an issue: {}",
+                path.display()
+            );
+        }
+    }));
+}
@@ -1,10 +1,110 @@
-use core::CargoRunner;
-use std::path::PathBuf;
+mod commands;
+mod crash;
+mod trace;
+
+use std::process::ExitCode;
+
 use anyhow::Result;
-fn main() -> Result<()> {
-    let _default = CargoRunner::init()?;
-    let config_path  = PathBuf::from("cargo-runner-leptos.toml");
-    let config = CargoRunner::load(config_path);
-    println!("{:#?}", config);
-    Ok(())
+use clap::{Parser, Subcommand};
+
+use commands::{
+    AliasCommands, AnalyzeArgs, CacheCommands, CheckArgs, ConfigCommands, CoverageArgs, DebugArgs,
+    DiffArgs, ExecArgs, FindArgs, IndexArgs, InitArgs, ProfileArgs, ProfileSelfArgs, RunArgs,
+    StateCommands, WatchArgs, WhichArgs,
+};
+use core::RunnableKind;
+
+#[derive(Debug, Parser)]
+#[command(name = "cargo-runner", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Initialize the default config at `~/.cargo-runner/config.toml`, or the team config with `--team`
+    Init(InitArgs),
+    /// Build and execute the command for a file
+    Exec(ExecArgs),
+    /// Run a test, bench or module by its fully qualified name instead of a file and line
+    Run(RunArgs),
+    /// Fuzzy search the runnables of the workspace by name, `--run` runs the best match
+    Find(FindArgs),
+    /// Print the runnables found in a file, or in a directory with `--all`
+    Analyze(AnalyzeArgs),
+    /// Store the runnables of every file of the workspace for `analyze --all` and `lsp`,
+    /// analyzing again only the files changed since the last run
+    Index(IndexArgs),
+    /// Print the runnable found at a location: package, target, module and function
+    Which(WhichArgs),
+    /// Run `cargo check` for the package of a file
+    Check(CheckArgs),
+    /// Run `cargo clippy` for the package of a file, with `lints` from the build config
+    Clippy(CheckArgs),
+    /// Run the command for a file once per combination of the config's `matrix`
+    Matrix(ExecArgs),
+    /// Run the command for a file under a profiler (flamegraph, samply or perf)
+    Profile(ProfileArgs),
+    /// Build the target of a file and launch it under rust-lldb or rust-gdb, or print a DAP
+    /// launch configuration for it
+    Debug(DebugArgs),
+    /// Run the tests for a file under cargo-llvm-cov, printing or writing the coverage report
+    Coverage(CoverageArgs),
+    /// Print how the commands of the runnables under a path change from a git revision
+    /// or config file to the current config
+    Diff(DiffArgs),
+    /// Manage config files
+    #[command(subcommand)]
+    Config(ConfigCommands),
+    /// Manage named aliases for frequently used targets
+    #[command(subcommand)]
+    Alias(AliasCommands),
+    /// Run the target, then run it again on every change in the workspace
+    Watch(WatchArgs),
+    /// Serve code lenses for the runnables of open files over LSP on stdin/stdout
+    Lsp,
+    /// Manage the versioned state directory holding caches and history
+    #[command(subcommand)]
+    State(StateCommands),
+    /// Inspect or clear the scopes and workspace indexes cached across runs
+    #[command(subcommand)]
+    Cache(CacheCommands),
+    /// Run a cargo-runner command and write where its time went as a Chrome trace,
+    /// with span names and timings only, to attach to performance issues
+    ProfileSelf(ProfileSelfArgs),
+}
+
+fn main() -> Result<ExitCode> {
+    crash::install();
+    run(Cli::parse())
+}
+
+fn run(cli: Cli) -> Result<ExitCode> {
+    match cli.command {
+        Commands::Init(args) => commands::init(args),
+        Commands::Exec(args) => commands::exec(args),
+        Commands::Run(args) => commands::run(args),
+        Commands::Find(args) => commands::find(args),
+        Commands::Analyze(args) => commands::analyze(args),
+        Commands::Index(args) => commands::index(args),
+        Commands::Which(args) => commands::which(args),
+        Commands::Check(args) => commands::check(args, RunnableKind::Check),
+        Commands::Clippy(args) => commands::check(args, RunnableKind::Clippy),
+        Commands::Matrix(args) => commands::matrix(args),
+        Commands::Profile(args) => commands::profile(args),
+        Commands::Debug(args) => commands::debug(args),
+        Commands::Coverage(args) => commands::coverage(args),
+        Commands::Diff(args) => commands::diff(args),
+        Commands::Config(command) => commands::config(command),
+        Commands::Alias(command) => commands::alias(command),
+        Commands::State(command) => commands::state(command),
+        Commands::Cache(command) => commands::cache(command),
+        Commands::Lsp => commands::lsp(),
+        Commands::Watch(args) => commands::watch(args),
+        Commands::ProfileSelf(args) => commands::profile_self(args, |command| {
+            let args = std::iter::once("cargo-runner").chain(command.iter().map(String::as_str));
+            run(Cli::parse_from(args))
+        }),
+    }
 }
@@ -0,0 +1,124 @@
+use std::{
+    cell::Cell,
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+    time::Instant,
+};
+
+use serde::Serialize;
+use tracing::{span, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// A completed span in the Chrome trace event format, which Perfetto also reads.
+/// Only span names and timings are kept, no fields, so traces can be shared as is.
+#[derive(Debug, Serialize)]
+struct TraceEvent {
+    name: &'static str,
+    cat: &'static str,
+    ph: &'static str,
+    /// Microseconds since recording started.
+    ts: u64,
+    dur: u64,
+    pid: u32,
+    tid: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Trace<'a> {
+    #[serde(rename = "traceEvents")]
+    trace_events: &'a [TraceEvent],
+}
+
+struct Recording {
+    started: Instant,
+    events: Vec<TraceEvent>,
+}
+
+static RECORDING: OnceLock<Mutex<Recording>> = OnceLock::new();
+
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static THREAD: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A small stable id for the current thread, as trace viewers group spans by thread.
+fn thread_id() -> u64 {
+    THREAD.with(|id| {
+        if id.get() == 0 {
+            id.set(NEXT_THREAD.fetch_add(1, Ordering::Relaxed));
+        }
+        id.get()
+    })
+}
+
+/// When a span was entered first.
+struct Entered(Instant);
+
+/// Records the timing of every span once [record] started, nothing before.
+pub struct SpanTrace;
+
+impl<S> Layer<S> for SpanTrace
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if RECORDING.get().is_none() {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            let mut extensions = span.extensions_mut();
+            if extensions.get_mut::<Entered>().is_none() {
+                extensions.insert(Entered(Instant::now()));
+            }
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let (Some(recording), Some(span)) = (RECORDING.get(), ctx.span(&id)) else {
+            return;
+        };
+        let Some(&Entered(entered)) = span.extensions().get::<Entered>() else {
+            return;
+        };
+        let metadata = span.metadata();
+        let mut recording = recording.lock().unwrap_or_else(|e| e.into_inner());
+        let event = TraceEvent {
+            name: metadata.name(),
+            cat: metadata.target(),
+            ph: "X",
+            ts: entered
+                .saturating_duration_since(recording.started)
+                .as_micros() as u64,
+            dur: entered.elapsed().as_micros() as u64,
+            pid: std::process::id(),
+            tid: thread_id(),
+        };
+        recording.events.push(event);
+    }
+}
+
+/// Starts recording the spans [SpanTrace] sees.
+pub fn record() {
+    let _ = RECORDING.set(Mutex::new(Recording {
+        started: Instant::now(),
+        events: Vec::new(),
+    }));
+}
+
+/// Writes the spans recorded so far as a Chrome trace JSON file, returning how many.
+pub fn write(path: &Path) -> anyhow::Result<usize> {
+    let Some(recording) = RECORDING.get() else {
+        return Ok(0);
+    };
+    let recording = recording.lock().unwrap_or_else(|e| e.into_inner());
+    let trace = Trace {
+        trace_events: &recording.events,
+    };
+    fs::write(path, serde_json::to_string(&trace)?)?;
+    Ok(recording.events.len())
+}
@@ -1,4 +1,4 @@
-use core::{CargoRunner, Config, Context, Error};
+use core::{CargoRunner, Context, Error};
 use std::path::PathBuf;
 
 fn main()-> anyhow::Result<(),Error> {
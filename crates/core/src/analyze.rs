@@ -0,0 +1,354 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use anyhow::anyhow;
+use ignore::{overrides::OverrideBuilder, WalkBuilder, WalkState};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    context_finder::FoundRunnable, read_source, ContextFinder, DetectionBudget, Error, Runnable,
+    RunnableKind, TargetKind,
+};
+
+/// The runnables found in one file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileAnalysis {
+    pub path: PathBuf,
+    pub runnables: Vec<FoundRunnable>,
+    /// Number of `#[test]` tokens in the source, to spot tests detection missed.
+    pub test_markers: usize,
+    /// Whether detection ran out of its budget and only found part of the runnables.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+}
+
+impl FileAnalysis {
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        Self::within(path, DetectionBudget::default())
+    }
+
+    /// Analyzes the file within `budget`, see [DetectionBudget].
+    pub fn within(path: impl Into<PathBuf>, budget: DetectionBudget) -> Result<Self, Error> {
+        let path = path.into();
+        let source = read_source(&path)?;
+        Self::of_source(path, source, budget)
+    }
+
+    /// Analyzes `source` as the content of the file at `path`.
+    pub fn of_source(
+        path: PathBuf,
+        source: String,
+        budget: DetectionBudget,
+    ) -> Result<Self, Error> {
+        let test_markers = source.matches("#[test]").count();
+        let detection = ContextFinder::new(&path)
+            .source(source)
+            .budget(budget)
+            .detect()?;
+
+        Ok(FileAnalysis {
+            path,
+            runnables: detection.runnables,
+            test_markers,
+            partial: detection.partial,
+        })
+    }
+}
+
+/// The fully qualified name of `runnable` as libtest and rustdoc show it, prefixed by
+/// its crate e.g. `my_crate::tests::it_works`, `None` without a target or path.
+pub fn qualified_name(runnable: &Runnable) -> Option<String> {
+    let krate = match runnable.target.as_ref()? {
+        TargetKind::Lib => runnable.package.clone()?,
+        TargetKind::Bin(name)
+        | TargetKind::Example(name)
+        | TargetKind::Test(name)
+        | TargetKind::Bench(name) => name.clone(),
+    };
+    let path = runnable.function.as_ref().or(runnable.filter.as_ref())?;
+    Some(format!("{}::{}", krate.replace('-', "_"), path))
+}
+
+/// The runnables of `files` named `name`, either fully qualified like [qualified_name]
+/// or by their path in the crate e.g. `tests::it_works`.
+pub fn find_by_name<'a>(
+    files: &'a [FileAnalysis],
+    name: &str,
+) -> Vec<(&'a FileAnalysis, &'a FoundRunnable)> {
+    files
+        .iter()
+        .flat_map(|file| file.runnables.iter().map(move |found| (file, found)))
+        .filter(|(_, found)| {
+            let runnable = &found.runnable;
+            runnable.function.as_deref().or(runnable.filter.as_deref()) == Some(name)
+                || qualified_name(runnable).as_deref() == Some(name)
+        })
+        .collect()
+}
+
+/// Workspace-level numbers over the files of an analysis.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnalysisSummary {
+    pub files: usize,
+    /// Number of runnables per [RunnableKind] name.
+    pub counts: BTreeMap<&'static str, usize>,
+    /// Files containing `#[test]` without any test runnable: detection misses.
+    pub missed: Vec<PathBuf>,
+    /// Files whose cargo target could not be determined.
+    pub ambiguous: Vec<PathBuf>,
+    /// Files detection ran out of budget on.
+    pub partial: Vec<PathBuf>,
+}
+
+impl AnalysisSummary {
+    pub fn new(files: &[FileAnalysis]) -> Self {
+        let mut summary = AnalysisSummary {
+            files: files.len(),
+            ..Default::default()
+        };
+
+        for file in files {
+            for found in &file.runnables {
+                *summary
+                    .counts
+                    .entry(found.runnable.kind.into())
+                    .or_default() += 1;
+            }
+            let has_tests = file
+                .runnables
+                .iter()
+                .any(|found| found.runnable.kind == RunnableKind::Test);
+            if file.partial {
+                summary.partial.push(file.path.clone());
+            } else if file.test_markers > 0 && !has_tests {
+                summary.missed.push(file.path.clone());
+            }
+            if file
+                .runnables
+                .iter()
+                .any(|found| found.runnable.target.is_none())
+            {
+                summary.ambiguous.push(file.path.clone());
+            }
+        }
+
+        summary
+    }
+
+    /// Whether detection missed tests somewhere, the condition `analyze --strict` fails on.
+    pub fn has_gaps(&self) -> bool {
+        !self.missed.is_empty()
+    }
+}
+
+/// Finds the runnables of every Rust file under `root`, in parallel.
+///
+/// Honors `.gitignore` and `.ignore` files, skips `target` directories and
+/// paths matching one of the `exclude` globs (relative to `root`).
+/// Results are sorted by path.
+pub fn analyze(root: &Path, exclude: &[String]) -> Result<Vec<FileAnalysis>, Error> {
+    analyze_within(root, exclude, DetectionBudget::default())
+}
+
+/// Like [analyze] with each file analyzed within `budget`.
+#[tracing::instrument(skip_all)]
+pub fn analyze_within(
+    root: &Path,
+    exclude: &[String],
+    budget: DetectionBudget,
+) -> Result<Vec<FileAnalysis>, Error> {
+    let mut files = walk_rust_files(root, exclude, |path| FileAnalysis::within(path, budget))?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(files)
+}
+
+/// Analyzes each of `paths` within `budget`, in parallel over the available cores,
+/// e.g. the files a change touched. Results are in the order of `paths`.
+#[tracing::instrument(skip_all)]
+pub fn analyze_files(
+    paths: &[PathBuf],
+    budget: DetectionBudget,
+) -> Result<Vec<FileAnalysis>, Error> {
+    let threads = thread::available_parallelism()
+        .map_or(1, |threads| threads.get())
+        .min(paths.len());
+    let next = AtomicUsize::new(0);
+    let mut analyzed: Vec<(usize, Result<FileAnalysis, Error>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut analyzed = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(i) else {
+                            break analyzed;
+                        };
+                        analyzed.push((i, FileAnalysis::within(path, budget)));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("analysis thread panicked"))
+            .collect()
+    });
+    analyzed.sort_by_key(|(i, _)| *i);
+
+    analyzed.into_iter().map(|(_, analysis)| analysis).collect()
+}
+
+/// Calls `visit` on every Rust file under `root` in parallel, skipping ignored, `target`
+/// and `exclude`d paths like [analyze]. The results are in no particular order.
+pub(crate) fn walk_rust_files<T: Send>(
+    root: &Path,
+    exclude: &[String],
+    visit: impl Fn(PathBuf) -> Result<T, Error> + Sync,
+) -> Result<Vec<T>, Error> {
+    let mut overrides = OverrideBuilder::new(root);
+    for pattern in exclude {
+        overrides
+            .add(&format!("!{}", pattern))
+            .map_err(|e| Error::Other(anyhow!(e)))?;
+    }
+    let overrides = overrides.build().map_err(|e| Error::Other(anyhow!(e)))?;
+
+    let results = Mutex::new(Vec::new());
+    WalkBuilder::new(root)
+        .require_git(false)
+        .overrides(overrides)
+        .filter_entry(|entry| entry.file_name() != "target")
+        .build_parallel()
+        .run(|| {
+            Box::new(|entry| {
+                let result = match entry {
+                    Ok(entry) if entry.path().extension().is_some_and(|ext| ext == "rs") => {
+                        visit(entry.into_path())
+                    }
+                    Ok(_) => return WalkState::Continue,
+                    Err(e) => Err(Error::Other(anyhow!(e))),
+                };
+                results.lock().expect("analysis results lock").push(result);
+                WalkState::Continue
+            })
+        });
+
+    results
+        .into_inner()
+        .expect("analysis results lock")
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_analyze_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("src");
+        fs::create_dir_all(src.join("generated")).unwrap();
+        fs::write(
+            root.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::write(root.path().join(".gitignore"), "ignored.rs\n").unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "#[cfg(test)]\nmod tests {\n    #[test]\n    fn it_works() {}\n}\n",
+        )
+        .unwrap();
+        fs::write(src.join("ignored.rs"), "#[test]\nfn skipped() {}\n").unwrap();
+        fs::write(
+            src.join("generated").join("api.rs"),
+            "#[test]\nfn skipped() {}\n",
+        )
+        .unwrap();
+
+        let files = analyze(root.path(), &["src/generated/**".to_string()]).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, src.join("lib.rs"));
+
+        let found: Vec<_> = files[0]
+            .runnables
+            .iter()
+            .map(|found| (found.runnable.kind, found.runnable.filter.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (RunnableKind::ModuleTests, None),
+                (RunnableKind::ModuleTests, Some("tests")),
+                (RunnableKind::Test, Some("tests::it_works")),
+            ]
+        );
+
+        fs::write(
+            src.join("macros.rs"),
+            "macro_rules! t {\n    ($name:ident) => { #[test] fn $name() {} };\n}\n",
+        )
+        .unwrap();
+        let files = analyze(root.path(), &["src/generated/**".to_string()]).unwrap();
+        let summary = AnalysisSummary::new(&files);
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.counts.get("test"), Some(&1));
+        assert_eq!(summary.missed, vec![src.join("macros.rs")]);
+        assert!(summary.has_gaps());
+
+        let found = find_by_name(&files, "demo::tests::it_works");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1.scope.start_line, 3);
+        assert_eq!(find_by_name(&files, "tests").len(), 1);
+
+        let paths = vec![src.join("macros.rs"), src.join("lib.rs")];
+        let files = analyze_files(&paths, DetectionBudget::default()).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                FileAnalysis::new(&paths[0]).unwrap(),
+                FileAnalysis::new(&paths[1]).unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analyze_files_in_order() {
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        // More files than threads, listed against the order they'd be walked in.
+        let paths: Vec<PathBuf> = (0..32)
+            .rev()
+            .map(|i| {
+                let path = src.join(format!("file_{:02}.rs", i));
+                fs::write(&path, format!("#[test]\nfn test_{}() {{}}\n", i)).unwrap();
+                path
+            })
+            .collect();
+
+        let files = analyze_files(&paths, DetectionBudget::default()).unwrap();
+        let analyzed: Vec<&PathBuf> = files.iter().map(|file| &file.path).collect();
+        assert_eq!(analyzed, paths.iter().collect::<Vec<_>>());
+        assert_eq!(files[5], FileAnalysis::new(&paths[5]).unwrap());
+
+        let mut missing = paths.clone();
+        missing.insert(16, src.join("missing.rs"));
+        assert!(analyze_files(&missing, DetectionBudget::default()).is_err());
+        assert!(analyze_files(&[], DetectionBudget::default())
+            .unwrap()
+            .is_empty());
+    }
+}
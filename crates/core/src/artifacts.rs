@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::TargetKind;
+
+/// A target cargo compiled to an executable, read from a `compiler-artifact`
+/// message of `--message-format=json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artifact {
+    #[serde(default)]
+    pub package_id: String,
+    #[serde(default)]
+    pub target: ArtifactTarget,
+    #[serde(default)]
+    pub profile: ArtifactProfile,
+    pub executable: PathBuf,
+    /// Whether cargo reused the artifact of a previous build.
+    #[serde(default)]
+    pub fresh: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactTarget {
+    /// e.g. `["lib"]`, `["bin"]` or `["test"]`.
+    #[serde(default)]
+    pub kind: Vec<String>,
+    #[serde(default)]
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactProfile {
+    /// Whether the artifact is a test harness binary e.g. the unit tests of a lib.
+    #[serde(default)]
+    pub test: bool,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    reason: String,
+    #[serde(flatten)]
+    artifact: Option<Artifact>,
+}
+
+/// The executables of a cargo build, the one place the debug, memcheck and profile
+/// modes find the binary to launch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Artifacts(pub Vec<Artifact>);
+
+const LIB_KINDS: &[&str] = &["lib", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"];
+
+impl Artifacts {
+    /// Reads the executables from the JSON messages of a build, skipping other output.
+    pub fn parse(messages: &str) -> Self {
+        Artifacts(
+            messages
+                .lines()
+                .filter_map(|line| serde_json::from_str::<Message>(line).ok())
+                .filter(|message| message.reason == "compiler-artifact")
+                .filter_map(|message| message.artifact)
+                .collect(),
+        )
+    }
+
+    /// The executable built for `target`. Without a target, or when no artifact
+    /// matches it, the last executable built wins.
+    pub fn executable(&self, target: Option<&TargetKind>) -> Option<&Path> {
+        let matched =
+            target.and_then(|target| self.0.iter().rev().find(|artifact| artifact.is_for(target)));
+        matched
+            .or_else(|| self.0.last())
+            .map(|artifact| artifact.executable.as_path())
+    }
+}
+
+impl Artifact {
+    fn is_for(&self, target: &TargetKind) -> bool {
+        let kind = |kind: &str| self.target.kind.iter().any(|k| k == kind);
+        // cargo names the targets of a lib and its tests after the crate, with `_`.
+        let named =
+            |name: &str| self.target.name == name || self.target.name == name.replace('-', "_");
+        match target {
+            TargetKind::Lib => self.profile.test && LIB_KINDS.iter().any(|lib| kind(lib)),
+            TargetKind::Bin(name) => kind("bin") && named(name),
+            TargetKind::Example(name) => kind("example") && named(name),
+            TargetKind::Test(name) => kind("test") && named(name),
+            TargetKind::Bench(name) => kind("bench") && named(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_executable_per_target() {
+        let messages = r#"{"reason":"compiler-artifact","package_id":"demo 0.1.0","target":{"kind":["lib"],"name":"demo"},"profile":{"test":false},"executable":null,"fresh":true}
+{"reason":"compiler-artifact","package_id":"demo 0.1.0","target":{"kind":["lib"],"name":"demo"},"profile":{"test":true},"executable":"/target/debug/deps/demo-1111","fresh":false}
+{"reason":"compiler-artifact","package_id":"demo 0.1.0","target":{"kind":["test"],"name":"smoke"},"profile":{"test":true},"executable":"/target/debug/deps/smoke-2222","fresh":true}
+{"reason":"build-finished","success":true}"#;
+        let artifacts = Artifacts::parse(messages);
+        assert_eq!(artifacts.0.len(), 2);
+
+        assert_eq!(
+            artifacts.executable(Some(&TargetKind::Lib)),
+            Some(Path::new("/target/debug/deps/demo-1111"))
+        );
+        assert_eq!(
+            artifacts.executable(Some(&TargetKind::Test("smoke".to_string()))),
+            Some(Path::new("/target/debug/deps/smoke-2222"))
+        );
+        assert_eq!(
+            artifacts.executable(None),
+            Some(Path::new("/target/debug/deps/smoke-2222"))
+        );
+        assert_eq!(Artifacts::parse("").executable(None), None);
+    }
+}
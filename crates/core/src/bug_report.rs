@@ -0,0 +1,152 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    scanner::{fnv1a, FNV_OFFSET},
+    CargoRunner,
+};
+
+/// What a crash report needs to be actionable without sharing secrets: the arguments
+/// with home paths and secret values redacted, and digests of the config layers
+/// instead of their contents.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BugReport {
+    pub version: String,
+    pub args: Vec<String>,
+    pub panic: String,
+    /// Where the panic happened e.g. `crates/core/src/scanner.rs:42:9`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// FNV-1a digests of the config layers applying to the current directory, user
+    /// config first, to tell whether a reproduction uses the same config.
+    pub config_layers: Vec<ConfigLayerDigest>,
+    /// The last tracing events before the panic, oldest first.
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigLayerDigest {
+    pub path: String,
+    pub digest: String,
+}
+
+/// Argument and env names whose values are replaced by `<redacted>`.
+const SECRET_NAMES: &[&str] = &["TOKEN", "SECRET", "PASSWORD", "PASSWD", "KEY", "AUTH"];
+
+impl BugReport {
+    pub fn new(version: &str, args: &[String], panic: &str, location: Option<String>) -> Self {
+        let home = dirs::home_dir();
+        let home = home.as_deref();
+        let mut layers: Vec<_> = CargoRunner::get_default_config_path().into_iter().collect();
+        if let Ok(current_dir) = std::env::current_dir() {
+            layers.extend(CargoRunner::local_config_layers(&current_dir));
+        }
+
+        BugReport {
+            version: version.to_string(),
+            args: redact_args(args, home),
+            panic: redact(panic, home),
+            location,
+            config_layers: layers
+                .iter()
+                .filter_map(|path| {
+                    let content = fs::read(path).ok()?;
+                    Some(ConfigLayerDigest {
+                        path: redact(&path.to_string_lossy(), home),
+                        digest: format!("{:016x}", fnv1a(FNV_OFFSET, &content)),
+                    })
+                })
+                .collect(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn events(mut self, events: impl IntoIterator<Item = String>) -> Self {
+        let home = dirs::home_dir();
+        self.events = events
+            .into_iter()
+            .map(|event| redact(&event, home.as_deref()))
+            .collect();
+        self
+    }
+}
+
+/// Redacts every argument, and the values following flags named like secrets
+/// e.g. `--token abc`.
+fn redact_args(args: &[String], home: Option<&Path>) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut secret_value = false;
+    for arg in args {
+        if secret_value && !arg.starts_with('-') {
+            redacted.push("<redacted>".to_string());
+        } else {
+            redacted.push(redact(arg, home));
+        }
+        secret_value = arg.starts_with("--") && !arg.contains('=') && is_secret(arg);
+    }
+    redacted
+}
+
+/// Replaces the home directory with `~` and the values of `NAME=value` pairs named
+/// like secrets.
+fn redact(text: &str, home: Option<&Path>) -> String {
+    let text = match home.map(|home| home.to_string_lossy()) {
+        Some(home) if home.len() > 1 => text.replace(home.as_ref(), "~"),
+        _ => text.to_string(),
+    };
+    text.split(' ')
+        .map(|word| match word.split_once('=') {
+            Some((name, _)) if is_secret(name) => format!("{}=<redacted>", name),
+            _ => word.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SECRET_NAMES.iter().any(|secret| name.contains(secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redaction() {
+        let home = Path::new("/home/dev");
+        let args: Vec<String> = [
+            "cargo-runner",
+            "exec",
+            "/home/dev/proj/src/lib.rs:10",
+            "--index-token",
+            "abc123",
+            "--",
+            "API_KEY=xyz",
+            "--nocapture",
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+        assert_eq!(
+            redact_args(&args, Some(home)),
+            vec![
+                "cargo-runner",
+                "exec",
+                "~/proj/src/lib.rs:10",
+                "--index-token",
+                "<redacted>",
+                "--",
+                "API_KEY=<redacted>",
+                "--nocapture",
+            ]
+        );
+        assert_eq!(
+            redact("GITHUB_TOKEN=ghp_1 RUST_LOG=debug", Some(home)),
+            "GITHUB_TOKEN=<redacted> RUST_LOG=debug"
+        );
+    }
+}
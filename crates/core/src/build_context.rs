@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    exec::nextest_installed, find_package_root, find_workspace_root, CargoConfig, Config, Error,
+    Heuristics, PathId,
+};
+
+/// Everything [CommandBuilder](crate::CommandBuilder) needs from the filesystem and
+/// the process environment, gathered up front so building a command does no IO
+/// and gives the same command for the same context.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildContext {
+    pub current_dir: PathBuf,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_root: Option<PathBuf>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_root: Option<PathBuf>,
+    /// The identity of the file, the one overrides match the linked path of.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<PathId>,
+    /// The parent environment a clean [EnvPolicy](crate::EnvPolicy) may pass on.
+    #[serde(default)]
+    pub env: HashMap<OsString, OsString>,
+    #[serde(default)]
+    pub heuristics: Heuristics,
+    /// The `.cargo/config.toml` settings that apply to the file.
+    #[serde(default)]
+    pub cargo: CargoConfig,
+    /// Whether cargo-nextest is installed, for `framework_test = "auto"`.
+    #[serde(default)]
+    pub nextest_installed: bool,
+}
+
+impl BuildContext {
+    /// Reads the current directory, the roots of `file_path` (or of the current
+    /// directory), the environment `config` may inherit and the installed tools.
+    #[tracing::instrument(skip_all)]
+    pub fn gather(config: &Config, file_path: Option<&Path>) -> Result<Self, Error> {
+        let current_dir = std::env::current_dir()?;
+        let anchor = file_path.unwrap_or(&current_dir);
+
+        let package_root = find_package_root(anchor);
+        let heuristics = match &package_root {
+            Some(root) => Heuristics::detect(root)?,
+            None => Heuristics::default(),
+        };
+
+        let cargo_dir = match file_path {
+            Some(path) if path.is_file() => path.parent().unwrap_or(path),
+            _ => anchor,
+        };
+        let cargo = CargoConfig::load(cargo_dir)?;
+
+        Ok(BuildContext {
+            workspace_root: find_workspace_root(anchor),
+            package_root,
+            file_id: file_path.map(PathId::new),
+            env: config.capture_env(),
+            heuristics,
+            cargo,
+            current_dir,
+            nextest_installed: nextest_installed(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{OsStr, OsString};
+
+    use super::*;
+    use crate::{command_line, CommandBuilder, Context, EnvPolicy, Runnable, TestFramework};
+
+    #[test]
+    fn test_build_without_io() {
+        let config = Config {
+            name: "default".to_string(),
+            sub_command: Some("test".to_string()),
+            working_dir: Some("{workspace_root}/crates".to_string()),
+            env_policy: Some(EnvPolicy::Clean),
+            framework_test: Some(TestFramework::Auto),
+            ..Default::default()
+        };
+        let runnable = Runnable {
+            package: Some("core".to_string()),
+            ..Runnable::new(Context::Test)
+        };
+
+        // A real package whose `.cargo/config.toml` doesn't parse: gathering anything
+        // from disk fails the build, and the context disagrees with the process on
+        // the roots, PATH and nextest.
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("src/lib.rs");
+        std::fs::create_dir_all(dir.path().join(".cargo")).unwrap();
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"core\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join(".cargo/config.toml"), "not [toml").unwrap();
+        std::fs::write(&file, "").unwrap();
+
+        let builder = || {
+            CommandBuilder::new(&config)
+                .runnable(&runnable)
+                .file_path(&file)
+        };
+        assert!(builder().build().is_err());
+
+        let context = BuildContext {
+            current_dir: PathBuf::from("/context"),
+            workspace_root: Some(PathBuf::from("/context/ws")),
+            package_root: Some(PathBuf::from("/context/ws/crates/core")),
+            file_id: None,
+            env: HashMap::from([(OsString::from("PATH"), OsString::from("/context/bin"))]),
+            heuristics: Heuristics::default(),
+            cargo: CargoConfig::default(),
+            nextest_installed: true,
+        };
+        let command = builder().context(&context).build().unwrap();
+
+        assert_eq!(command_line(&command), "cargo nextest run -p core");
+        assert_eq!(
+            command.get_current_dir(),
+            Some(Path::new("/context/ws/crates"))
+        );
+        let envs: Vec<_> = command.get_envs().collect();
+        assert_eq!(
+            envs,
+            vec![(OsStr::new("PATH"), Some(OsStr::new("/context/bin")))]
+        );
+    }
+}
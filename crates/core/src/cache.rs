@@ -0,0 +1,450 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analyze::walk_rust_files,
+    analyze_files, find_package_root, find_workspace_root, read_source,
+    scanner::{fnv1a, inclusions, FNV_OFFSET},
+    DetectionBudget, Error, FileAnalysis, PathId, Scope, StateDir,
+};
+
+/// Version of the stored [WorkspaceIndex] format, an index of another version is
+/// rebuilt rather than reused.
+const INDEX_VERSION: u32 = 2;
+
+/// Store of the [StateDir] holding the [WorkspaceIndex] of each workspace.
+const INDEX_STORE: &str = "index";
+
+/// Store of the [StateDir] holding the [ScopeCache].
+const SCOPE_STORE: &str = "scopes";
+
+/// The stores `cache stats` and `cache clear` cover.
+pub const CACHE_STORES: &[&str] = &[SCOPE_STORE, INDEX_STORE];
+
+/// The analysis of a file with the hash of the content it was made from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexedFile {
+    pub hash: u64,
+    /// The root of the [PackageStamp] the analysis also depends on.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<PathBuf>,
+    pub analysis: FileAnalysis,
+}
+
+/// What the analyses of a package's files depend on besides their own content: the
+/// manifest, which names the package and its targets and lists their required
+/// features, and the files declaring `#[path]` modules or `include!`s, which place
+/// other files in the module tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageStamp {
+    pub root: PathBuf,
+    pub manifest_hash: u64,
+    /// Checked before hashing the manifest again.
+    pub manifest_mtime: Option<SystemTime>,
+    /// The declaring files with the hash of their content, sorted by path.
+    pub includers: Vec<(PathBuf, u64)>,
+}
+
+impl PackageStamp {
+    fn new(root: PathBuf, includers: Vec<(PathBuf, u64)>) -> Self {
+        let manifest = root.join("Cargo.toml");
+        PackageStamp {
+            manifest_hash: fs::read(&manifest).map_or(0, |bytes| fnv1a(FNV_OFFSET, &bytes)),
+            manifest_mtime: modified(&manifest),
+            root,
+            includers,
+        }
+    }
+
+    /// Whether the stamp was made from the same inputs as `other`, whatever the mtimes.
+    fn same_inputs(&self, other: &Self) -> bool {
+        self.manifest_hash == other.manifest_hash && self.includers == other.includers
+    }
+
+    /// Whether the manifest and the declaring files are unchanged on disk. A file that
+    /// starts declaring a `#[path]` module is only noticed by [WorkspaceIndex::refresh].
+    fn is_current(&self) -> bool {
+        let manifest = self.root.join("Cargo.toml");
+        let manifest_current = (self.manifest_mtime.is_some()
+            && modified(&manifest) == self.manifest_mtime)
+            || fs::read(&manifest).map_or(0, |bytes| fnv1a(FNV_OFFSET, &bytes))
+                == self.manifest_hash;
+        manifest_current
+            && self.includers.iter().all(|(path, hash)| {
+                fs::read(path).is_ok_and(|bytes| fnv1a(FNV_OFFSET, &bytes) == *hash)
+            })
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// The runnables of every Rust file of a workspace, kept in the state directory so
+/// `analyze` and the LSP server can answer without parsing. Files are keyed by the
+/// hash of their content and the [PackageStamp] of their package, only the files
+/// where either changed are analyzed again on refresh. The analyses don't depend on
+/// the config, readers apply `detectors.disabled` and `labels` themselves.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceIndex {
+    #[serde(default)]
+    pub version: u32,
+    pub root: PathBuf,
+    /// Sorted by path.
+    pub files: Vec<IndexedFile>,
+    /// Sorted by root.
+    #[serde(default)]
+    pub packages: Vec<PackageStamp>,
+}
+
+impl WorkspaceIndex {
+    /// Where the index of `root` is stored, one file per workspace.
+    pub fn path(state: &StateDir, root: &Path) -> Result<PathBuf, Error> {
+        let root = PathId::new(root);
+        let key = fnv1a(FNV_OFFSET, root.to_string_lossy().as_bytes());
+        Ok(state.store(INDEX_STORE)?.join(format!("{:016x}.json", key)))
+    }
+
+    /// The stored index of `root`, `None` if it was never indexed.
+    pub fn load(state: &StateDir, root: &Path) -> Result<Option<Self>, Error> {
+        let content = match fs::read_to_string(Self::path(state, root)?) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        // An index written by another version is rebuilt rather than failing.
+        Ok(serde_json::from_str(&content)
+            .ok()
+            .filter(|index: &Self| index.version == INDEX_VERSION))
+    }
+
+    pub fn save(&self, state: &StateDir) -> Result<PathBuf, Error> {
+        let path = Self::path(state, &self.root)?;
+        fs::write(
+            &path,
+            serde_json::to_string(self).map_err(anyhow::Error::from)?,
+        )?;
+        Ok(path)
+    }
+
+    /// Indexes every Rust file under `root` in parallel like [crate::analyze], reusing
+    /// the analysis of `self` for files whose content and [PackageStamp] didn't change.
+    /// Returns the new index and the number of files analyzed again. Files are indexed
+    /// by their path under the canonical `root`.
+    pub fn refresh(&self, root: &Path, exclude: &[String]) -> Result<(Self, usize), Error> {
+        let root = PathId::new(root);
+        let packages_of_dirs = Mutex::new(HashMap::new());
+        let mut hashed = walk_rust_files(&root, exclude, |path| {
+            let source = read_source(&path)?;
+            let hash = fnv1a(FNV_OFFSET, source.as_bytes());
+            let declares = (source.contains("include!") || source.contains("path"))
+                && !inclusions(&source).is_empty();
+            let dir = path.parent().unwrap_or(&path).to_path_buf();
+            let package = packages_of_dirs
+                .lock()
+                .expect("package roots lock")
+                .entry(dir)
+                .or_insert_with_key(|dir| manifest_root(dir))
+                .clone();
+            Ok((path, hash, declares, package))
+        })?;
+        hashed.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut includers: BTreeMap<PathBuf, Vec<(PathBuf, u64)>> = BTreeMap::new();
+        for (path, hash, declares, package) in &hashed {
+            if let Some(package) = package {
+                let declaring = includers.entry(package.clone()).or_default();
+                if *declares {
+                    declaring.push((path.clone(), *hash));
+                }
+            }
+        }
+        let packages: Vec<PackageStamp> = includers
+            .into_iter()
+            .map(|(root, includers)| PackageStamp::new(root, includers))
+            .collect();
+
+        let unchanged = |file: &IndexedFile, hash: u64, package: &Option<PathBuf>| {
+            let stamps_match = match package {
+                Some(root) => stamp_of(&self.packages, root)
+                    .zip(stamp_of(&packages, root))
+                    .is_some_and(|(old, new)| old.same_inputs(new)),
+                None => true,
+            };
+            self.version == INDEX_VERSION
+                && file.hash == hash
+                && file.package == *package
+                && stamps_match
+        };
+        let mut files = Vec::with_capacity(hashed.len());
+        let mut changed = Vec::new();
+        for (path, hash, _, package) in hashed {
+            match self
+                .find(&path)
+                .filter(|file| unchanged(file, hash, &package))
+            {
+                Some(file) => files.push(file.clone()),
+                None => changed.push((path, hash, package)),
+            }
+        }
+
+        let paths: Vec<PathBuf> = changed.iter().map(|(path, _, _)| path.clone()).collect();
+        let analyses = analyze_files(&paths, DetectionBudget::default())?;
+        let analyzed = analyses.len();
+        for ((_, hash, package), analysis) in changed.into_iter().zip(analyses) {
+            files.push(IndexedFile {
+                hash,
+                package,
+                analysis,
+            });
+        }
+        files.sort_by(|a, b| a.analysis.path.cmp(&b.analysis.path));
+        let index = WorkspaceIndex {
+            version: INDEX_VERSION,
+            root: root.into(),
+            files,
+            packages,
+        };
+        Ok((index, analyzed))
+    }
+
+    /// The indexed file at `path`, whatever its content now.
+    pub fn get(&self, path: &Path) -> Option<&IndexedFile> {
+        self.find(&PathId::new(path))
+    }
+
+    fn find(&self, path: &Path) -> Option<&IndexedFile> {
+        self.files
+            .binary_search_by(|file| file.analysis.path.as_path().cmp(path))
+            .ok()
+            .map(|at| &self.files[at])
+    }
+
+    /// The analysis of the file at `path` if it was indexed with `source` as content
+    /// and the [PackageStamp] of its package is still current.
+    pub fn fresh(&self, path: &Path, source: &str) -> Option<&FileAnalysis> {
+        let hash = fnv1a(FNV_OFFSET, source.as_bytes());
+        let file = self
+            .get(path)
+            .filter(|file| self.version == INDEX_VERSION && file.hash == hash)?;
+        let current = match &file.package {
+            Some(root) => stamp_of(&self.packages, root).is_some_and(PackageStamp::is_current),
+            None => true,
+        };
+        current.then_some(&file.analysis)
+    }
+
+    /// The analyses of the index, in path order.
+    pub fn analyses(&self) -> Vec<FileAnalysis> {
+        self.files
+            .iter()
+            .map(|file| file.analysis.clone())
+            .collect()
+    }
+}
+
+/// The stamp of the package at `root` in `stamps`, sorted by root.
+fn stamp_of<'a>(stamps: &'a [PackageStamp], root: &Path) -> Option<&'a PackageStamp> {
+    stamps
+        .binary_search_by(|stamp| stamp.root.as_path().cmp(root))
+        .ok()
+        .map(|at| &stamps[at])
+}
+
+/// The directory of the manifest that locates the files of `dir`: its package, else
+/// the workspace whose members may claim them.
+fn manifest_root(dir: &Path) -> Option<PathBuf> {
+    find_package_root(dir).or_else(|| find_workspace_root(dir))
+}
+
+/// The scopes scanned from source files, kept across runs so invoking cargo-runner
+/// again on an unchanged file skips scanning it. One entry per file, replaced when
+/// its content changes. Only complete scans are cached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeCache {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedScopes {
+    path: PathId,
+    hash: u64,
+    scopes: Vec<Scope>,
+}
+
+impl ScopeCache {
+    pub fn open(state: &StateDir) -> Result<Self, Error> {
+        Ok(ScopeCache {
+            dir: state.store(SCOPE_STORE)?,
+        })
+    }
+
+    /// The scopes of the file at `path` if they were scanned from `source`.
+    pub fn get(&self, path: &Path, source: &str) -> Option<Vec<Scope>> {
+        let path = PathId::new(path);
+        let content = fs::read_to_string(self.entry(&path)).ok()?;
+        let cached: CachedScopes = serde_json::from_str(&content).ok()?;
+        let hash = fnv1a(FNV_OFFSET, source.as_bytes());
+        (cached.path == path && cached.hash == hash).then_some(cached.scopes)
+    }
+
+    pub fn insert(&self, path: &Path, source: &str, scopes: &[Scope]) -> Result<(), Error> {
+        let path = PathId::new(path);
+        let cached = CachedScopes {
+            hash: fnv1a(FNV_OFFSET, source.as_bytes()),
+            scopes: scopes.to_vec(),
+            path,
+        };
+        let content = serde_json::to_string(&cached).map_err(anyhow::Error::from)?;
+        fs::write(self.entry(&cached.path), content)?;
+        Ok(())
+    }
+
+    fn entry(&self, path: &Path) -> PathBuf {
+        let key = fnv1a(FNV_OFFSET, path.to_string_lossy().as_bytes());
+        self.dir.join(format!("{:016x}.json", key))
+    }
+}
+
+/// The number of files and bytes in a cache store.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub store: &'static str,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl CacheStats {
+    /// The stats of each of the [CACHE_STORES].
+    pub fn of(state: &StateDir) -> Result<Vec<Self>, Error> {
+        CACHE_STORES
+            .iter()
+            .map(|store| {
+                let mut stats = CacheStats {
+                    store,
+                    ..Default::default()
+                };
+                for entry in fs::read_dir(state.store(store)?)? {
+                    stats.files += 1;
+                    stats.bytes += entry?.metadata()?.len();
+                }
+                Ok(stats)
+            })
+            .collect()
+    }
+}
+
+/// Deletes every entry of the [CACHE_STORES], returning how many there were.
+pub fn clear_caches(state: &StateDir) -> Result<usize, Error> {
+    let mut cleared = 0;
+    for store in CACHE_STORES {
+        for entry in fs::read_dir(state.store(store)?)? {
+            fs::remove_file(entry?.path())?;
+            cleared += 1;
+        }
+    }
+    Ok(cleared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_index_refresh() {
+        let root = tempfile::tempdir().unwrap();
+        let state = StateDir::open(root.path().join("state")).unwrap();
+        let workspace = root.path().join("demo");
+        fs::create_dir_all(workspace.join("src")).unwrap();
+        fs::write(
+            workspace.join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        let lib = workspace.join("src").join("lib.rs");
+        fs::write(&lib, "#[test]\nfn first() {}\n").unwrap();
+        fs::write(workspace.join("src").join("util.rs"), "pub fn util() {}\n").unwrap();
+
+        let (index, analyzed) = WorkspaceIndex::default().refresh(&workspace, &[]).unwrap();
+        assert_eq!(analyzed, 2);
+        index.save(&state).unwrap();
+
+        fs::write(&lib, "#[test]\nfn first() {}\n#[test]\nfn second() {}\n").unwrap();
+        let stored = WorkspaceIndex::load(&state, &workspace).unwrap().unwrap();
+        assert_eq!(stored, index);
+        assert!(stored
+            .fresh(&lib, &fs::read_to_string(&lib).unwrap())
+            .is_none());
+
+        let (index, analyzed) = stored.refresh(&workspace, &[]).unwrap();
+        assert_eq!(analyzed, 1);
+        let tests = index
+            .fresh(&lib, &fs::read_to_string(&lib).unwrap())
+            .unwrap()
+            .test_markers;
+        assert_eq!(tests, 2);
+        index.save(&state).unwrap();
+
+        // Renaming the package changes every runnable of it without touching a file.
+        let manifest = workspace.join("Cargo.toml");
+        fs::write(
+            &manifest,
+            "[package]\nname = \"renamed\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        let source = fs::read_to_string(&lib).unwrap();
+        assert!(index.fresh(&lib, &source).is_none());
+        let (index, analyzed) = index.refresh(&workspace, &[]).unwrap();
+        assert_eq!(analyzed, 2);
+        let package = index.fresh(&lib, &source).unwrap().runnables[0]
+            .runnable
+            .package
+            .clone();
+        assert_eq!(package.as_deref(), Some("renamed"));
+
+        // So does moving a file in the module tree through `#[path]`.
+        fs::write(
+            workspace.join("src").join("util.rs"),
+            "#[path = \"other.rs\"]\nmod other;\n",
+        )
+        .unwrap();
+        let (index, analyzed) = index.refresh(&workspace, &[]).unwrap();
+        assert_eq!(analyzed, 2);
+        assert_eq!(index.refresh(&workspace, &[]).unwrap().1, 0);
+
+        // An index of another format version is rebuilt.
+        let older = WorkspaceIndex {
+            version: INDEX_VERSION - 1,
+            ..index.clone()
+        };
+        older.save(&state).unwrap();
+        assert!(WorkspaceIndex::load(&state, &workspace).unwrap().is_none());
+        assert!(older.fresh(&lib, &source).is_none());
+        assert_eq!(older.refresh(&workspace, &[]).unwrap().1, 2);
+        index.save(&state).unwrap();
+
+        let cache = ScopeCache::open(&state).unwrap();
+        let source = fs::read_to_string(&lib).unwrap();
+        assert_eq!(cache.get(&lib, &source), None);
+        cache.insert(&lib, &source, &crate::scan(&source)).unwrap();
+        assert_eq!(cache.get(&lib, &source), Some(crate::scan(&source)));
+        assert_eq!(cache.get(&lib, "#[test]\nfn first() {}\n"), None);
+
+        let stats = CacheStats::of(&state).unwrap();
+        let files: Vec<_> = stats
+            .iter()
+            .map(|stats| (stats.store, stats.files))
+            .collect();
+        assert_eq!(files, vec![("scopes", 1), ("index", 1)]);
+        assert_eq!(clear_caches(&state).unwrap(), 2);
+        assert!(WorkspaceIndex::load(&state, &workspace).unwrap().is_none());
+    }
+}
@@ -0,0 +1,167 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    exec::nextest_installed, script::join_quoted, ArgStrategy, BuildContext, CargoRunner,
+    CommandBuilder, CommandType, Config, Context, Error, Runnable, RunnableKind,
+};
+
+/// A command that could run a runnable, with the reason it would be picked, so
+/// editors can offer the alternatives of an ambiguous location in a submenu.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Candidate {
+    /// Name of the config the command is built from.
+    pub config: String,
+    pub reason: String,
+    /// Whether this is the command `exec` runs without `--config`.
+    pub selected: bool,
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Candidate {
+    /// The command as it would be typed in a shell.
+    pub fn command_line(&self) -> String {
+        join_quoted(std::iter::once(&self.program).chain(&self.args).cloned())
+    }
+}
+
+/// Every command that could run `runnable` of `file_path`: the default config of its
+/// context first, then the other configs of the context, then `cargo nextest run`
+/// for tests when it is installed and no config uses it yet.
+pub fn candidates(
+    runner: &CargoRunner,
+    runnable: &Runnable,
+    file_path: &Path,
+) -> Result<Vec<Candidate>, Error> {
+    let default = runner.get_default(runnable.context);
+    let mut configs: Vec<(Config, String)> = runner
+        .0
+        .get(<&str>::from(runnable.context))
+        .and_then(|(_, configs)| configs.clone())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|config| {
+            let reason = match Some(config.name.as_str()) == default {
+                true => format!("default config for {}", runnable.context),
+                false => format!("config `{}` for {}", config.name, runnable.context),
+            };
+            (config, reason)
+        })
+        .collect();
+    configs.sort_by_key(|(config, _)| Some(config.name.as_str()) != default);
+
+    let runs_nextest = configs.iter().any(|(config, _)| {
+        CommandBuilder::new(config)
+            .runnable(runnable)
+            .arg_strategy()
+            == Some(ArgStrategy::Nextest)
+    });
+    if runnable.context == Context::Test
+        && runnable.kind != RunnableKind::DocTest
+        && !runs_nextest
+        && nextest_installed()
+    {
+        let config = Config {
+            name: "nextest".to_string(),
+            command_type: Some(CommandType::SubCommand),
+            command: Some("nextest".to_string()),
+            sub_command: Some("run".to_string()),
+            ..Default::default()
+        };
+        configs.push((config, "cargo-nextest is installed".to_string()));
+    }
+
+    configs
+        .into_iter()
+        .map(|(config, reason)| {
+            let context = BuildContext::gather(&config, Some(file_path))?;
+            let command = CommandBuilder::new(&config)
+                .runnable(runnable)
+                .file_path(file_path)
+                .context(&context)
+                .build()?;
+            Ok(Candidate {
+                selected: Some(config.name.as_str()) == default,
+                config: config.name,
+                reason,
+                program: command.get_program().to_string_lossy().to_string(),
+                args: command
+                    .get_args()
+                    .map(|arg| arg.to_string_lossy().to_string())
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TargetKind, TestFramework};
+
+    #[test]
+    fn test_candidates_per_config() {
+        let mut runner = CargoRunner::default();
+        if let Some((_, Some(configs))) = runner.0.get_mut("test") {
+            configs.push(Config {
+                name: "nextest".to_string(),
+                command_type: Some(CommandType::SubCommand),
+                command: Some("nextest".to_string()),
+                sub_command: Some("run".to_string()),
+                ..Default::default()
+            });
+        }
+        let runnable = Runnable {
+            kind: RunnableKind::Test,
+            package: Some("demo".to_string()),
+            target: Some(TargetKind::Lib),
+            filter: Some("tests::it_works".to_string()),
+            exact: true,
+            ..Runnable::new(Context::Test)
+        };
+
+        let candidates =
+            candidates(&runner, &runnable, Path::new("/nonexistent/src/lib.rs")).unwrap();
+        let summary: Vec<_> = candidates
+            .iter()
+            .map(|c| (c.config.as_str(), c.selected, c.command_line()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "default",
+                    true,
+                    "cargo test -p demo --lib -- tests::it_works --exact".to_string()
+                ),
+                (
+                    "nextest",
+                    false,
+                    "cargo nextest run -p demo --lib -E 'test(=tests::it_works)'".to_string()
+                ),
+            ]
+        );
+        assert_eq!(candidates[1].reason, "config `nextest` for test");
+
+        // A default config running nextest leaves no nextest candidate to add.
+        let mut runner = CargoRunner::default();
+        if let Some((_, Some(configs))) = runner.0.get_mut("test") {
+            configs[0].framework_test = Some(TestFramework::Nextest);
+        }
+        let nextest_default =
+            super::candidates(&runner, &runnable, Path::new("/nonexistent/src/lib.rs")).unwrap();
+        let summary: Vec<_> = nextest_default
+            .iter()
+            .map(|c| (c.reason.as_str(), c.command_line()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![(
+                "default config for test",
+                "cargo nextest run -p demo --lib -E 'test(=tests::it_works)'".to_string()
+            )]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// The settings of cargo's own `.cargo/config.toml` files that change what
+/// cargo-runner's commands do or where their output lands.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CargoConfig {
+    /// `build.target-dir`, or `CARGO_TARGET_DIR`, made absolute.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_dir: Option<PathBuf>,
+    /// `build.target`, the default target triple.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_target: Option<String>,
+    /// `build.rustflags`.
+    #[serde(default)]
+    pub rustflags: Vec<String>,
+    /// `[alias]` entries split into their args.
+    #[serde(default)]
+    pub aliases: BTreeMap<String, Vec<String>>,
+}
+
+impl CargoConfig {
+    /// Merges the config files cargo reads for a command run in `dir`: `$CARGO_HOME/config.toml`
+    /// first, then `.cargo/config.toml` of every ancestor from the root down, deeper
+    /// files winning and `rustflags` arrays joined.
+    pub fn load(dir: &Path) -> Result<Self, Error> {
+        let mut files: Vec<PathBuf> = Vec::new();
+        if let Some(home) = cargo_home() {
+            files.extend(config_file(&home));
+        }
+        let mut ancestors: Vec<&Path> = dir.ancestors().collect();
+        ancestors.reverse();
+        for ancestor in ancestors {
+            if let Some(file) = config_file(&ancestor.join(".cargo")) {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+        }
+
+        let mut config = CargoConfig::default();
+        for file in files {
+            let table: toml::Table = toml::from_str(&fs::read_to_string(&file)?)?;
+            let base = file
+                .parent()
+                .and_then(Path::parent)
+                .unwrap_or(Path::new("/"));
+            config.merge(&table, base);
+        }
+
+        if let Ok(target_dir) = std::env::var("CARGO_TARGET_DIR") {
+            config.target_dir = Some(dir.join(target_dir));
+        }
+
+        Ok(config)
+    }
+
+    fn merge(&mut self, table: &toml::Table, base: &Path) {
+        if let Some(build) = table.get("build").and_then(toml::Value::as_table) {
+            if let Some(target_dir) = build.get("target-dir").and_then(toml::Value::as_str) {
+                self.target_dir = Some(base.join(target_dir));
+            }
+            if let Some(target) = build.get("target").and_then(toml::Value::as_str) {
+                self.build_target = Some(target.to_string());
+            }
+            if let Some(rustflags) = build.get("rustflags") {
+                self.rustflags.extend(string_list(rustflags));
+            }
+        }
+        if let Some(aliases) = table.get("alias").and_then(toml::Value::as_table) {
+            for (name, value) in aliases {
+                self.aliases.insert(name.clone(), string_list(value));
+            }
+        }
+    }
+
+    /// Where cargo puts its build output for the workspace at `workspace_root`.
+    pub fn target_dir(&self, workspace_root: &Path) -> PathBuf {
+        self.target_dir
+            .clone()
+            .unwrap_or_else(|| workspace_root.join("target"))
+    }
+
+    /// The args `sub_command` stands for if it is a cargo alias e.g. `t` for `test`.
+    pub fn expand_alias(&self, sub_command: &str) -> Option<&[String]> {
+        self.aliases.get(sub_command).map(Vec::as_slice)
+    }
+}
+
+fn cargo_home() -> Option<PathBuf> {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")))
+}
+
+fn config_file(cargo_dir: &Path) -> Option<PathBuf> {
+    ["config.toml", "config"]
+        .into_iter()
+        .map(|name| cargo_dir.join(name))
+        .find(|file| file.is_file())
+}
+
+/// A cargo config value that is either a space separated string or an array of strings.
+fn string_list(value: &toml::Value) -> Vec<String> {
+    match value {
+        toml::Value::String(value) => value.split_whitespace().map(str::to_string).collect(),
+        toml::Value::Array(values) => values
+            .iter()
+            .filter_map(toml::Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_nested_configs() {
+        let root = tempfile::tempdir().unwrap();
+        let member = root.path().join("crates").join("core");
+        fs::create_dir_all(root.path().join(".cargo")).unwrap();
+        fs::create_dir_all(member.join(".cargo")).unwrap();
+        fs::write(
+            root.path().join(".cargo").join("config.toml"),
+            "[build]\ntarget-dir = \"out\"\nrustflags = [\"-Cdebuginfo=1\"]\n\n[alias]\nt = \"test --workspace\"\n",
+        )
+        .unwrap();
+        fs::write(
+            member.join(".cargo").join("config.toml"),
+            "[build]\ntarget = \"wasm32-wasip1\"\nrustflags = \"-Dwarnings\"\n",
+        )
+        .unwrap();
+
+        let config = CargoConfig::load(&member).unwrap();
+        assert_eq!(config.target_dir, Some(root.path().join("out")));
+        assert_eq!(config.build_target.as_deref(), Some("wasm32-wasip1"));
+        assert_eq!(config.rustflags, vec!["-Cdebuginfo=1", "-Dwarnings"]);
+        assert_eq!(
+            config.expand_alias("t"),
+            Some(&["test".to_string(), "--workspace".to_string()][..])
+        );
+    }
+}
@@ -0,0 +1,168 @@
+/// How the test binary of a command receives its filter and arguments.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArgStrategy {
+    /// libtest: `cargo test <cargo args> -- <filter> --exact <binary args>`
+    #[default]
+    Libtest,
+    /// cargo-nextest: `cargo nextest run <cargo args> -E 'test(=<filter>)' -- <binary args>`
+    Nextest,
+    /// `harness = false` targets e.g. criterion: `cargo bench <cargo args> -- <filter> <binary args>`
+    NoHarness,
+}
+
+/// The arguments of a command split into sections, so each [ArgStrategy]
+/// can place the filter and the `--` separator where its tool expects them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandArgs {
+    /// Arguments for cargo or the cargo subcommand e.g. `-p core --lib`.
+    pub cargo: Vec<String>,
+    /// Test or bench name filters, a test runs if it matches any of them.
+    pub filters: Vec<String>,
+    /// Whether the filters must match exactly.
+    pub exact: bool,
+    /// Whether the filters are module paths ending in `::`, matching the tests under
+    /// them. Nextest anchors them at the start of the test name, libtest has no such
+    /// match and runs any test whose name contains one.
+    pub prefix: bool,
+    /// Arguments for the test binary e.g. `--nocapture`.
+    pub binary: Vec<String>,
+}
+
+impl CommandArgs {
+    /// Adds user supplied args: those before `--` go to cargo, the rest to the test binary.
+    pub fn extend<I, S>(&mut self, args: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut binary = false;
+        for arg in args.into_iter().map(Into::into) {
+            if !binary && arg == "--" {
+                binary = true;
+            } else if binary {
+                self.binary.push(arg);
+            } else {
+                self.cargo.push(arg);
+            }
+        }
+    }
+
+    pub fn to_args(&self, strategy: ArgStrategy) -> Vec<String> {
+        let mut args = self.cargo.clone();
+        let mut binary = Vec::new();
+
+        match strategy {
+            ArgStrategy::Libtest => {
+                binary.extend(self.filters.iter().cloned());
+                if self.exact && !self.filters.is_empty() {
+                    binary.push("--exact".to_string());
+                }
+            }
+            // A filterset matches exactly with `=`, from the start of the name with an
+            // anchored regex, else the name contains the filter.
+            ArgStrategy::Nextest if !self.filters.is_empty() => {
+                let filterset = self
+                    .filters
+                    .iter()
+                    .map(|filter| match (self.exact, self.prefix) {
+                        (true, _) => format!("test(={})", filter),
+                        (false, true) => format!("test(/^{}/)", filter),
+                        (false, false) => format!("test({})", filter),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" | ");
+                args.extend(["-E".to_string(), filterset]);
+            }
+            ArgStrategy::Nextest => {}
+            ArgStrategy::NoHarness => binary.extend(self.filters.iter().cloned()),
+        }
+        binary.extend(self.binary.iter().cloned());
+
+        if !binary.is_empty() {
+            args.push("--".to_string());
+            args.extend(binary);
+        }
+
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args() -> CommandArgs {
+        let mut args = CommandArgs {
+            cargo: vec!["-p".to_string(), "core".to_string()],
+            filters: vec!["tests::it_works".to_string()],
+            exact: true,
+            prefix: false,
+            binary: Vec::new(),
+        };
+        args.extend(["--release", "--", "--nocapture"]);
+        args
+    }
+
+    #[test]
+    fn test_to_args_per_strategy() {
+        assert_eq!(
+            args().to_args(ArgStrategy::Libtest),
+            vec![
+                "-p",
+                "core",
+                "--release",
+                "--",
+                "tests::it_works",
+                "--exact",
+                "--nocapture"
+            ]
+        );
+        assert_eq!(
+            args().to_args(ArgStrategy::Nextest),
+            vec![
+                "-p",
+                "core",
+                "--release",
+                "-E",
+                "test(=tests::it_works)",
+                "--",
+                "--nocapture"
+            ]
+        );
+        let modules = CommandArgs {
+            filters: vec!["math::".to_string(), "io::".to_string()],
+            ..CommandArgs::default()
+        };
+        assert_eq!(
+            modules.to_args(ArgStrategy::Nextest),
+            vec!["-E", "test(math::) | test(io::)"]
+        );
+        let prefixes = CommandArgs {
+            prefix: true,
+            ..modules
+        };
+        assert_eq!(
+            prefixes.to_args(ArgStrategy::Nextest),
+            vec!["-E", "test(/^math::/) | test(/^io::/)"]
+        );
+        assert_eq!(
+            prefixes.to_args(ArgStrategy::Libtest),
+            vec!["--", "math::", "io::"]
+        );
+        assert_eq!(
+            args().to_args(ArgStrategy::NoHarness),
+            vec![
+                "-p",
+                "core",
+                "--release",
+                "--",
+                "tests::it_works",
+                "--nocapture"
+            ]
+        );
+        assert_eq!(
+            CommandArgs::default().to_args(ArgStrategy::Libtest),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -0,0 +1,880 @@
+use std::{path::PathBuf, process::Command};
+
+use anyhow::anyhow;
+
+use crate::{
+    exec::nextest_installed, ArgStrategy, BuildContext, CommandArgs, CommandType, Config, Context,
+    CoverageReport, DebugPlan, Directive, DocTestMode, Error, Hint, Override, PathId, Runnable,
+    RunnableKind, Shard, TargetKind, TestFramework,
+};
+
+/// Builds a [Command] from a [Config].
+///
+/// - `cargo` configs run `cargo <sub_command>`
+/// - `subcommand` configs run `cargo <command> <sub_command>` e.g. `cargo leptos watch`
+/// - `shell` configs run `<command> <sub_command>` e.g. `dx serve`
+#[derive(Debug, Clone)]
+pub struct CommandBuilder<'a> {
+    config: &'a Config,
+    runnable: Option<&'a Runnable>,
+    file_path: Option<PathBuf>,
+    working_dir: Option<PathBuf>,
+    filters: Option<Vec<String>>,
+    toolchain: Option<String>,
+    context: Option<&'a BuildContext>,
+    cold: bool,
+    shard: Option<Shard>,
+    auto_framework: bool,
+    tool: Option<TestFramework>,
+    coverage: Option<CoverageReport>,
+    /// Builds without running, for [CommandBuilder::debug_plan].
+    no_run: bool,
+    args: Vec<String>,
+}
+
+impl<'a> CommandBuilder<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        CommandBuilder {
+            config,
+            runnable: None,
+            file_path: None,
+            working_dir: None,
+            filters: None,
+            toolchain: None,
+            context: None,
+            cold: false,
+            shard: None,
+            auto_framework: true,
+            tool: None,
+            coverage: None,
+            no_run: false,
+            args: Vec::new(),
+        }
+    }
+
+    /// Adds the package, target and test filter of `runnable` to `cargo` commands.
+    pub fn runnable(mut self, runnable: &'a Runnable) -> Self {
+        self.runnable = Some(runnable);
+        self
+    }
+
+    /// The file the command is built for, used to resolve `{workspace_root}` and `{package_root}`.
+    pub fn file_path(mut self, file_path: impl Into<PathBuf>) -> Self {
+        self.file_path = Some(file_path.into());
+        self
+    }
+
+    /// Overrides the `working_dir` of the config e.g. from the `--cwd` flag.
+    pub fn working_dir(mut self, working_dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
+    /// Replaces the filter of the runnable, e.g. to run several tagged tests of a target at once.
+    pub fn filters<I, S>(mut self, filters: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.filters = Some(filters.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Runs cargo with a rustup toolchain e.g. `cargo +nightly test`.
+    pub fn toolchain(mut self, toolchain: impl Into<String>) -> Self {
+        self.toolchain = Some(toolchain.into());
+        self
+    }
+
+    /// Builds from a gathered [BuildContext] instead of reading the filesystem
+    /// and environment during [CommandBuilder::build].
+    pub fn context(mut self, context: &'a BuildContext) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// Builds without incremental compilation or a rustc wrapper, for reproducible timings.
+    pub fn cold(mut self, cold: bool) -> Self {
+        self.cold = cold;
+        self
+    }
+
+    /// Runs one shard of the tests with nextest's `--partition`. libtest has no
+    /// equivalent, list the tests and pass those of [Shard::contains] as filters instead.
+    pub fn shard(mut self, shard: Shard) -> Self {
+        self.shard = Some(shard);
+        self
+    }
+
+    /// Whether `framework_test = "auto"` may pick nextest, `false` runs libtest e.g. for `--no-auto`.
+    pub fn auto_framework(mut self, auto_framework: bool) -> Self {
+        self.auto_framework = auto_framework;
+        self
+    }
+
+    /// Overrides the `framework_test` of the config e.g. from the `--tool` flag.
+    pub fn tool(mut self, tool: Option<TestFramework>) -> Self {
+        self.tool = tool;
+        self
+    }
+
+    /// Runs the command under cargo-llvm-cov e.g. `cargo llvm-cov nextest`, writing `report`.
+    /// Only `cargo` configs can be wrapped.
+    pub fn coverage(mut self, report: CoverageReport) -> Self {
+        self.coverage = Some(report);
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Resolves the directory the command would run in, if any was configured.
+    pub fn resolve_working_dir(&self) -> Result<Option<PathBuf>, Error> {
+        match self.context {
+            Some(context) => self.working_dir_in(context),
+            None => self.working_dir_in(&self.gather()?),
+        }
+    }
+
+    fn gather(&self) -> Result<BuildContext, Error> {
+        BuildContext::gather(self.config, self.file_path.as_deref())
+    }
+
+    fn working_dir_in(&self, context: &BuildContext) -> Result<Option<PathBuf>, Error> {
+        if let Some(dir) = &self.working_dir {
+            return Ok(Some(dir.clone()));
+        }
+
+        let Some(template) = &self.config.working_dir else {
+            return Ok(None);
+        };
+
+        let anchor = self.file_path.as_ref().unwrap_or(&context.current_dir);
+        let mut resolved = template.clone();
+        for (placeholder, root) in [
+            ("{workspace_root}", &context.workspace_root),
+            ("{package_root}", &context.package_root),
+        ] {
+            if resolved.contains(placeholder) {
+                let root = root
+                    .as_ref()
+                    .ok_or_else(|| Error::CargoTomlNotFound(anchor.display().to_string()))?;
+                resolved = resolved.replace(placeholder, &root.display().to_string());
+            }
+        }
+
+        Ok(Some(PathBuf::from(resolved)))
+    }
+
+    /// The overrides of the config that apply to the runnable, in config order.
+    pub fn matching_overrides(&self) -> Vec<&'a Override> {
+        let file_id = match self.context {
+            Some(context) => context.file_id.clone(),
+            None => self.file_path.as_ref().map(PathId::new),
+        };
+        self.overrides_matching(file_id.as_ref())
+    }
+
+    fn overrides_matching(&self, file_id: Option<&PathId>) -> Vec<&'a Override> {
+        let Some(runnable) = self.runnable else {
+            return Vec::new();
+        };
+        self.config
+            .overrides
+            .iter()
+            .flatten()
+            .filter(|o| o.matches(runnable, self.file_path.as_deref(), file_id))
+            .collect()
+    }
+
+    /// The target triple to build for: that of the last matching override, else of the
+    /// config. `None` when the extra args pick one, like the cells of a matrix do.
+    pub fn target(&self) -> Option<&str> {
+        self.target_of(&self.matching_overrides())
+    }
+
+    fn target_of(&self, overrides: &[&'a Override]) -> Option<&'a str> {
+        if self
+            .args
+            .iter()
+            .any(|arg| arg == "--target" || arg.starts_with("--target="))
+        {
+            return None;
+        }
+        overrides
+            .iter()
+            .rev()
+            .find_map(|matched| matched.target.as_deref())
+            .or(self.config.target.as_deref())
+    }
+
+    /// The [Hint]s of the package that apply to the runnable, none when `heuristics = false`.
+    pub fn hints(&self) -> Result<Vec<Hint>, Error> {
+        match self.context {
+            Some(context) => Ok(self.hints_in(context)),
+            None => Ok(self.hints_in(&self.gather()?)),
+        }
+    }
+
+    fn hints_in(&self, context: &BuildContext) -> Vec<Hint> {
+        match (self.runnable, self.config.heuristics) {
+            (Some(_), Some(false)) | (None, _) => Vec::new(),
+            (Some(runnable), _) => context.heuristics.hints(runnable),
+        }
+    }
+
+    /// The [ArgStrategy] of the configured tool, `None` for tools that
+    /// don't take cargo target and test filter args e.g. `dx serve`.
+    pub fn arg_strategy(&self) -> Option<ArgStrategy> {
+        match self.config.command_type.clone().unwrap_or_default() {
+            CommandType::Cargo if self.framework() == TestFramework::Nextest => {
+                Some(ArgStrategy::Nextest)
+            }
+            CommandType::Cargo => Some(ArgStrategy::Libtest),
+            CommandType::SubCommand if self.config.command.as_deref() == Some("nextest") => {
+                Some(ArgStrategy::Nextest)
+            }
+            _ => None,
+        }
+    }
+
+    /// The framework a `cargo` config runs the tests of the runnable with, as its
+    /// `framework_test` asks with `auto` resolved. Doc tests stay on `cargo test`
+    /// with nextest, which can't run them. Other runnables always use libtest.
+    /// Whether nextest is installed comes from the [BuildContext] when one was given.
+    fn framework(&self) -> TestFramework {
+        let Some(runnable) = self
+            .runnable
+            .filter(|runnable| runnable.context == Context::Test)
+        else {
+            return TestFramework::Libtest;
+        };
+        let framework = match self.tool.or(self.config.framework_test).unwrap_or_default() {
+            TestFramework::Auto
+                if self.auto_framework
+                    && self
+                        .context
+                        .map_or_else(nextest_installed, |context| context.nextest_installed) =>
+            {
+                TestFramework::Nextest
+            }
+            TestFramework::Auto => TestFramework::Libtest,
+            framework => framework,
+        };
+        match (framework, runnable.kind) {
+            (TestFramework::Nextest, RunnableKind::DocTest) => TestFramework::Libtest,
+            (framework, _) => framework,
+        }
+    }
+
+    /// The `cargo test --doc` command for the doc tests a nextest run of a library
+    /// module skips, `None` when the config doesn't run nextest or `doctest_fallback = false`.
+    pub fn doc_tests_fallback(&self) -> Result<Option<Command>, Error> {
+        let Some(runnable) = self.runnable else {
+            return Ok(None);
+        };
+        if runnable.kind != RunnableKind::ModuleTests
+            || runnable.target != Some(TargetKind::Lib)
+            || self.arg_strategy() != Some(ArgStrategy::Nextest)
+            || self.config.doctest_fallback == Some(false)
+        {
+            return Ok(None);
+        }
+
+        let doc_tests = Runnable {
+            kind: RunnableKind::DocTest,
+            exact: false,
+            nested: Vec::new(),
+            ..runnable.clone()
+        };
+        let config = self.cargo_config("test");
+        let builder = CommandBuilder {
+            config: &config,
+            runnable: Some(&doc_tests),
+            shard: None,
+            ..self.clone()
+        };
+        builder.build().map(Some)
+    }
+
+    /// The [DebugPlan] of the runnable: the command this builder runs with `cargo` itself,
+    /// keeping the features, target, env and args of the config, built with `--no-run`
+    /// (`build` for binaries), and its test binary args for launching the binary directly.
+    pub fn debug_plan(&self) -> Result<DebugPlan, Error> {
+        let runnable = self
+            .runnable
+            .ok_or_else(|| Error::Other(anyhow!("No runnable to build")))?;
+        let sub_command = match runnable.context {
+            Context::Run => "run",
+            Context::Bench => "bench",
+            Context::Test | Context::Build | Context::None => "test",
+        };
+
+        let config = self.cargo_config(sub_command);
+        let run = CommandBuilder {
+            config: &config,
+            tool: None,
+            shard: None,
+            coverage: None,
+            ..self.clone()
+        };
+        let mut binary_args: Vec<String> = run
+            .build()?
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .skip_while(|arg| arg != "--")
+            .skip(1)
+            .collect();
+        if runnable.context == Context::Test && !binary_args.iter().any(|arg| arg == "--nocapture")
+        {
+            binary_args.push("--nocapture".to_string());
+        }
+
+        let config = match sub_command {
+            "run" => self.cargo_config("build"),
+            _ => config.clone(),
+        };
+        let build = CommandBuilder {
+            config: &config,
+            no_run: true,
+            ..run
+        }
+        .build()?;
+
+        Ok(DebugPlan {
+            build,
+            binary_args,
+            target: runnable.target.clone(),
+        })
+    }
+
+    /// The config running `cargo <sub_command>` with libtest, keeping its env and args,
+    /// e.g. `cargo test` for the doc tests nextest can't run.
+    fn cargo_config(&self, sub_command: &str) -> Config {
+        Config {
+            command_type: Some(CommandType::Cargo),
+            command: None,
+            sub_command: Some(sub_command.to_string()),
+            framework_test: Some(TestFramework::Libtest),
+            ..self.config.clone()
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub fn build(&self) -> Result<Command, Error> {
+        // nextest can't run doc tests, they fall back to `cargo test --doc`.
+        if let Some(runnable) = self.runnable.filter(|runnable| {
+            runnable.kind == RunnableKind::DocTest
+                && self.arg_strategy() == Some(ArgStrategy::Nextest)
+                && self.config.doctest_fallback != Some(false)
+        }) {
+            let config = self.cargo_config("test");
+            let builder = CommandBuilder {
+                config: &config,
+                runnable: Some(runnable),
+                ..self.clone()
+            };
+            return builder.build();
+        }
+
+        let gathered;
+        let context = match self.context {
+            Some(context) => context,
+            None => {
+                gathered = self.gather()?;
+                &gathered
+            }
+        };
+
+        let command_type = self.config.command_type.clone().unwrap_or_default();
+        let program = match command_type {
+            // `cross` takes the args of cargo, running it in a container for the target.
+            CommandType::Cargo if self.config.command.as_deref() == Some("cross") => "cross",
+            CommandType::Cargo | CommandType::SubCommand => "cargo",
+            CommandType::Shell => self.config.command.as_deref().unwrap_or("cargo"),
+        };
+
+        let framework = match command_type {
+            CommandType::Cargo => self.framework(),
+            _ => TestFramework::Libtest,
+        };
+        if framework == TestFramework::Miri
+            && self
+                .runnable
+                .is_some_and(|runnable| runnable.kind == RunnableKind::DocTest)
+        {
+            return Err(Error::Other(anyhow!("miri can't run doc tests")));
+        }
+        if self.coverage.is_some() {
+            if command_type != CommandType::Cargo {
+                return Err(Error::Other(anyhow!(
+                    "coverage needs a `cargo` config, `{}` runs another tool",
+                    self.config.name
+                )));
+            }
+            if framework == TestFramework::Miri {
+                return Err(Error::Other(anyhow!(
+                    "cargo-llvm-cov can't run tests under miri"
+                )));
+            }
+        }
+
+        let mut command = Command::new(program);
+
+        // Miri is only distributed for nightly.
+        let toolchain = self
+            .toolchain
+            .as_deref()
+            .or((framework == TestFramework::Miri).then_some("nightly"));
+        if let (Some(toolchain), "cargo" | "cross") = (toolchain, program) {
+            command.arg(format!("+{}", toolchain));
+        }
+
+        if command_type == CommandType::SubCommand {
+            if let Some(sub) = &self.config.command {
+                command.arg(sub);
+            }
+        }
+        if self.coverage.is_some() {
+            command.arg("llvm-cov");
+        }
+        match framework {
+            TestFramework::Nextest => command.arg("nextest"),
+            TestFramework::Miri => command.arg("miri"),
+            _ => &mut command,
+        };
+        let sub_command = match self.runnable.map(|runnable| runnable.kind) {
+            // `cargo llvm-cov nextest` takes no `run`.
+            _ if framework == TestFramework::Nextest && self.coverage.is_some() => None,
+            _ if framework == TestFramework::Nextest => Some("run"),
+            _ if framework == TestFramework::Miri => Some("test"),
+            Some(RunnableKind::Check) if command_type == CommandType::Cargo => Some("check"),
+            Some(RunnableKind::Clippy) if command_type == CommandType::Cargo => Some("clippy"),
+            _ => self.config.sub_command.as_deref(),
+        };
+        if let Some(sub_command) = sub_command {
+            command.arg(sub_command);
+        }
+        if self.no_run && matches!(sub_command, Some("test" | "bench")) {
+            command.arg("--no-run");
+        }
+
+        let mut strategy = self.arg_strategy();
+        // Cargo expands its own aliases, but args must be placed for the tool they run.
+        if let (Some(ArgStrategy::Libtest), Some(sub_command)) = (strategy, sub_command) {
+            if context
+                .cargo
+                .expand_alias(sub_command)
+                .is_some_and(|args| args.first().map(String::as_str) == Some("nextest"))
+            {
+                strategy = Some(ArgStrategy::Nextest);
+            }
+        }
+        let mut args = CommandArgs::default();
+        let overrides = self.overrides_matching(context.file_id.as_ref());
+
+        if let (Some(runnable), Some(_)) = (self.runnable, strategy) {
+            args.cargo = runnable.cargo_args();
+            args.cargo.extend(self.config.feature_args());
+            if let Some(target) = self.target_of(&overrides) {
+                args.cargo.push("--target".to_string());
+                args.cargo.push(target.to_string());
+            }
+            args.filters = match &self.filters {
+                Some(filters) => filters.clone(),
+                // The full path from the crate root with a trailing `::` keeps `math::tests`
+                // from matching `math::tests_io`. Nextest anchors it so it doesn't match
+                // `io::math::tests` either, libtest can't and runs those too.
+                None if runnable.kind == RunnableKind::ModuleTests => {
+                    args.prefix = true;
+                    runnable
+                        .filter
+                        .iter()
+                        .map(|module| format!("{}::", module))
+                        .collect()
+                }
+                None => runnable.filter.iter().cloned().collect(),
+            };
+            let include_nested = self
+                .config
+                .module_tests
+                .as_ref()
+                .and_then(|module_tests| module_tests.include_nested)
+                .unwrap_or(true);
+            if runnable.kind == RunnableKind::ModuleTests && !include_nested {
+                for nested in &runnable.nested {
+                    args.binary.push("--skip".to_string());
+                    args.binary.push(format!("{}::", nested));
+                }
+            }
+            args.exact = runnable.exact;
+            // rustdoc reports an `ignore` block as ignored unless asked to run it.
+            if runnable.doc_test == Some(DocTestMode::Ignore) {
+                args.binary.push("--include-ignored".to_string());
+            }
+            if runnable.kind == RunnableKind::Clippy {
+                args.binary
+                    .extend(self.config.lints.iter().flatten().cloned());
+            }
+            // Only libtest defaults are replaced, a configured tool knows its own args.
+            if strategy == Some(ArgStrategy::Libtest) {
+                if runnable
+                    .target
+                    .as_ref()
+                    .is_some_and(|target| !context.heuristics.harness(target))
+                {
+                    strategy = Some(ArgStrategy::NoHarness);
+                }
+                for hint in self.hints_in(context) {
+                    match hint {
+                        Hint::Strategy(hinted) => strategy = Some(hinted),
+                        Hint::CargoArg(arg) if !args.cargo.contains(&arg) => args.cargo.push(arg),
+                        Hint::CargoArg(_) => {}
+                        Hint::Inexact => args.exact = false,
+                    }
+                }
+            }
+        }
+
+        if let Some(report) = &self.coverage {
+            args.cargo.extend(report.args());
+            if self
+                .runnable
+                .is_some_and(|runnable| runnable.kind == RunnableKind::DocTest)
+            {
+                args.cargo.push("--doctests".to_string());
+            }
+        }
+
+        if let (Some(runnable), Some(strategy), Some(options)) =
+            (self.runnable, strategy, &self.config.test_options)
+        {
+            if runnable.context == Context::Test {
+                options.apply(&mut args, strategy);
+            }
+        }
+
+        if let (Some(shard), Some(ArgStrategy::Nextest)) = (self.shard, strategy) {
+            args.cargo.push("--partition".to_string());
+            args.cargo.push(shard.partition());
+        }
+
+        args.extend(self.args.iter().cloned());
+
+        for matched in &overrides {
+            args.extend(matched.args.iter().flatten().cloned());
+        }
+        // Directives in the source are the most specific, they come last.
+        let directives = self
+            .runnable
+            .map_or(&[][..], |runnable| &runnable.directives);
+        for directive in directives {
+            if let Directive::Args(directive_args) = directive {
+                args.extend(directive_args.iter().cloned());
+            }
+        }
+
+        // The build step of a debug plan passes nothing to the binary, it reports its artifacts.
+        if self.no_run {
+            args.filters.clear();
+            args.binary.clear();
+            args.cargo.push("--message-format=json".to_string());
+        }
+        command.args(args.to_args(strategy.unwrap_or_default()));
+
+        self.config.apply_env_from(&mut command, &context.env);
+        for matched in &overrides {
+            command.envs(matched.env.iter().flatten());
+        }
+        for directive in directives {
+            if let Directive::Env(key, value) = directive {
+                command.env(key, value);
+            }
+        }
+        if self.cold {
+            command.env("CARGO_INCREMENTAL", "0");
+            command.env("RUSTC_WRAPPER", "");
+        } else if let Some(wrapper) = &self.config.rustc_wrapper {
+            command.env("RUSTC_WRAPPER", wrapper);
+        }
+        // A RUSTFLAGS variable makes cargo ignore `build.rustflags`, keep them.
+        let rustflags = command
+            .get_envs()
+            .find(|(key, _)| *key == "RUSTFLAGS")
+            .and_then(|(_, value)| value)
+            .map(|value| value.to_string_lossy().to_string());
+        if let (Some(rustflags), false) = (rustflags, context.cargo.rustflags.is_empty()) {
+            let mut flags = context.cargo.rustflags.clone();
+            flags.push(rustflags);
+            command.env("RUSTFLAGS", flags.join(" "));
+        }
+
+        if let Some(dir) = self.working_dir_in(context)? {
+            command.current_dir(dir);
+        }
+
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_line;
+
+    #[test]
+    fn test_framework_test() {
+        let runnable = Runnable {
+            kind: RunnableKind::Test,
+            package: Some("demo".to_string()),
+            target: Some(TargetKind::Lib),
+            filter: Some("tests::it_works".to_string()),
+            exact: true,
+            ..Runnable::new(Context::Test)
+        };
+        let context = BuildContext::default();
+        let command = |framework: TestFramework, auto: bool| {
+            let config = Config {
+                name: "default".to_string(),
+                sub_command: Some("test".to_string()),
+                framework_test: Some(framework),
+                ..Default::default()
+            };
+            let command = CommandBuilder::new(&config)
+                .runnable(&runnable)
+                .context(&context)
+                .auto_framework(auto)
+                .build()
+                .unwrap();
+            command_line(&command)
+        };
+
+        assert_eq!(
+            command(TestFramework::Nextest, true),
+            "cargo nextest run -p demo --lib -E 'test(=tests::it_works)'"
+        );
+        assert_eq!(
+            command(TestFramework::Auto, false),
+            "cargo test -p demo --lib -- tests::it_works --exact"
+        );
+        // `auto` reads the context, whatever is installed on this machine.
+        assert_eq!(
+            command(TestFramework::Auto, true),
+            "cargo test -p demo --lib -- tests::it_works --exact"
+        );
+        let installed = BuildContext {
+            nextest_installed: true,
+            ..Default::default()
+        };
+        let auto = Config {
+            name: "default".to_string(),
+            sub_command: Some("test".to_string()),
+            framework_test: Some(TestFramework::Auto),
+            ..Default::default()
+        };
+        let nextest = CommandBuilder::new(&auto)
+            .runnable(&runnable)
+            .context(&installed)
+            .build()
+            .unwrap();
+        assert_eq!(
+            command_line(&nextest),
+            "cargo nextest run -p demo --lib -E 'test(=tests::it_works)'"
+        );
+        assert_eq!(
+            command(TestFramework::Miri, true),
+            "cargo +nightly miri test -p demo --lib -- tests::it_works --exact"
+        );
+    }
+
+    #[test]
+    fn test_feature_args() {
+        let runnable = Runnable {
+            kind: RunnableKind::DocTest,
+            package: Some("demo".to_string()),
+            target: Some(TargetKind::Lib),
+            ..Runnable::new(Context::Test)
+        };
+        let context = BuildContext {
+            heuristics: crate::Heuristics {
+                features: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let config = Config {
+            name: "default".to_string(),
+            sub_command: Some("test".to_string()),
+            features: Some(vec!["fast".to_string(), "serde".to_string()]),
+            all_features: Some(true),
+            no_default_features: Some(true),
+            ..Default::default()
+        };
+        let command = CommandBuilder::new(&config)
+            .runnable(&runnable)
+            .context(&context)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            command_line(&command),
+            "cargo test -p demo --doc --features fast,serde --all-features --no-default-features"
+        );
+    }
+
+    #[test]
+    fn test_cross_target() {
+        let runnable = Runnable {
+            kind: RunnableKind::Test,
+            package: Some("demo".to_string()),
+            target: Some(TargetKind::Lib),
+            filter: Some("tests::it_works".to_string()),
+            function: Some("tests::it_works".to_string()),
+            exact: true,
+            ..Runnable::new(Context::Test)
+        };
+        let context = BuildContext::default();
+        let config = Config {
+            name: "default".to_string(),
+            command_type: Some(CommandType::Cargo),
+            command: Some("cross".to_string()),
+            sub_command: Some("test".to_string()),
+            target: Some("aarch64-unknown-linux-musl".to_string()),
+            overrides: Some(vec![Override {
+                function: Some("it_works".to_string()),
+                target: Some("armv7-unknown-linux-gnueabihf".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        let command = |runnable: &Runnable, args: &[&str]| {
+            let command = CommandBuilder::new(&config)
+                .runnable(runnable)
+                .context(&context)
+                .toolchain("nightly")
+                .args(args.iter().copied())
+                .build()
+                .unwrap();
+            command_line(&command)
+        };
+
+        assert_eq!(
+            command(&runnable, &[]),
+            "cross +nightly test -p demo --lib --target armv7-unknown-linux-gnueabihf -- tests::it_works --exact"
+        );
+        let other = Runnable {
+            function: Some("tests::other".to_string()),
+            filter: Some("tests::other".to_string()),
+            ..runnable.clone()
+        };
+        assert_eq!(
+            command(&other, &[]),
+            "cross +nightly test -p demo --lib --target aarch64-unknown-linux-musl -- tests::other --exact"
+        );
+        assert_eq!(
+            command(&other, &["--target", "wasm32-wasip1"]),
+            "cross +nightly test -p demo --lib --target wasm32-wasip1 -- tests::other --exact"
+        );
+    }
+
+    #[test]
+    fn test_coverage() {
+        let runnable = Runnable {
+            kind: RunnableKind::Test,
+            package: Some("demo".to_string()),
+            target: Some(TargetKind::Lib),
+            filter: Some("tests::it_works".to_string()),
+            exact: true,
+            ..Runnable::new(Context::Test)
+        };
+        let context = BuildContext::default();
+        let command = |framework: TestFramework, report: CoverageReport| {
+            let config = Config {
+                name: "default".to_string(),
+                sub_command: Some("test".to_string()),
+                framework_test: Some(framework),
+                ..Default::default()
+            };
+            CommandBuilder::new(&config)
+                .runnable(&runnable)
+                .context(&context)
+                .coverage(report)
+                .build()
+                .map(|command| command_line(&command))
+        };
+
+        assert_eq!(
+            command(TestFramework::Libtest, CoverageReport::Summary).unwrap(),
+            "cargo llvm-cov test -p demo --lib -- tests::it_works --exact"
+        );
+        assert_eq!(
+            command(
+                TestFramework::Nextest,
+                CoverageReport::Lcov(PathBuf::from("lcov.info"))
+            )
+            .unwrap(),
+            "cargo llvm-cov nextest -p demo --lib --lcov --output-path lcov.info -E 'test(=tests::it_works)'"
+        );
+        assert!(command(TestFramework::Miri, CoverageReport::Summary).is_err());
+    }
+
+    #[test]
+    fn test_doc_tests_fallback() {
+        let config = Config {
+            name: "nextest".to_string(),
+            command_type: Some(CommandType::SubCommand),
+            command: Some("nextest".to_string()),
+            sub_command: Some("run".to_string()),
+            ..Default::default()
+        };
+        let module = Runnable {
+            kind: RunnableKind::ModuleTests,
+            package: Some("demo".to_string()),
+            target: Some(TargetKind::Lib),
+            filter: Some("math".to_string()),
+            ..Runnable::new(Context::Test)
+        };
+        let doc_test = Runnable {
+            kind: RunnableKind::DocTest,
+            filter: Some("math::add".to_string()),
+            ..module.clone()
+        };
+        let context = BuildContext::default();
+        let builder = |runnable| {
+            CommandBuilder::new(&config)
+                .runnable(runnable)
+                .context(&context)
+        };
+
+        assert_eq!(
+            command_line(&builder(&doc_test).build().unwrap()),
+            "cargo test -p demo --doc -- math::add"
+        );
+        let ignored = Runnable {
+            doc_test: Some(DocTestMode::Ignore),
+            ..doc_test.clone()
+        };
+        assert_eq!(
+            command_line(&builder(&ignored).build().unwrap()),
+            "cargo test -p demo --doc -- math::add --include-ignored"
+        );
+        let fallback = builder(&module).doc_tests_fallback().unwrap().unwrap();
+        assert_eq!(command_line(&fallback), "cargo test -p demo --doc -- math");
+        let disabled = Config {
+            doctest_fallback: Some(false),
+            ..config.clone()
+        };
+        assert!(CommandBuilder::new(&disabled)
+            .runnable(&module)
+            .context(&context)
+            .doc_tests_fallback()
+            .unwrap()
+            .is_none());
+    }
+}
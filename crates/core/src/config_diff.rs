@@ -0,0 +1,181 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::anyhow;
+
+use crate::{command_line, find_workspace_root, CargoRunner, Error};
+
+/// A difference between the commands two configs build for the same runnable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandChange {
+    /// The program and args, as [command_line] prints them.
+    Args { before: String, after: String },
+    /// An env var set by either command, `None` where it isn't set.
+    Env {
+        key: String,
+        before: Option<String>,
+        after: Option<String>,
+    },
+    WorkingDir {
+        before: Option<PathBuf>,
+        after: Option<PathBuf>,
+    },
+}
+
+impl Display for CommandChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_unset = |value: &Option<String>| value.clone().unwrap_or_else(|| "unset".into());
+        match self {
+            CommandChange::Args { before, after } => write!(f, "- {}\n+ {}", before, after),
+            CommandChange::Env { key, before, after } => {
+                write!(
+                    f,
+                    "env {}: {} -> {}",
+                    key,
+                    or_unset(before),
+                    or_unset(after)
+                )
+            }
+            CommandChange::WorkingDir { before, after } => {
+                let display = |dir: &Option<PathBuf>| dir.as_ref().map(|d| d.display().to_string());
+                write!(
+                    f,
+                    "working dir: {} -> {}",
+                    or_unset(&display(before)),
+                    or_unset(&display(after))
+                )
+            }
+        }
+    }
+}
+
+/// The changes from `before` to `after`, empty when both run the same way.
+pub fn diff_commands(before: &Command, after: &Command) -> Vec<CommandChange> {
+    let mut changes = Vec::new();
+
+    let (before_line, after_line) = (command_line(before), command_line(after));
+    if before_line != after_line {
+        changes.push(CommandChange::Args {
+            before: before_line,
+            after: after_line,
+        });
+    }
+
+    let envs = |command: &Command| -> BTreeMap<String, Option<String>> {
+        command
+            .get_envs()
+            .map(|(key, value)| {
+                (
+                    key.to_string_lossy().to_string(),
+                    value.map(|value| value.to_string_lossy().to_string()),
+                )
+            })
+            .collect()
+    };
+    let (before_env, after_env) = (envs(before), envs(after));
+    let keys: BTreeSet<_> = before_env.keys().chain(after_env.keys()).collect();
+    for key in keys {
+        let before = before_env.get(key).cloned().flatten();
+        let after = after_env.get(key).cloned().flatten();
+        if before != after {
+            changes.push(CommandChange::Env {
+                key: key.clone(),
+                before,
+                after,
+            });
+        }
+    }
+
+    let dir = |command: &Command| command.get_current_dir().map(Path::to_path_buf);
+    if dir(before) != dir(after) {
+        changes.push(CommandChange::WorkingDir {
+            before: dir(before),
+            after: dir(after),
+        });
+    }
+
+    changes
+}
+
+impl CargoRunner {
+    /// The config `file_path` had under `against`: the user config with either
+    /// the config file at `against` in place of the workspace layers, or the
+    /// workspace layers as they were at the git revision `against`. Layers missing
+    /// at the revision are skipped.
+    pub fn reference(against: &str, file_path: &Path) -> Result<CargoRunner, Error> {
+        let mut runner = CargoRunner::init()?;
+        if Path::new(against).is_file() {
+            runner.merge(toml::from_str(&fs::read_to_string(against)?)?)?;
+            return Ok(runner);
+        }
+
+        let root = find_workspace_root(file_path)
+            .ok_or_else(|| Error::CargoTomlNotFound(file_path.display().to_string()))?;
+        for layer in Self::local_config_layers(file_path) {
+            let relative = layer.strip_prefix(&root).unwrap_or(&layer);
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(&root)
+                .arg("show")
+                .arg(format!("{}:./{}", against, relative.display()))
+                .output()?;
+            if output.status.success() {
+                runner.merge(toml::from_str(&String::from_utf8_lossy(&output.stdout))?)?;
+            } else if !revision_exists(&root, against) {
+                return Err(Error::Other(anyhow!(
+                    "`{}` is neither a config file nor a git revision",
+                    against
+                )));
+            }
+        }
+
+        Ok(runner)
+    }
+}
+
+fn revision_exists(root: &Path, revision: &str) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{}^{{commit}}", revision))
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_commands() {
+        let mut before = Command::new("cargo");
+        before.args(["test", "--lib"]).env("RUST_LOG", "debug");
+        let mut after = Command::new("cargo");
+        after
+            .args(["nextest", "run", "--lib"])
+            .env("RUST_LOG", "debug")
+            .env("RUST_BACKTRACE", "1");
+
+        assert_eq!(
+            diff_commands(&before, &after),
+            vec![
+                CommandChange::Args {
+                    before: "cargo test --lib".to_string(),
+                    after: "cargo nextest run --lib".to_string(),
+                },
+                CommandChange::Env {
+                    key: "RUST_BACKTRACE".to_string(),
+                    before: None,
+                    after: Some("1".to_string()),
+                },
+            ]
+        );
+        assert!(diff_commands(&before, &before).is_empty());
+    }
+}
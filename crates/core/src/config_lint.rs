@@ -0,0 +1,221 @@
+use std::fmt::{self, Display};
+
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+use crate::{migrate::CONFIG_KEYS, Error};
+
+/// Keys of a config entry that were renamed, with their current name.
+pub(crate) const RENAMED_KEYS: &[(&str, &str)] = &[
+    ("allowed_subconfig", "allowed_subcommands"),
+    ("test_framework", "framework_test"),
+];
+
+/// A key of a config file that does nothing or no longer parses, for `config lint`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigLint {
+    /// Dotted key e.g. `run.leptos.allowed_subconfig`, configs being keyed by their name.
+    pub key: String,
+    pub message: String,
+    /// Whether `config fix` rewrites the key.
+    pub fixable: bool,
+}
+
+impl Display for ConfigLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`: {}", self.key, self.message)
+    }
+}
+
+/// The lints of `content`, the text of a config file.
+pub fn lint_config(content: &str) -> Result<Vec<ConfigLint>, Error> {
+    let mut document: DocumentMut = content.parse().map_err(anyhow::Error::from)?;
+    let mut lints = Vec::new();
+    check_runner(document.as_table_mut(), "", false, &mut lints);
+    Ok(lints)
+}
+
+/// Rewrites the fixable lints of `content`, keeping its comments and layout. Returns
+/// the new text with every lint found, fixed or not.
+pub fn fix_config(content: &str) -> Result<(String, Vec<ConfigLint>), Error> {
+    let mut document: DocumentMut = content.parse().map_err(anyhow::Error::from)?;
+    let mut lints = Vec::new();
+    check_runner(document.as_table_mut(), "", true, &mut lints);
+    Ok((document.to_string(), lints))
+}
+
+fn check_runner(runner: &mut dyn TableLike, prefix: &str, fix: bool, lints: &mut Vec<ConfigLint>) {
+    let contexts: Vec<String> = runner.iter().map(|(key, _)| key.to_string()).collect();
+    for context in contexts {
+        let Some(entry) = runner.get_mut(&context).and_then(Item::as_table_like_mut) else {
+            continue;
+        };
+        if prefix.is_empty() && context == "target" {
+            let specs: Vec<String> = entry.iter().map(|(key, _)| key.to_string()).collect();
+            for spec in specs {
+                if let Some(section) = entry.get_mut(&spec).and_then(Item::as_table_like_mut) {
+                    check_runner(section, &format!("target.{}.", spec), fix, lints);
+                }
+            }
+            continue;
+        }
+        check_context(entry, &format!("{}{}", prefix, context), fix, lints);
+    }
+}
+
+fn check_context(entry: &mut dyn TableLike, context: &str, fix: bool, lints: &mut Vec<ConfigLint>) {
+    let keys: Vec<String> = entry.iter().map(|(key, _)| key.to_string()).collect();
+    for key in keys {
+        match key.as_str() {
+            "default" => {}
+            "config" | "commands" => {
+                let renamed = key == "commands";
+                let fixable = renamed && !entry.contains_key("config");
+                if renamed {
+                    lints.push(ConfigLint {
+                        key: format!("{}.commands", context),
+                        message: "renamed to `config`".to_string(),
+                        fixable,
+                    });
+                }
+                if let Some(configs) = entry.get_mut(&key) {
+                    for config in config_entries(configs) {
+                        check_config(config, context, fix, lints);
+                    }
+                }
+                if fix && fixable {
+                    if let Some(configs) = entry.remove("commands") {
+                        entry.insert("config", configs);
+                    }
+                }
+            }
+            _ => lints.push(ConfigLint {
+                key: format!("{}.{}", context, key),
+                message: "unknown key, ignored".to_string(),
+                fixable: false,
+            }),
+        }
+    }
+}
+
+fn check_config(config: &mut dyn TableLike, context: &str, fix: bool, lints: &mut Vec<ConfigLint>) {
+    let name = config
+        .get("name")
+        .and_then(Item::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let keys: Vec<String> = config.iter().map(|(key, _)| key.to_string()).collect();
+    for key in keys {
+        let lint = |message: String, fixable| ConfigLint {
+            key: format!("{}.{}.{}", context, name, key),
+            message,
+            fixable,
+        };
+        if let Some((_, renamed)) = RENAMED_KEYS.iter().find(|(old, _)| *old == key) {
+            let fixable = !config.contains_key(renamed);
+            lints.push(lint(format!("renamed to `{}`", renamed), fixable));
+            if fix && fixable {
+                if let Some(value) = config.remove(&key) {
+                    config.insert(renamed, value);
+                }
+            }
+        } else if key == "command_type" {
+            let Some(value) = config.get_mut(&key) else {
+                continue;
+            };
+            if value.as_str() == Some("sub_command") {
+                lints.push(lint("`sub_command` is now `subcommand`".to_string(), true));
+                if fix {
+                    let decor = value.as_value().map(|value| value.decor().clone());
+                    let mut subcommand = Value::from("subcommand");
+                    if let Some(decor) = decor {
+                        *subcommand.decor_mut() = decor;
+                    }
+                    *value = Item::Value(subcommand);
+                }
+            }
+        } else if !CONFIG_KEYS.contains(&key.as_str()) {
+            lints.push(lint("unknown key, ignored".to_string(), false));
+        }
+    }
+}
+
+/// The configs of a context, written as `[[test.config]]` or `config = [{ .. }]`.
+fn config_entries(item: &mut Item) -> Vec<&mut dyn TableLike> {
+    match item {
+        Item::ArrayOfTables(tables) => tables
+            .iter_mut()
+            .map(|table| table as &mut dyn TableLike)
+            .collect(),
+        Item::Value(Value::Array(array)) => array
+            .iter_mut()
+            .filter_map(|value| value.as_inline_table_mut())
+            .map(|table| table as &mut dyn TableLike)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CargoRunner, CommandType, Context};
+
+    #[test]
+    fn test_fix_config() {
+        let content = r#"# Team config
+[run]
+default = "leptos"
+
+[[run.commands]]
+name = "leptos"
+command_type = "sub_command" # watch mode
+command = "leptos"
+allowed_subconfig = ["serve"]
+color = true
+
+[test]
+config = [{ name = "default", test_framework = "nextest" }]
+
+[[target.'cfg(unix)'.bench.config]]
+name = "default"
+allowed_subconfig = ["x"]
+"#;
+
+        let lints: Vec<String> = lint_config(content)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            lints,
+            vec![
+                "`run.commands`: renamed to `config`",
+                "`run.leptos.command_type`: `sub_command` is now `subcommand`",
+                "`run.leptos.allowed_subconfig`: renamed to `allowed_subcommands`",
+                "`run.leptos.color`: unknown key, ignored",
+                "`test.default.test_framework`: renamed to `framework_test`",
+                "`target.cfg(unix).bench.default.allowed_subconfig`: renamed to `allowed_subcommands`",
+            ]
+        );
+
+        let (fixed, _) = fix_config(content).unwrap();
+        assert!(fixed.starts_with("# Team config\n"));
+        assert!(fixed.contains("command_type = \"subcommand\" # watch mode\n"));
+        let remaining: Vec<_> = lint_config(&fixed)
+            .unwrap()
+            .into_iter()
+            .map(|lint| lint.key)
+            .collect();
+        assert_eq!(remaining, vec!["run.leptos.color"]);
+
+        let runner = CargoRunner::try_from(fixed.as_str()).unwrap();
+        let leptos = runner.find(Context::Run, "leptos").unwrap();
+        assert_eq!(leptos.command_type, Some(CommandType::SubCommand));
+        assert_eq!(leptos.allowed_subcommands, Some(vec!["serve".to_string()]));
+        assert!(runner
+            .find(Context::Test, "default")
+            .unwrap()
+            .framework_test
+            .is_some());
+    }
+}
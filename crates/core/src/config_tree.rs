@@ -0,0 +1,138 @@
+use std::{fs, path::PathBuf};
+
+use toml::{Table, Value};
+
+use crate::Error;
+
+/// Keys whose values accumulate across layers instead of the last layer winning,
+/// see [Config::merge](crate::Config::merge).
+const APPENDED_KEYS: &[&str] = &["env_allowlist", "overrides"];
+
+/// A config file with the keys it defines, for `config tree`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigLayer {
+    pub path: PathBuf,
+    pub keys: Vec<LayerKey>,
+}
+
+/// A dotted key of a layer e.g. `test.config.default.env.RUST_LOG`, configs being
+/// keyed by their name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerKey {
+    pub key: String,
+    pub value: String,
+    /// The later layer replacing the value, `None` when this value is in effect.
+    pub overridden_by: Option<PathBuf>,
+}
+
+impl ConfigLayer {
+    /// Reads `paths`, lowest precedence first, marking the keys later layers override.
+    /// Missing files are skipped.
+    pub fn read_all(paths: &[PathBuf]) -> Result<Vec<ConfigLayer>, Error> {
+        let mut layers = Vec::new();
+        for path in paths.iter().filter(|path| path.is_file()) {
+            let table: Table = toml::from_str(&fs::read_to_string(path)?)?;
+            let mut keys = Vec::new();
+            flatten(&mut keys, String::new(), &table);
+            layers.push(ConfigLayer {
+                path: path.clone(),
+                keys,
+            });
+        }
+
+        for index in 0..layers.len() {
+            let (earlier, later) = layers.split_at_mut(index + 1);
+            for key in &mut earlier[index].keys {
+                let appended = key
+                    .key
+                    .rsplit('.')
+                    .next()
+                    .is_some_and(|last| APPENDED_KEYS.contains(&last));
+                if appended {
+                    continue;
+                }
+                key.overridden_by = later
+                    .iter()
+                    .rev()
+                    .find(|layer| layer.keys.iter().any(|other| other.key == key.key))
+                    .map(|layer| layer.path.clone());
+            }
+        }
+
+        Ok(layers)
+    }
+}
+
+fn flatten(keys: &mut Vec<LayerKey>, prefix: String, table: &Table) {
+    for (name, value) in table {
+        let key = match prefix.is_empty() {
+            true => name.clone(),
+            false => format!("{}.{}", prefix, name),
+        };
+        match value {
+            Value::Table(table) => flatten(keys, key, table),
+            // `[[test.config]]` entries are keyed by their name.
+            Value::Array(configs) if name == "config" => {
+                for config in configs.iter().filter_map(Value::as_table) {
+                    let config_name = config
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+                    let mut fields = config.clone();
+                    fields.remove("name");
+                    flatten(keys, format!("{}.{}", key, config_name), &fields);
+                }
+            }
+            value => keys.push(LayerKey {
+                key,
+                value: value.to_string(),
+                overridden_by: None,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = dir.path().join("user.toml");
+        let team = dir.path().join("team.toml");
+        fs::write(
+            &user,
+            "[test]\ndefault = \"default\"\n\n[[test.config]]\nname = \"default\"\ncommand = \"cargo\"\nenv = { RUST_LOG = \"info\", RUST_BACKTRACE = \"1\" }\nenv_allowlist = [\"HOME\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            &team,
+            "[[test.config]]\nname = \"default\"\nenv = { RUST_LOG = \"debug\" }\nenv_allowlist = [\"CI\"]\n",
+        )
+        .unwrap();
+
+        let layers =
+            ConfigLayer::read_all(&[user.clone(), dir.path().join("missing.toml"), team.clone()])
+                .unwrap();
+        assert_eq!(layers.len(), 2);
+
+        let keys: Vec<_> = layers[0]
+            .keys
+            .iter()
+            .map(|key| (key.key.as_str(), key.overridden_by.is_some()))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("test.config.default.command", false),
+                ("test.config.default.env.RUST_BACKTRACE", false),
+                ("test.config.default.env.RUST_LOG", true),
+                ("test.config.default.env_allowlist", false),
+                ("test.default", false),
+            ]
+        );
+        assert_eq!(layers[0].keys[2].overridden_by, Some(team));
+        assert_eq!(layers[1].keys[0].value, "\"debug\"");
+    }
+}
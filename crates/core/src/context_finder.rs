@@ -0,0 +1,1224 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    analyze::walk_rust_files,
+    find_claiming_member, find_package_name, find_package_root, find_workspace_root,
+    is_virtual_manifest, read_source,
+    scanner::{inclusions, scan_within},
+    workspace::declared_target,
+    workspace_members, Anchor, Context, CursorStrategy, Error, NormalizedSource, Runnable,
+    RunnableKind, Scope, ScopeCache, ScopeKind, TargetKind, WorkspaceMetadata,
+};
+
+/// A runnable of a file with the scope it was found at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FoundRunnable {
+    pub scope: Scope,
+    pub runnable: Runnable,
+}
+
+/// The runnables of a file, `partial` when detection ran out of its [DetectionBudget].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    pub runnables: Vec<FoundRunnable>,
+    pub partial: bool,
+}
+
+/// Limits keeping detection in huge files, e.g. generated code, from blocking an editor.
+/// Unlimited by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DetectionBudget {
+    /// Files larger than this only get the items outside function, impl and trait bodies.
+    pub max_bytes: Option<usize>,
+    /// Scanning stops after this long, keeping the scopes found so far.
+    pub max_time: Option<Duration>,
+}
+
+/// Why a scope near a line did not become the runnable found there.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rejection {
+    pub scope: Scope,
+    pub reason: String,
+}
+
+/// The order [ContextFinder::find] tries the kinds of runnables at a line in, taking
+/// the innermost scope of the first kind found there. The file is the last resort.
+pub const DEFAULT_SCOPE_ORDER: &[RunnableKind] = &[
+    RunnableKind::DocTest,
+    RunnableKind::Test,
+    RunnableKind::Bench,
+    RunnableKind::Binary,
+    RunnableKind::ModuleTests,
+];
+
+/// Finds the [Runnable] for a location in a file using its path, its `Cargo.toml`
+/// and the scopes around the given line.
+#[derive(Debug, Clone)]
+pub struct ContextFinder {
+    path: PathBuf,
+    source: Option<String>,
+    budget: DetectionBudget,
+    cursor_strategy: CursorStrategy,
+    scope_order: Vec<RunnableKind>,
+    disabled: Vec<RunnableKind>,
+    scope_cache: Option<ScopeCache>,
+    scanned: Option<Vec<Scope>>,
+}
+
+impl ContextFinder {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        ContextFinder {
+            path: path.into(),
+            source: None,
+            budget: DetectionBudget::default(),
+            cursor_strategy: CursorStrategy::default(),
+            scope_order: Vec::new(),
+            disabled: Vec::new(),
+            scope_cache: None,
+            scanned: None,
+        }
+    }
+
+    /// Uses `source` instead of reading the file from disk, e.g. an unsaved editor buffer.
+    /// The path is still used to resolve the package and target.
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn budget(mut self, budget: DetectionBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn cursor_strategy(mut self, strategy: CursorStrategy) -> Self {
+        self.cursor_strategy = strategy;
+        self
+    }
+
+    /// Reuses the scopes `cache` holds for the source and caches them after a scan.
+    pub fn scope_cache(mut self, cache: ScopeCache) -> Self {
+        self.scope_cache = Some(cache);
+        self
+    }
+
+    /// Tries the kinds of runnables at a line in `order`, then the others in
+    /// [DEFAULT_SCOPE_ORDER], e.g. `[ModuleTests]` picks the tests module around a test
+    /// rather than the test.
+    pub fn scope_order(mut self, order: &[RunnableKind]) -> Self {
+        self.scope_order = order.to_vec();
+        self
+    }
+
+    /// Uses the `scopes` of the source scanned already, e.g. by a [crate::ParsedSource],
+    /// instead of scanning it again.
+    pub fn scanned(mut self, scopes: Vec<Scope>) -> Self {
+        self.scanned = Some(scopes);
+        self
+    }
+
+    /// Leaves runnables of the `disabled` kinds out of [ContextFinder::runnables].
+    pub fn disabled(mut self, disabled: &[RunnableKind]) -> Self {
+        self.disabled = disabled.to_vec();
+        self
+    }
+
+    pub fn scopes(&self) -> Result<Vec<Scope>, Error> {
+        Ok(self.scan()?.0)
+    }
+
+    fn scan(&self) -> Result<(Vec<Scope>, bool), Error> {
+        if let Some(scopes) = &self.scanned {
+            return Ok((scopes.clone(), false));
+        }
+        let bytes;
+        let source = match &self.source {
+            Some(source) => NormalizedSource::new(source),
+            None => {
+                bytes = fs::read(&self.path)?;
+                NormalizedSource::from_bytes(&bytes)
+            }
+        };
+        let cache = self.scope_cache.as_ref();
+        if let Some(scopes) = cache.and_then(|cache| cache.get(&self.path, source.text())) {
+            return Ok((scopes, false));
+        }
+        let (scopes, partial) = scan_within(&source, &self.budget);
+        if let (Some(cache), false) = (cache, partial) {
+            if let Err(e) = cache.insert(&self.path, source.text(), &scopes) {
+                debug!(
+                    "could not cache the scopes of {}: {}",
+                    self.path.display(),
+                    e
+                );
+            }
+        }
+        Ok((scopes, partial))
+    }
+
+    /// Re-binds `anchor` against the current source, returning the line it moved to.
+    pub fn resolve_anchor(&self, anchor: &Anchor) -> Result<Option<u32>, Error> {
+        let scopes = self.scopes()?;
+        Ok(anchor.resolve(&scopes).map(|scope| scope.start_line as u32))
+    }
+
+    /// Runnables for the whole package of the file: build, check and clippy.
+    pub fn package_runnables(&self) -> Vec<Runnable> {
+        let package = find_package_root(&self.path)
+            .as_deref()
+            .and_then(find_package_name);
+
+        [
+            RunnableKind::Build,
+            RunnableKind::Check,
+            RunnableKind::Clippy,
+        ]
+        .into_iter()
+        .map(|kind| Runnable {
+            kind,
+            package: package.clone(),
+            ..Runnable::new(Context::Build)
+        })
+        .collect()
+    }
+
+    /// The runnable at `line`, or the whole file without one. A file at the root of a
+    /// virtual workspace that no member claims is an error listing the members, rather
+    /// than a command without `-p` running the whole workspace.
+    pub fn find(&self, line: Option<u32>) -> Result<Runnable, Error> {
+        let scopes = self.scopes()?;
+        let location = self.locate();
+        if location.package.is_none() {
+            if let Some(root) =
+                find_workspace_root(&self.path).filter(|root| is_virtual_manifest(root))
+            {
+                let members: Vec<String> = workspace_members(&root)
+                    .iter()
+                    .filter_map(|member| find_package_name(member))
+                    .collect();
+                return Err(Error::NoMemberPackage(
+                    self.path.display().to_string(),
+                    members.join(", "),
+                ));
+            }
+        }
+        let line = line.map(|line| self.cursor_line(&location, &scopes, line as usize) as u32);
+        Ok(self.find_in(&location, &scopes, line))
+    }
+
+    /// The line the [CursorStrategy] resolves `line` to: unchanged inside a test, bench,
+    /// `main` or doc test, counting its doc comment, else the start of the closest one.
+    fn cursor_line(&self, location: &Location, scopes: &[Scope], line: usize) -> usize {
+        let candidates: Vec<&Scope> = scopes
+            .iter()
+            .filter(|scope| match scope.kind {
+                ScopeKind::Function => scope.is_test() || scope.is_bench() || is_main(scope),
+                ScopeKind::DocTest => location.target == Some(TargetKind::Lib),
+                _ => false,
+            })
+            .collect();
+        if self.cursor_strategy == CursorStrategy::Enclosing {
+            return line;
+        }
+        if let Some(scope) = candidates
+            .iter()
+            .find(|scope| scope.lens_line() <= line && line <= scope.end_line)
+        {
+            return line.max(scope.start_line);
+        }
+
+        let above = candidates
+            .iter()
+            .filter(|scope| scope.end_line < line)
+            .max_by_key(|scope| scope.end_line);
+        let below = candidates
+            .iter()
+            .filter(|scope| scope.lens_line() > line)
+            .min_by_key(|scope| scope.lens_line());
+        let nearest = match (self.cursor_strategy, above, below) {
+            (CursorStrategy::NearestAbove, above, _) => above,
+            (_, Some(above), Some(below)) => {
+                match line - above.end_line <= below.lens_line() - line {
+                    true => Some(above),
+                    false => Some(below),
+                }
+            }
+            (_, above, below) => above.or(below),
+        };
+        nearest.map_or(line, |scope| scope.start_line)
+    }
+
+    /// The scopes around `line` that did not give a runnable, with the reason each was
+    /// passed over: the scopes containing the line, innermost first, then the closest
+    /// items above and below it.
+    pub fn explain(&self, line: u32) -> Result<Vec<Rejection>, Error> {
+        let scopes = self.scopes()?;
+        let location = self.locate();
+        let line = line as usize;
+        let mut rejections = Vec::new();
+
+        if location.target.is_none() {
+            rejections.push(Rejection {
+                scope: scopes[0].clone(),
+                reason: "no cargo target matches the path of the file".to_string(),
+            });
+        }
+
+        let mut containing: Vec<&Scope> = scopes
+            .iter()
+            .filter(|scope| scope.kind != ScopeKind::File && scope.contains(line))
+            .collect();
+        containing.sort_by_key(|scope| std::cmp::Reverse(scope.start_line));
+        let above = scopes
+            .iter()
+            .filter(|scope| scope.kind != ScopeKind::File && scope.end_line < line)
+            .max_by_key(|scope| scope.end_line);
+        let below = scopes
+            .iter()
+            .filter(|scope| scope.kind != ScopeKind::File && scope.start_line > line)
+            .min_by_key(|scope| scope.start_line);
+
+        for scope in containing {
+            if let Some(reason) = rejection_reason(&location, &scopes, scope) {
+                rejections.push(Rejection {
+                    scope: scope.clone(),
+                    reason,
+                });
+            }
+        }
+        for scope in above.into_iter().chain(below) {
+            rejections.push(Rejection {
+                scope: scope.clone(),
+                reason: format!(
+                    "line {} is outside it (lines {}-{})",
+                    line, scope.start_line, scope.end_line
+                ),
+            });
+        }
+
+        Ok(rejections)
+    }
+
+    /// Every runnable of the file: the file itself, then one per test, bench,
+    /// `main`, module containing tests and doc test of a library, one per command.
+    pub fn runnables(&self) -> Result<Vec<FoundRunnable>, Error> {
+        Ok(self.detect()?.runnables)
+    }
+
+    /// Like [ContextFinder::runnables], flagging results cut short by the budget.
+    #[tracing::instrument(skip_all)]
+    pub fn detect(&self) -> Result<Detection, Error> {
+        let (scopes, partial) = self.scan()?;
+        let location = self.locate();
+        let mut found: Vec<FoundRunnable> = Vec::new();
+
+        for scope in &scopes {
+            let line = match scope.kind {
+                ScopeKind::File => None,
+                ScopeKind::Module if contains_tests(&scopes, scope) => Some(scope.start_line),
+                ScopeKind::Function if scope.is_test() || scope.is_bench() || is_main(scope) => {
+                    Some(scope.start_line)
+                }
+                ScopeKind::DocTest if location.target == Some(TargetKind::Lib) => {
+                    Some(scope.start_line)
+                }
+                _ => continue,
+            };
+            let runnable = self.find_in(&location, &scopes, line.map(|line| line as u32));
+            if self.disabled.contains(&runnable.kind) {
+                continue;
+            }
+            let found_runnable = FoundRunnable {
+                scope: scope.clone(),
+                runnable,
+            };
+            // Scopes come outermost first, so a later one running the same command is
+            // the more specific place for it e.g. `fn main` over its binary's file.
+            match found
+                .iter_mut()
+                .find(|f| f.runnable.same_command(&found_runnable.runnable))
+            {
+                Some(existing) => *existing = found_runnable,
+                None => found.push(found_runnable),
+            }
+        }
+
+        Ok(Detection {
+            runnables: found,
+            partial,
+        })
+    }
+
+    /// The package, target and module of the file. A file pulled in by `include!` or
+    /// `#[path]` takes them from the file including it rather than from its own location.
+    fn locate(&self) -> Location {
+        let package_root = find_package_root(&self.path);
+        let package = package_root.as_deref().and_then(find_package_name);
+        let Some(root) = package_root else {
+            // A file outside any package may still be a target of a virtual workspace member.
+            if let Some((package, target)) = find_workspace_root(&self.path)
+                .and_then(|root| find_claiming_member(&root, &self.path))
+            {
+                return Location {
+                    package: Some(package),
+                    target: Some(target),
+                    file_module: Vec::new(),
+                    relative_path: self.path.to_string_lossy().to_string(),
+                    required_features: Vec::new(),
+                };
+            }
+            return Location {
+                package,
+                target: None,
+                file_module: Vec::new(),
+                relative_path: String::new(),
+                required_features: Vec::new(),
+            };
+        };
+
+        let (path, module_path) = match including_file(&root, &self.path) {
+            Some((includer, module_path)) => (includer, module_path),
+            None => (self.path.clone(), Vec::new()),
+        };
+        // The package root of a relative path can be empty, the current directory.
+        let root_dir = match root.as_os_str().is_empty() {
+            true => Path::new("."),
+            false => root.as_path(),
+        };
+        // Targets as cargo resolves them, when `cargo metadata` can read the workspace.
+        let metadata = find_workspace_root(root_dir).and_then(|workspace_root| {
+            WorkspaceMetadata::load(&workspace_root)
+                .map_err(|e| debug!("falling back to the standard layout: {}", e))
+                .ok()
+        });
+        // A target root declared in the manifest, e.g. the `#![no_main]` entry of firmware
+        // at a custom path, wins over the standard layout and is the root of its crate.
+        let declared = match &metadata {
+            Some(metadata) => metadata
+                .target_of(&path)
+                .and_then(|(_, target)| target.target_kind()),
+            None => declared_target(&root, &path),
+        };
+        // A module of a target rooted outside the standard layout is named after it.
+        let enclosing = metadata
+            .as_ref()
+            .zip(package.as_deref())
+            .and_then(|(metadata, package)| metadata.enclosing_target(package, &path))
+            .filter(|target| {
+                !target
+                    .src_path
+                    .parent()
+                    .is_some_and(|dir| is_standard_dir(root_dir, dir))
+            });
+        let target = declared
+            .clone()
+            .or_else(|| enclosing.and_then(|target| target.target_kind()))
+            .or_else(|| target_kind(&root, &path, package.as_deref()));
+        let mut file_module = match (&target, enclosing) {
+            _ if declared.is_some() => Vec::new(),
+            (_, Some(enclosing)) => enclosing
+                .src_path
+                .parent()
+                .map(|dir| module_path_within(dir, &path))
+                .unwrap_or_default(),
+            (Some(TargetKind::Lib | TargetKind::Bin(_)), None) => file_module_path(&root, &path),
+            _ => Vec::new(),
+        };
+        file_module.extend(module_path);
+        let relative_path = relative_components(&root, &self.path)
+            .unwrap_or_default()
+            .join("/");
+
+        let required_features = metadata
+            .as_ref()
+            .zip(package.as_deref().zip(target.as_ref()))
+            .and_then(|(metadata, (package, target))| metadata.target_named(package, target))
+            .map(|target| target.required_features.clone())
+            .unwrap_or_default();
+
+        Location {
+            package,
+            target,
+            file_module,
+            relative_path,
+            required_features,
+        }
+    }
+
+    fn find_in(&self, location: &Location, scopes: &[Scope], line: Option<u32>) -> Runnable {
+        let line = line.map(|line| line as usize);
+        let file_module = &location.file_module;
+
+        let mut runnable = Runnable {
+            package: location.package.clone(),
+            target: location.target.clone(),
+            required_features: location.required_features.clone(),
+            ..Runnable::new(Context::Build)
+        };
+
+        let innermost = |predicate: &dyn Fn(&Scope) -> bool| {
+            scopes
+                .iter()
+                .filter(|scope| line.is_some_and(|line| scope.contains(line)) && predicate(scope))
+                .max_by_key(|scope| scope.start_line)
+        };
+
+        let order = self.scope_order.iter().chain(
+            DEFAULT_SCOPE_ORDER
+                .iter()
+                .filter(|kind| !self.scope_order.contains(kind)),
+        );
+        for kind in order {
+            match kind {
+                RunnableKind::DocTest => {
+                    let doc_test = innermost(&|scope| scope.kind == ScopeKind::DocTest)
+                        .filter(|_| runnable.target == Some(TargetKind::Lib));
+                    if let Some(doc_test) = doc_test {
+                        // rustdoc names doc tests `src/lib.rs - math::add (line 10)` but splits
+                        // its test args on whitespace, so the filter is the part without spaces.
+                        let item = join_path(file_module, &doc_test.path());
+                        runnable.context = Context::Test;
+                        runnable.kind = RunnableKind::DocTest;
+                        runnable.filter = match item.as_str() {
+                            "" => Some(location.relative_path.clone()),
+                            item => Some(item.to_string()),
+                        };
+                        runnable.function = Some(item).filter(|item| !item.is_empty());
+                        runnable.doc_test = doc_test.doc_test_mode();
+                        return runnable;
+                    }
+                }
+                RunnableKind::Test => {
+                    if let Some(test) = innermost(&|scope| scope.is_test()) {
+                        let function = join_path(file_module, &test.path());
+                        runnable.context = Context::Test;
+                        runnable.kind = RunnableKind::Test;
+                        runnable.filter = Some(function.clone());
+                        runnable.function = Some(function);
+                        runnable.exact = true;
+                        runnable.directives = test.extended.directives.clone();
+                        return runnable;
+                    }
+                }
+                RunnableKind::Bench => {
+                    if let Some(bench) = innermost(&|scope| scope.is_bench()) {
+                        let function = join_path(file_module, &bench.path());
+                        runnable.context = Context::Bench;
+                        runnable.kind = RunnableKind::Bench;
+                        // Criterion matches its filter against benchmark ids, not function paths.
+                        runnable.filter =
+                            Some(bench.extended.bench_id.clone().unwrap_or(function.clone()));
+                        runnable.exact = bench.extended.bench_id.is_none();
+                        runnable.function = Some(function);
+                        runnable.directives = bench.extended.directives.clone();
+                        return runnable;
+                    }
+                }
+                RunnableKind::Binary => {
+                    let main = innermost(&|scope| is_main(scope)).filter(|_| {
+                        matches!(
+                            runnable.target,
+                            Some(TargetKind::Bin(_) | TargetKind::Example(_))
+                        )
+                    });
+                    if let Some(main) = main {
+                        runnable.context = Context::Run;
+                        runnable.kind = RunnableKind::Binary;
+                        runnable.function = Some("main".to_string());
+                        runnable.directives = main.extended.directives.clone();
+                        return runnable;
+                    }
+                }
+                RunnableKind::ModuleTests => {
+                    if let Some(module) = innermost(&|scope| {
+                        scope.kind == ScopeKind::Module && contains_tests(scopes, scope)
+                    }) {
+                        runnable.context = Context::Test;
+                        runnable.kind = RunnableKind::ModuleTests;
+                        runnable.filter = Some(join_path(file_module, &module.path()));
+                        runnable.nested = nested_test_modules(scopes, file_module, &module.path());
+                        runnable.directives = module.extended.directives.clone();
+                        return runnable;
+                    }
+                }
+                RunnableKind::Build | RunnableKind::Check | RunnableKind::Clippy => {}
+            }
+        }
+
+        runnable.context = match runnable.target {
+            Some(TargetKind::Bin(_) | TargetKind::Example(_)) => Context::Run,
+            Some(TargetKind::Test(_)) => Context::Test,
+            Some(TargetKind::Bench(_)) => Context::Bench,
+            Some(TargetKind::Lib) | None => {
+                if scopes.iter().any(Scope::is_test) {
+                    Context::Test
+                } else {
+                    Context::Build
+                }
+            }
+        };
+        runnable.kind = Runnable::new(runnable.context).kind;
+        if runnable.context == Context::Test && !file_module.is_empty() {
+            runnable.filter = Some(file_module.join("::"));
+            runnable.nested = nested_test_modules(scopes, file_module, "");
+        }
+
+        runnable
+    }
+}
+
+/// Where a file sits in its package.
+struct Location {
+    package: Option<String>,
+    target: Option<TargetKind>,
+    file_module: Vec<String>,
+    /// The path of the file from the package root, which rustdoc puts in doc test names.
+    relative_path: String,
+    required_features: Vec<String>,
+}
+
+/// The file of the package that pulls `path` in through `#[path = "..."] mod name;` or
+/// `include!("...")`, with the module the included items land in relative to it.
+fn including_file(package_root: &Path, path: &Path) -> Option<(PathBuf, Vec<String>)> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, Arc<Inclusions>>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+
+    let path = path.canonicalize().ok()?;
+    let cached = cache
+        .lock()
+        .expect("inclusions lock")
+        .get(package_root)
+        .cloned()
+        .filter(|cached| cached.is_current());
+    let inclusions = match cached {
+        Some(cached) => cached,
+        None => {
+            let walked = Arc::new(Inclusions::walk(package_root).ok()?);
+            cache
+                .lock()
+                .expect("inclusions lock")
+                .insert(package_root.to_path_buf(), walked.clone());
+            walked
+        }
+    };
+    inclusions.included.get(&path).cloned()
+}
+
+/// The files of a package other files include, walked once and kept until one of the
+/// walked files or directories changes.
+struct Inclusions {
+    /// The Rust files of the package and the directories holding them, with their
+    /// modification times.
+    stamps: Vec<(PathBuf, Option<SystemTime>)>,
+    /// The canonical path of each included file, with the file including it first and
+    /// the module the included items land in.
+    included: HashMap<PathBuf, (PathBuf, Vec<String>)>,
+}
+
+impl Inclusions {
+    fn walk(package_root: &Path) -> Result<Self, Error> {
+        let mut files = walk_rust_files(package_root, &[], Ok)?;
+        files.sort();
+        let dirs: BTreeSet<PathBuf> = files
+            .iter()
+            .flat_map(|file| {
+                file.ancestors()
+                    .skip(1)
+                    .take_while(|dir| dir.starts_with(package_root))
+            })
+            .map(Path::to_path_buf)
+            .collect();
+        // Stamped before reading, so a file changing meanwhile is read again next time.
+        let stamps = dirs
+            .into_iter()
+            .chain(files.iter().cloned())
+            .map(|path| {
+                let modified = modified(&path);
+                (path, modified)
+            })
+            .collect();
+
+        let mut included = HashMap::new();
+        for file in files {
+            let Some(source) = read_source(&file)
+                .ok()
+                .filter(|source| mentions_inclusion(source))
+            else {
+                continue;
+            };
+            let Some(dir) = file.parent() else {
+                continue;
+            };
+            for inclusion in inclusions(&source) {
+                if let Ok(path) = dir.join(&inclusion.file).canonicalize() {
+                    included
+                        .entry(path)
+                        .or_insert_with(|| (file.clone(), inclusion.module_path));
+                }
+            }
+        }
+        Ok(Inclusions { stamps, included })
+    }
+
+    /// Whether no walked file or directory changed, a directory changing when a file is
+    /// added to or removed from it.
+    fn is_current(&self) -> bool {
+        self.stamps
+            .iter()
+            .all(|(path, modified_at)| modified(path) == *modified_at)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Whether `source` has an `include!(` or a `#[path` attribute, whitespace allowed between
+/// the tokens, so files that can't include another aren't tokenized.
+fn mentions_inclusion(source: &str) -> bool {
+    fn then(rest: &str, token: char) -> Option<&str> {
+        rest.trim_start().strip_prefix(token)
+    }
+    source.match_indices("include").any(|(at, keyword)| {
+        then(&source[at + keyword.len()..], '!')
+            .and_then(|rest| then(rest, '('))
+            .is_some()
+    }) || source.match_indices('#').any(|(at, _)| {
+        then(&source[at + 1..], '[').is_some_and(|rest| rest.trim_start().starts_with("path"))
+    })
+}
+
+/// Why `scope` gives no runnable of its own, `None` when it does.
+fn rejection_reason(location: &Location, scopes: &[Scope], scope: &Scope) -> Option<String> {
+    let reason = match scope.kind {
+        ScopeKind::Function if scope.is_test() || scope.is_bench() => return None,
+        ScopeKind::Function if is_main(scope) => match location.target {
+            Some(TargetKind::Bin(_) | TargetKind::Example(_)) => return None,
+            _ => "`main` only runs in a bin or example target".to_string(),
+        },
+        ScopeKind::Function if scope.has_attribute("ignore") => {
+            "`#[ignore]` without `#[test]`, the test attribute is missing".to_string()
+        }
+        ScopeKind::Function => "no `#[test]` or `#[bench]` attribute".to_string(),
+        ScopeKind::Module if contains_tests(scopes, scope) => return None,
+        ScopeKind::Module => "the module contains no tests".to_string(),
+        ScopeKind::Impl | ScopeKind::Trait => {
+            "only the tests and doc tests inside impls and traits run".to_string()
+        }
+        ScopeKind::DocTest if location.target == Some(TargetKind::Lib) => return None,
+        ScopeKind::DocTest => "rustdoc only runs the doc tests of a library target".to_string(),
+        ScopeKind::File => return None,
+    };
+    Some(reason)
+}
+
+fn contains_tests(scopes: &[Scope], module: &Scope) -> bool {
+    scopes.iter().any(|scope| {
+        scope.is_test()
+            && module.start_line <= scope.start_line
+            && scope.end_line <= module.end_line
+    })
+}
+
+/// Full paths of the modules containing tests directly inside the inline module at
+/// `path` of the file, or at the top of the file for an empty `path`.
+fn nested_test_modules(scopes: &[Scope], file_module: &[String], path: &str) -> Vec<String> {
+    scopes
+        .iter()
+        .filter(|scope| {
+            scope.kind == ScopeKind::Module
+                && scope.module_path.join("::") == path
+                && contains_tests(scopes, scope)
+        })
+        .map(|scope| join_path(file_module, &scope.path()))
+        .collect()
+}
+
+fn is_main(scope: &Scope) -> bool {
+    scope.kind == ScopeKind::Function && scope.name == "main" && scope.module_path.is_empty()
+}
+
+fn join_path(file_module: &[String], path: &str) -> String {
+    file_module
+        .iter()
+        .map(String::as_str)
+        .chain(std::iter::once(path))
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+fn relative_components(package_root: &Path, path: &Path) -> Option<Vec<String>> {
+    let relative = path.strip_prefix(package_root).ok()?;
+    Some(
+        relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect(),
+    )
+}
+
+fn file_stem(name: &str) -> String {
+    name.trim_end_matches(".rs").to_string()
+}
+
+/// Resolves the cargo target of `path` from the standard package layout.
+fn target_kind(package_root: &Path, path: &Path, package: Option<&str>) -> Option<TargetKind> {
+    let components = relative_components(package_root, path)?;
+    let parts: Vec<&str> = components.iter().map(String::as_str).collect();
+
+    let named = |parts: &[&str]| match parts {
+        [name] => Some(file_stem(name)),
+        [dir, ..] => Some(dir.to_string()),
+        [] => None,
+    };
+
+    match parts.as_slice() {
+        ["src", "bin", rest @ ..] => named(rest).map(TargetKind::Bin),
+        ["src", "main.rs"] => package.map(|name| TargetKind::Bin(name.to_string())),
+        ["src", ..] if !package_root.join("src").join("lib.rs").exists() => {
+            package.map(|name| TargetKind::Bin(name.to_string()))
+        }
+        ["src", ..] => Some(TargetKind::Lib),
+        ["tests", rest @ ..] => named(rest).map(TargetKind::Test),
+        ["benches", rest @ ..] => named(rest).map(TargetKind::Bench),
+        ["examples", rest @ ..] => named(rest).map(TargetKind::Example),
+        _ => None,
+    }
+}
+
+/// Whether `dir` is a directory of the standard package layout, e.g. `src/` or
+/// `tests/`, whose files the layout already assigns to targets.
+fn is_standard_dir(package_root: &Path, dir: &Path) -> bool {
+    let (Ok(package_root), Ok(dir)) = (package_root.canonicalize(), dir.canonicalize()) else {
+        return false;
+    };
+    ["src", "src/bin", "tests", "benches", "examples"]
+        .iter()
+        .any(|standard| package_root.join(standard) == dir)
+}
+
+/// The module path of `path` in the crate rooted in `dir` e.g. `args` for
+/// `src/tools/cli/args.rs` in the crate of `src/tools/cli/main.rs`.
+fn module_path_within(dir: &Path, path: &Path) -> Vec<String> {
+    let (Ok(dir), Ok(path)) = (dir.canonicalize(), path.canonicalize()) else {
+        return Vec::new();
+    };
+    let mut segments: Vec<String> = relative_components(&dir, &path)
+        .unwrap_or_default()
+        .iter()
+        .map(|part| file_stem(part))
+        .collect();
+    if matches!(
+        segments.last().map(String::as_str),
+        Some("mod" | "lib" | "main")
+    ) {
+        segments.pop();
+    }
+    segments
+}
+
+/// The module path of a file under `src/` e.g. `src/entities/config.rs` is `entities::config`.
+fn file_module_path(package_root: &Path, path: &Path) -> Vec<String> {
+    let Some(components) = relative_components(package_root, path) else {
+        return Vec::new();
+    };
+
+    match components.as_slice() {
+        [src, rest @ ..] if src == "src" => {
+            let mut segments: Vec<String> = rest.iter().map(|part| file_stem(part)).collect();
+            if matches!(
+                segments.last().map(String::as_str),
+                Some("mod" | "lib" | "main")
+            ) {
+                segments.pop();
+            }
+            if segments.first().map(String::as_str) == Some("bin") {
+                return Vec::new();
+            }
+            segments
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"pub fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        assert_eq!(add(2, 2), 4);
+    }
+}
+"#;
+
+    fn package() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(
+            root.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.path().join("src").join("math")).unwrap();
+        fs::write(root.path().join("src").join("lib.rs"), "pub mod math;").unwrap();
+        root
+    }
+
+    #[test]
+    fn test_find_test_function() {
+        let root = package();
+        let path = root.path().join("src").join("math").join("mod.rs");
+        let finder = ContextFinder::new(&path).source(SOURCE);
+
+        let runnable = finder.find(Some(11)).unwrap();
+        assert_eq!(runnable.context, Context::Test);
+        assert_eq!(runnable.package.as_deref(), Some("demo"));
+        assert_eq!(runnable.target, Some(TargetKind::Lib));
+        assert_eq!(runnable.filter.as_deref(), Some("math::tests::it_works"));
+        assert!(runnable.exact);
+
+        let runnable = finder.find(Some(7)).unwrap();
+        assert_eq!(runnable.filter.as_deref(), Some("math::tests"));
+        assert!(!runnable.exact);
+
+        let runnable = finder.find(Some(2)).unwrap();
+        assert_eq!(runnable.context, Context::Test);
+        assert_eq!(runnable.filter.as_deref(), Some("math"));
+    }
+
+    #[test]
+    fn test_module_tests_nested() {
+        let root = package();
+        let path = root.path().join("src").join("math").join("mod.rs");
+        let source = "#[cfg(test)]\nmod tests {\n    #[test]\n    fn top() {}\n\n    mod io {\n        #[test]\n        fn read() {}\n    }\n}\n";
+        let runnable = ContextFinder::new(&path)
+            .source(source)
+            .find(Some(2))
+            .unwrap();
+        assert_eq!(runnable.kind, RunnableKind::ModuleTests);
+        assert_eq!(runnable.nested, vec!["math::tests::io"]);
+
+        let config = crate::Config {
+            name: "default".to_string(),
+            sub_command: Some("test".to_string()),
+            module_tests: Some(crate::ModuleTests {
+                include_nested: Some(false),
+            }),
+            ..Default::default()
+        };
+        let context = crate::BuildContext::default();
+        let command = crate::CommandBuilder::new(&config)
+            .runnable(&runnable)
+            .context(&context)
+            .build()
+            .unwrap();
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            vec![
+                "test",
+                "-p",
+                "demo",
+                "--lib",
+                "--",
+                "math::tests::",
+                "--skip",
+                "math::tests::io::"
+            ]
+        );
+
+        let nextest = crate::Config {
+            name: "default".to_string(),
+            sub_command: Some("test".to_string()),
+            framework_test: Some(crate::TestFramework::Nextest),
+            ..Default::default()
+        };
+        let command = crate::CommandBuilder::new(&nextest)
+            .runnable(&runnable)
+            .context(&context)
+            .build()
+            .unwrap();
+        assert_eq!(
+            crate::command_line(&command),
+            "cargo nextest run -p demo --lib -E 'test(/^math::tests::/)'"
+        );
+    }
+
+    #[test]
+    fn test_find_in_included_files() {
+        let root = package();
+        let src = root.path().join("src");
+        fs::create_dir_all(src.join("math").join("shared")).unwrap();
+        fs::write(
+            src.join("math").join("mod.rs"),
+            "#[cfg(test)]\n#[path = \"shared/tests.rs\"]\nmod tests;\n",
+        )
+        .unwrap();
+        fs::write(
+            src.join("math").join("shared").join("tests.rs"),
+            "#[test]\nfn shared() {}\n",
+        )
+        .unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "mod math;\n\nmod generated {\n    include!(concat!(env!(\"OUT_DIR\"), \"/gen.rs\"));\n    include!(\"gen.rs\");\n}\n",
+        )
+        .unwrap();
+        fs::write(src.join("gen.rs"), "#[test]\nfn generated_case() {}\n").unwrap();
+
+        let runnable = ContextFinder::new(src.join("math").join("shared").join("tests.rs"))
+            .find(Some(2))
+            .unwrap();
+        assert_eq!(runnable.target, Some(TargetKind::Lib));
+        assert_eq!(runnable.filter.as_deref(), Some("math::tests::shared"));
+
+        let runnable = ContextFinder::new(src.join("gen.rs"))
+            .find(Some(2))
+            .unwrap();
+        assert_eq!(
+            runnable.filter.as_deref(),
+            Some("generated::generated_case")
+        );
+
+        // The cached inclusions follow the including file.
+        fs::write(src.join("lib.rs"), "mod math;\n").unwrap();
+        let runnable = ContextFinder::new(src.join("gen.rs"))
+            .find(Some(2))
+            .unwrap();
+        assert_eq!(runnable.filter.as_deref(), Some("gen::generated_case"));
+    }
+
+    #[test]
+    fn test_mentions_inclusion() {
+        assert!(mentions_inclusion("include!(\"gen.rs\");"));
+        assert!(mentions_inclusion(
+            "include ! (concat!(env!(\"OUT_DIR\"), \"/gen.rs\"));"
+        ));
+        assert!(mentions_inclusion(
+            "#[path = \"shared/tests.rs\"]\nmod tests;"
+        ));
+        assert!(mentions_inclusion("# [ path = \"a.rs\"]\nmod a;"));
+        assert!(!mentions_inclusion(
+            "use std::path::Path;\n#[test]\nfn include() {}"
+        ));
+    }
+
+    #[test]
+    fn test_find_doc_test() {
+        let root = package();
+        let path = root.path().join("src").join("math").join("mod.rs");
+        let source = "/// ```\n/// assert_eq!(demo::math::add(1, 1), 2);\n/// ```\npub fn add(a: u32, b: u32) -> u32 {\n    a + b\n}\n";
+        let runnable = ContextFinder::new(&path)
+            .source(source)
+            .find(Some(2))
+            .unwrap();
+
+        assert_eq!(runnable.kind, RunnableKind::DocTest);
+        assert_eq!(runnable.doc_test, Some(crate::DocTestMode::Run));
+        assert_eq!(runnable.filter.as_deref(), Some("math::add"));
+        assert!(!runnable.exact);
+        assert_eq!(runnable.cargo_args(), vec!["-p", "demo", "--doc"]);
+
+        let bin = root.path().join("src").join("bin").join("cli.rs");
+        let runnable = ContextFinder::new(&bin)
+            .source(source)
+            .find(Some(2))
+            .unwrap();
+        assert_ne!(runnable.kind, RunnableKind::DocTest);
+    }
+
+    #[test]
+    fn test_explain_rejections() {
+        let root = package();
+        let path = root.path().join("src").join("math").join("mod.rs");
+        let source = "pub fn add(a: u32, b: u32) -> u32 {\n    a + b\n}\n\nmod helpers {\n    fn setup() {}\n}\n";
+        let finder = ContextFinder::new(&path).source(source);
+
+        let reasons: Vec<_> = finder
+            .explain(6)
+            .unwrap()
+            .into_iter()
+            .map(|rejection| (rejection.scope.path(), rejection.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (
+                    "helpers::setup".to_string(),
+                    "no `#[test]` or `#[bench]` attribute".to_string()
+                ),
+                (
+                    "helpers".to_string(),
+                    "the module contains no tests".to_string()
+                ),
+                (
+                    "add".to_string(),
+                    "line 6 is outside it (lines 1-3)".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_binary() {
+        let root = package();
+        let path = root.path().join("src").join("bin").join("cli.rs");
+        let runnable = ContextFinder::new(&path)
+            .source("fn main() {\n    println!(\"hi\");\n}\n")
+            .find(Some(2))
+            .unwrap();
+
+        assert_eq!(runnable.context, Context::Run);
+        assert_eq!(runnable.target, Some(TargetKind::Bin("cli".to_string())));
+        assert_eq!(runnable.cargo_args(), vec!["-p", "demo", "--bin", "cli"]);
+
+        let source = "#[tokio::main(\n    flavor = \"current_thread\",\n)]\npub(crate) async fn main() -> std::io::Result<()> {\n    serve().await\n}\n\nasync fn serve() -> std::io::Result<()> {\n    Ok(())\n}\n";
+        let finder = ContextFinder::new(&path).source(source);
+        let runnable = finder.find(Some(5)).unwrap();
+        assert_eq!(runnable.kind, RunnableKind::Binary);
+        assert_eq!(runnable.function, Some("main".to_string()));
+        let main = finder
+            .runnables()
+            .unwrap()
+            .into_iter()
+            .find(|found| found.runnable.function.as_deref() == Some("main"))
+            .unwrap();
+        assert_eq!(main.scope.lens_line(), 1);
+        let binaries = finder
+            .runnables()
+            .unwrap()
+            .into_iter()
+            .filter(|found| found.runnable.kind == RunnableKind::Binary)
+            .count();
+        assert_eq!(binaries, 1);
+    }
+
+    #[test]
+    fn test_find_declared_no_main_binary() {
+        let root = package();
+        fs::write(
+            root.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[[bin]]\nname = \"firmware\"\npath = \"firmware/entry.rs\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.path().join("firmware")).unwrap();
+        let path = root.path().join("firmware").join("entry.rs");
+        let source = "#![no_std]\n#![no_main]\n\n#[no_mangle]\npub extern \"C\" fn _start() -> ! {\n    loop {}\n}\n";
+        fs::write(&path, source).unwrap();
+
+        let runnable = ContextFinder::new(&path).find(Some(6)).unwrap();
+        assert_eq!(runnable.kind, RunnableKind::Binary);
+        assert_eq!(
+            runnable.cargo_args(),
+            vec!["-p", "demo", "--bin", "firmware"]
+        );
+    }
+
+    #[test]
+    fn test_cursor_strategies() {
+        let root = package();
+        let path = root.path().join("src").join("math").join("mod.rs");
+        let source = "#[cfg(test)]\nmod tests {\n    #[test]\n    fn first() {}\n\n\n\n    /// Checks the second case.\n    #[test]\n    fn second() {}\n\n    fn helper() {}\n}\n";
+        let filter = |strategy: CursorStrategy, line: u32| {
+            ContextFinder::new(&path)
+                .source(source)
+                .cursor_strategy(strategy)
+                .find(Some(line))
+                .unwrap()
+                .filter
+        };
+        let first = Some("math::tests::first".to_string());
+        let second = Some("math::tests::second".to_string());
+
+        // Blank lines between the tests.
+        assert_eq!(
+            filter(CursorStrategy::Enclosing, 5),
+            Some("math::tests".to_string())
+        );
+        assert_eq!(filter(CursorStrategy::Nearest, 5), first);
+        assert_eq!(filter(CursorStrategy::Nearest, 7), second);
+        assert_eq!(filter(CursorStrategy::NearestAbove, 7), first);
+        // The doc comment and attribute lines belong to the test below.
+        assert_eq!(filter(CursorStrategy::NearestAbove, 8), second);
+        assert_eq!(filter(CursorStrategy::NearestAbove, 9), second);
+        // A helper after the last test.
+        assert_eq!(filter(CursorStrategy::Nearest, 12), second);
+        // Nothing above, the enclosing module.
+        assert_eq!(
+            filter(CursorStrategy::NearestAbove, 2),
+            Some("math::tests".to_string())
+        );
+
+        let module_first = ContextFinder::new(&path)
+            .source(source)
+            .scope_order(&[RunnableKind::ModuleTests])
+            .find(Some(4))
+            .unwrap();
+        assert_eq!(module_first.kind, RunnableKind::ModuleTests);
+        assert_eq!(module_first.filter, Some("math::tests".to_string()));
+    }
+
+    #[test]
+    fn test_package_runnables() {
+        let root = package();
+        let path = root.path().join("src").join("lib.rs");
+        let runnables = ContextFinder::new(&path).package_runnables();
+
+        let kinds: Vec<_> = runnables.iter().map(|runnable| runnable.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                RunnableKind::Build,
+                RunnableKind::Check,
+                RunnableKind::Clippy
+            ]
+        );
+        assert_eq!(runnables[2].cargo_args(), vec!["-p", "demo"]);
+    }
+
+    #[test]
+    fn test_disabled_detectors() {
+        let root = package();
+        let path = root.path().join("src").join("lib.rs");
+        let source =
+            "/// ```\n/// assert!(true);\n/// ```\npub fn add() {}\n\n#[test]\nfn it_works() {}\n";
+        let kinds = |disabled: &[RunnableKind]| -> Vec<RunnableKind> {
+            ContextFinder::new(&path)
+                .source(source)
+                .disabled(disabled)
+                .runnables()
+                .unwrap()
+                .iter()
+                .map(|found| found.runnable.kind)
+                .collect()
+        };
+
+        assert!(kinds(&[]).contains(&RunnableKind::DocTest));
+        let kinds = kinds(&[RunnableKind::DocTest]);
+        assert!(!kinds.contains(&RunnableKind::DocTest));
+        assert!(kinds.contains(&RunnableKind::Test));
+    }
+}
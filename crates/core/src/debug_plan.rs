@@ -0,0 +1,191 @@
+use std::{path::PathBuf, process::Command};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Artifacts, TargetKind};
+
+/// How to start a [Runnable](crate::Runnable) under a debugger: cargo builds it without
+/// running it, then the debugger launches the compiled binary directly with the test
+/// filter. See [CommandBuilder::debug_plan](crate::CommandBuilder::debug_plan).
+#[derive(Debug)]
+pub struct DebugPlan {
+    /// The cargo build step e.g. `cargo test --no-run -p core --lib --message-format=json`.
+    pub build: Command,
+    /// Arguments for the compiled binary e.g. `tests::it_works --exact --nocapture`.
+    pub binary_args: Vec<String>,
+    /// The target whose executable is launched when the build produces several.
+    pub target: Option<TargetKind>,
+}
+
+/// What a debugger needs to launch the binary of a [DebugPlan].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DebugLaunch {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+}
+
+/// A DAP `launch` configuration for CodeLLDB, e.g. an entry of `.vscode/launch.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DapConfiguration {
+    #[serde(rename = "type")]
+    pub adapter: String,
+    pub request: String,
+    pub name: String,
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+}
+
+impl DebugPlan {
+    /// Builds with the release profile but keeps debuginfo, as profilers need symbols.
+    pub fn release(mut self) -> Self {
+        self.build
+            .arg("--release")
+            .env("CARGO_PROFILE_RELEASE_DEBUG", "true");
+        self
+    }
+
+    /// Picks the executable of the target from the JSON messages printed by the build step.
+    pub fn launch(&self, messages: &str) -> Option<DebugLaunch> {
+        let artifacts = Artifacts::parse(messages);
+        let program = artifacts.executable(self.target.as_ref())?;
+
+        Some(DebugLaunch {
+            program: program.to_path_buf(),
+            args: self.binary_args.clone(),
+        })
+    }
+}
+
+impl DebugLaunch {
+    /// Runs the binary through a tool e.g. `["perf", "record", "-g", "--"]`.
+    pub fn wrapped(&self, wrapper: &[String]) -> Command {
+        let mut command = match wrapper.split_first() {
+            Some((program, args)) => {
+                let mut command = Command::new(program);
+                command.args(args).arg(&self.program);
+                command
+            }
+            None => Command::new(&self.program),
+        };
+        command.args(&self.args);
+        command
+    }
+
+    /// The DAP configuration launching the binary, named `name` in the editor.
+    pub fn dap(&self, name: impl Into<String>, cwd: Option<PathBuf>) -> DapConfiguration {
+        DapConfiguration {
+            adapter: "lldb".to_string(),
+            request: "launch".to_string(),
+            name: name.into(),
+            program: self.program.clone(),
+            args: self.args.clone(),
+            cwd,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        command_line, BuildContext, CommandBuilder, Config, Context, Runnable, RunnableKind,
+        TestFramework,
+    };
+
+    #[test]
+    fn test_debug_plan_for_test() {
+        let runnable = Runnable {
+            kind: RunnableKind::Test,
+            package: Some("core".to_string()),
+            target: Some(TargetKind::Lib),
+            filter: Some("tests::it_works".to_string()),
+            exact: true,
+            ..Runnable::new(Context::Test)
+        };
+        // The plan builds what the config runs, with nextest swapped for libtest.
+        let config = Config {
+            name: "default".to_string(),
+            sub_command: Some("test".to_string()),
+            framework_test: Some(TestFramework::Nextest),
+            features: Some(vec!["fast".to_string()]),
+            target: Some("x86_64-unknown-linux-musl".to_string()),
+            env: Some(HashMap::from([(
+                "RUST_LOG".to_string(),
+                "debug".to_string(),
+            )])),
+            ..Default::default()
+        };
+        let context = BuildContext::default();
+        let plan = CommandBuilder::new(&config)
+            .runnable(&runnable)
+            .context(&context)
+            .args(["--", "--ignored"])
+            .debug_plan()
+            .unwrap();
+
+        assert_eq!(
+            command_line(&plan.build),
+            "cargo test --no-run -p core --lib --features fast --target x86_64-unknown-linux-musl --message-format=json"
+        );
+        assert!(plan
+            .build
+            .get_envs()
+            .any(|(key, value)| key == "RUST_LOG" && value.is_some_and(|value| value == "debug")));
+
+        let messages = r#"{"reason":"compiler-artifact","executable":null}
+{"reason":"compiler-artifact","executable":"/target/debug/deps/core-1234"}
+{"reason":"build-finished","success":true}"#;
+        assert_eq!(
+            plan.launch(messages),
+            Some(DebugLaunch {
+                program: PathBuf::from("/target/debug/deps/core-1234"),
+                args: vec![
+                    "tests::it_works".to_string(),
+                    "--exact".to_string(),
+                    "--ignored".to_string(),
+                    "--nocapture".to_string(),
+                ],
+            })
+        );
+
+        let dap = plan.launch(messages).unwrap().dap("it_works", None);
+        assert_eq!(
+            serde_json::to_value(&dap).unwrap()["type"],
+            serde_json::json!("lldb")
+        );
+
+        let release = plan.release();
+        assert!(command_line(&release.build).ends_with(" --message-format=json --release"));
+        assert!(release
+            .build
+            .get_envs()
+            .any(|(key, _)| key == "CARGO_PROFILE_RELEASE_DEBUG"));
+
+        let binary = Runnable {
+            package: Some("demo".to_string()),
+            target: Some(TargetKind::Bin("demo".to_string())),
+            ..Runnable::new(Context::Run)
+        };
+        let config = Config {
+            name: "default".to_string(),
+            sub_command: Some("run".to_string()),
+            ..Default::default()
+        };
+        let plan = CommandBuilder::new(&config)
+            .runnable(&binary)
+            .context(&context)
+            .args(["--", "serve"])
+            .debug_plan()
+            .unwrap();
+        assert_eq!(
+            command_line(&plan.build),
+            "cargo build -p demo --bin demo --message-format=json"
+        );
+        assert_eq!(plan.binary_args, vec!["serve"]);
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+use super::{Anchor, FileTarget};
+
+/// A named shortcut to a location, e.g. the integration test you run 50 times a day.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Alias {
+    /// The target as `path[:line[:column]]`.
+    pub target: String,
+    /// Path of the scope at the target when the alias was added, used to
+    /// re-map the line when the file changed since.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+}
+
+impl Alias {
+    pub fn file_target(&self) -> Result<FileTarget, Error> {
+        self.target.parse()
+    }
+
+    pub fn anchor(&self) -> Result<Option<Anchor>, Error> {
+        let target = self.file_target()?;
+        Ok(self
+            .anchor
+            .as_ref()
+            .map(|path| Anchor::new(path, target.line.unwrap_or(1) as usize)))
+    }
+}
+
+/// Aliases stored in an `aliases.toml` file, in `~/.cargo-runner/` or a workspace `.cargo-runner/`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Aliases(pub BTreeMap<String, Alias>);
+
+impl Aliases {
+    pub fn user_path() -> Result<PathBuf, Error> {
+        Ok(dirs::home_dir()
+            .ok_or(Error::Other(anyhow!("Could not find home directory")))?
+            .join(".cargo-runner")
+            .join("aliases.toml"))
+    }
+
+    pub fn workspace_path(workspace_root: &Path) -> PathBuf {
+        workspace_root.join(".cargo-runner").join("aliases.toml")
+    }
+
+    /// Loads the aliases at `path`, a missing file has no aliases.
+    pub fn load(path: &Path) -> Result<Aliases, Error> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(toml::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Aliases::default()),
+            Err(e) => Err(Error::Io(e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Alias> {
+        self.0.get(name)
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, alias: Alias) -> Option<Alias> {
+        self.0.insert(name.into(), alias)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Alias> {
+        self.0.remove(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = Aliases::workspace_path(dir.path());
+        assert_eq!(Aliases::load(&path).unwrap(), Aliases::default());
+
+        let mut aliases = Aliases::default();
+        aliases.insert(
+            "it",
+            Alias {
+                target: "src/lib.rs:11".to_string(),
+                anchor: Some("tests::it_works".to_string()),
+            },
+        );
+        aliases.save(&path).unwrap();
+
+        let loaded = Aliases::load(&path).unwrap();
+        assert_eq!(loaded, aliases);
+        let alias = loaded.get("it").unwrap();
+        assert_eq!(alias.file_target().unwrap().line, Some(11));
+        assert_eq!(
+            alias.anchor().unwrap(),
+            Some(Anchor::new("tests::it_works", 11))
+        );
+    }
+}
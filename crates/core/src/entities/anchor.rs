@@ -0,0 +1,64 @@
+use super::Scope;
+
+/// A position remembered by the scope it pointed at, so it can be re-bound
+/// after the file changed, like a debugger re-binding a breakpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anchor {
+    /// The `::` separated scope path within the file e.g. `tests::it_works`.
+    pub path: String,
+    /// The 1-based line the scope started at when the anchor was taken.
+    pub line: usize,
+}
+
+impl Anchor {
+    pub fn new(path: impl Into<String>, line: usize) -> Self {
+        Anchor {
+            path: path.into(),
+            line,
+        }
+    }
+
+    pub fn from_scope(scope: &Scope) -> Self {
+        Anchor::new(scope.path(), scope.start_line)
+    }
+
+    /// Finds the scope this anchor points to in `scopes`.
+    ///
+    /// Prefers scopes with the same path, then scopes with the same name
+    /// (e.g. the item moved into another module); ties are broken by the
+    /// distance to the original line.
+    pub fn resolve<'a>(&self, scopes: &'a [Scope]) -> Option<&'a Scope> {
+        let name = self.path.rsplit("::").next().unwrap_or_default();
+        let nearest = |matches: &dyn Fn(&Scope) -> bool| {
+            scopes
+                .iter()
+                .filter(|scope| matches(scope))
+                .min_by_key(|scope| scope.start_line.abs_diff(self.line))
+        };
+
+        nearest(&|scope| scope.path() == self.path)
+            .or_else(|| nearest(&|scope| !name.is_empty() && scope.name == name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan;
+
+    #[test]
+    fn test_resolve_shifted_scope() {
+        let before = "#[test]\nfn first() {}\n\n#[test]\nfn second() {}\n";
+        let after = "use super::*;\n\n#[test]\nfn first() {}\n\nmod moved {\n    #[test]\n    fn second() {}\n}\n";
+
+        let anchor = Anchor::from_scope(&scan(before)[2]);
+        assert_eq!(anchor, Anchor::new("second", 4));
+
+        let scopes = scan(after);
+        let resolved = anchor.resolve(&scopes).expect("anchor should resolve");
+        assert_eq!(resolved.path(), "moved::second");
+        assert_eq!(resolved.start_line, 7);
+
+        assert!(Anchor::new("missing", 1).resolve(&scopes).is_none());
+    }
+}
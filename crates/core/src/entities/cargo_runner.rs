@@ -2,15 +2,30 @@ use anyhow::{anyhow, Result};
 use std::{
     collections::{HashMap, HashSet},
     fs,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use tracing::warn;
 
-use crate::Error;
+use crate::{find_workspace_root, Error};
 
-use super::{CommandType, Config, Context};
+/// File name of the directory-local config fragments.
+pub const LOCAL_CONFIG_FILE: &str = ".cargo-runner.toml";
+
+/// Directory at the workspace root holding the committed team config.
+pub const TEAM_CONFIG_DIR: &str = ".cargo-runner";
+
+const TEAM_CONFIG_TEMPLATE: &str = r#"# Team defaults for cargo-runner, committed with the workspace.
+# Same format as ~/.cargo-runner/config.toml and merged on top of it, then
+# `.cargo-runner.toml` fragments and the uncommitted `local.toml` next to this file.
+#
+# [[test.config]]
+# name = "default"
+# env = { RUST_BACKTRACE = "1" }
+"#;
+
+use super::{CommandType, Config, Context, Platform};
 
 pub type ConfigKey = String;
 
@@ -52,7 +67,8 @@ impl Serialize for CargoRunner {
     }
 }
 
-// Custom deserialization implementation
+// Custom deserialization implementation, `[target.<spec>]` sections matching the
+// platform are merged on top of the rest of the file in key order, see [Platform::matches].
 impl<'de> Deserialize<'de> for CargoRunner {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -64,14 +80,32 @@ impl<'de> Deserialize<'de> for CargoRunner {
             config: Option<Vec<Config>>,
         }
 
-        let map = HashMap::<String, CommandEntry>::deserialize(deserializer)?;
+        #[derive(Deserialize)]
+        struct Sections {
+            #[serde(default)]
+            target: HashMap<String, CargoRunner>,
+            #[serde(flatten)]
+            contexts: HashMap<String, CommandEntry>,
+        }
+
+        let sections = Sections::deserialize(deserializer)?;
 
-        let converted = map
+        let converted = sections
+            .contexts
             .into_iter()
             .map(|(k, v)| (k, (v.default, v.config)))
             .collect();
 
-        Ok(CargoRunner(converted))
+        let mut runner = CargoRunner(converted);
+        let mut targets: Vec<_> = sections.target.into_iter().collect();
+        targets.sort_by(|a, b| a.0.cmp(&b.0));
+        for (spec, target) in targets {
+            if Platform::HOST.matches(&spec).map_err(de::Error::custom)? {
+                runner.merge(target).map_err(de::Error::custom)?;
+            }
+        }
+
+        Ok(runner)
     }
 }
 
@@ -111,20 +145,19 @@ impl CargoRunner {
             sub_command: Some(sub_command.to_string()),
             allowed_subcommands: Some(vec![]),
             env: Some(HashMap::new()),
+            ..Default::default()
         };
         (Some("default".to_string()), Some(vec![config]))
     }
 
     pub fn set_default(&mut self, context: Context, name: &str) -> Result<(), Error> {
-        if let Some((_, configs)) = self.0.get(context.into()) {
-            if let Some(configs) = configs {
-                if configs.iter().any(|c| c.name == name) {
-                    self.0.insert(
-                        context.into(),
-                        (Some(name.to_string()), Some(configs.clone())),
-                    );
-                    return Ok(());
-                }
+        if let Some((_, Some(configs))) = self.0.get(context.into()) {
+            if configs.iter().any(|c| c.name == name) {
+                self.0.insert(
+                    context.into(),
+                    (Some(name.to_string()), Some(configs.clone())),
+                );
+                return Ok(());
             }
         }
         Err(Error::SetDefault(context))
@@ -137,7 +170,7 @@ impl CargoRunner {
             .map(|s| s.as_str())
     }
 
-    fn get_default_config_path() -> Result<PathBuf, Error> {
+    pub fn get_default_config_path() -> Result<PathBuf, Error> {
         Ok(dirs::home_dir()
             .ok_or(Error::Other(anyhow!("Could not find home directory")))?
             .join(".cargo-runner")
@@ -183,6 +216,7 @@ impl CargoRunner {
 }
 
 impl CargoRunner {
+    #[tracing::instrument(skip_all)]
     pub fn init() -> Result<CargoRunner, Error> {
         let home =
             dirs::home_dir().ok_or(Error::Other(anyhow!("Could not find home directory")))?;
@@ -190,7 +224,7 @@ impl CargoRunner {
         let config_dir = home.join(".cargo-runner");
         let config_path = Self::get_default_config_path()?;
 
-        fs::create_dir_all(&config_dir).map_err(|e| Error::Io(e))?;
+        fs::create_dir_all(&config_dir).map_err(Error::Io)?;
 
         CargoRunner::load(config_path)
     }
@@ -204,9 +238,9 @@ impl CargoRunner {
 
         fs::write(
             &config_path,
-            toml::to_string_pretty(&default_config).map_err(|e| Error::Serialize(e))?,
+            toml::to_string_pretty(&default_config).map_err(Error::Serialize)?,
         )
-        .map_err(|e| Error::Io(e))?;
+        .map_err(Error::Io)?;
 
         Ok(())
     }
@@ -293,9 +327,9 @@ impl CargoRunner {
 
             Self::create_backup(&config_path);
 
-            let toml = toml::to_string_pretty(&default_config).map_err(|e| Error::Serialize(e))?;
+            let toml = toml::to_string_pretty(&default_config).map_err(Error::Serialize)?;
 
-            fs::write(config_path, toml).map_err(|e| Error::Io(e))?;
+            fs::write(config_path, toml).map_err(Error::Io)?;
         }
 
         Ok(())
@@ -310,10 +344,10 @@ impl CargoRunner {
         };
 
         // Serialize the CargoRunner struct to TOML format
-        let toml_content = toml::to_string_pretty(&self).map_err(|e| Error::Serialize(e))?;
+        let toml_content = toml::to_string_pretty(&self).map_err(Error::Serialize)?;
 
         // Write the serialized content to the file
-        fs::write(&path_to_save, toml_content).map_err(|e| Error::Io(e))?;
+        fs::write(&path_to_save, toml_content).map_err(Error::Io)?;
 
         Ok(())
     }
@@ -333,9 +367,9 @@ impl CargoRunner {
                     let default_config = Self::default();
 
                     let toml =
-                        toml::to_string_pretty(&default_config).map_err(|e| Error::Serialize(e))?;
+                        toml::to_string_pretty(&default_config).map_err(Error::Serialize)?;
 
-                    fs::write(&path, toml).map_err(|e| Error::Io(e))?;
+                    fs::write(&path, toml).map_err(Error::Io)?;
 
                     Ok(default_config)
                 }
@@ -349,9 +383,9 @@ impl CargoRunner {
                 let default_config = Self::default();
 
                 let toml =
-                    toml::to_string_pretty(&default_config).map_err(|e| Error::Serialize(e))?;
+                    toml::to_string_pretty(&default_config).map_err(Error::Serialize)?;
 
-                fs::write(&path, toml).map_err(|e| Error::Io(e))?;
+                fs::write(&path, toml).map_err(Error::Io)?;
 
                 Ok(default_config)
             }
@@ -381,7 +415,7 @@ impl CargoRunner {
             }
 
             if let Some(ref new_default) = other_default {
-                if base_configs.as_ref().map_or(false, |base| {
+                if base_configs.as_ref().is_some_and(|base| {
                     base.iter().any(|cmd| cmd.name == *new_default)
                 }) {
                     *base_default = Some(new_default.clone());
@@ -398,7 +432,85 @@ impl CargoRunner {
         Ok(())
     }
 
-    fn create_backup(config_path: &PathBuf) {
+    /// Merges the workspace layers of config for `file_path`, each taking precedence
+    /// over the previous one:
+    ///
+    /// 1. the team config, `.cargo-runner/config.toml` at the workspace root
+    /// 2. the `.cargo-runner.toml` fragments from the workspace root down to the
+    ///    directory of `file_path`
+    /// 3. `.cargo-runner/local.toml` at the workspace root, personal and gitignored
+    ///
+    /// Returns the files that were applied, in that order.
+    #[tracing::instrument(skip_all)]
+    pub fn merge_local_configs(&mut self, file_path: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut applied = Vec::new();
+        for layer in Self::local_config_layers(file_path) {
+            let config: CargoRunner = toml::from_str(&fs::read_to_string(&layer)?)?;
+            self.merge(config)?;
+            applied.push(layer);
+        }
+
+        Ok(applied)
+    }
+
+    /// The existing workspace layers of config for `file_path`, in the order
+    /// [CargoRunner::merge_local_configs] applies them.
+    pub fn local_config_layers(file_path: &Path) -> Vec<PathBuf> {
+        let dir = if file_path.is_dir() {
+            file_path
+        } else {
+            file_path.parent().unwrap_or(file_path)
+        };
+        let root = find_workspace_root(file_path).unwrap_or_else(|| dir.to_path_buf());
+
+        let mut dirs: Vec<&Path> = dir
+            .ancestors()
+            .take_while(|ancestor| ancestor.starts_with(&root))
+            .collect();
+        dirs.reverse();
+
+        let team_dir = root.join(TEAM_CONFIG_DIR);
+        std::iter::once(team_dir.join("config.toml"))
+            .chain(dirs.into_iter().map(|dir| dir.join(LOCAL_CONFIG_FILE)))
+            .chain(std::iter::once(team_dir.join("local.toml")))
+            .filter(|layer| layer.is_file())
+            .collect()
+    }
+
+    /// Scaffolds the team config directory at `workspace_root`: a commented
+    /// `config.toml`, `presets/`, and a `.gitignore` keeping `local.toml` out of git.
+    /// Existing files are left untouched. Returns the files that were created.
+    pub fn init_team(workspace_root: &Path) -> Result<Vec<PathBuf>, Error> {
+        Self::init_team_from(workspace_root, TEAM_CONFIG_TEMPLATE)
+    }
+
+    /// Like [CargoRunner::init_team], with `config_template` as the team `config.toml`
+    /// e.g. a preset. The template must parse as a config.
+    pub fn init_team_from(
+        workspace_root: &Path,
+        config_template: &str,
+    ) -> Result<Vec<PathBuf>, Error> {
+        CargoRunner::try_from(config_template)?;
+        let team_dir = workspace_root.join(TEAM_CONFIG_DIR);
+        fs::create_dir_all(team_dir.join("presets"))?;
+
+        let mut created = Vec::new();
+        for (name, content) in [
+            ("config.toml", config_template),
+            (".gitignore", "local.toml\n"),
+            ("presets/.gitkeep", ""),
+        ] {
+            let path = team_dir.join(name);
+            if !path.exists() {
+                fs::write(&path, content)?;
+                created.push(path);
+            }
+        }
+
+        Ok(created)
+    }
+
+    pub fn create_backup(config_path: &PathBuf) {
         let backup_path_with_index = config_path.with_extension(""); // Start with the original path without extension
         let mut index = 0; // Start with 0
 
@@ -436,6 +548,7 @@ impl CargoRunner {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::EnvPolicy;
 
     #[test]
     fn test_default_commands() {
@@ -460,6 +573,7 @@ mod tests {
                 sub_command: Some("serve".to_string()),
                 allowed_subcommands: Some(vec![]),
                 env: Some(HashMap::new()),
+                ..Default::default()
             });
 
         assert!(config.set_default(Context::Run, "dx").is_ok());
@@ -493,9 +607,9 @@ mod tests {
         assert_eq!(dx_config.sub_command, Some("serve".to_string()));
         assert!(matches!(dx_config.command_type, Some(CommandType::Shell)));
 
-        assert!(config.0.get("test").is_none());
-        assert!(config.0.get("build").is_none());
-        assert!(config.0.get("bench").is_none());
+        assert!(!config.0.contains_key("test"));
+        assert!(!config.0.contains_key("build"));
+        assert!(!config.0.contains_key("bench"));
     }
 
     #[test]
@@ -541,4 +655,140 @@ mod tests {
         assert_eq!(default_config.sub_command, Some("run".to_string()));
         assert_eq!(default_config.command_type, Some(CommandType::Cargo));
     }
+
+    #[test]
+    fn test_clean_env_policy() {
+        let content = r#"
+        [test]
+        default = "isolated"
+        [[test.config]]
+        name = "isolated"
+        command = "cargo"
+        sub_command = "test"
+        env_policy = "clean"
+        env_allowlist = ["HOME"]
+        env = { RUST_LOG = "debug" }
+        "#;
+
+        let config: CargoRunner = toml::from_str(content).expect("Failed to parse config");
+        let isolated = config
+            .find(Context::Test, "isolated")
+            .expect("isolated config should exist");
+
+        assert_eq!(isolated.env_policy, Some(EnvPolicy::Clean));
+
+        let mut command = std::process::Command::new("cargo");
+        isolated.apply_env(&mut command);
+
+        let envs: HashMap<_, _> = command.get_envs().collect();
+        assert_eq!(
+            envs.get(std::ffi::OsStr::new("RUST_LOG")),
+            Some(&Some(std::ffi::OsStr::new("debug")))
+        );
+        assert_eq!(
+            envs.contains_key(std::ffi::OsStr::new("PATH")),
+            std::env::var_os("PATH").is_some()
+        );
+    }
+
+    #[test]
+    fn test_target_sections() {
+        let content = r#"
+        [test]
+        default = "default"
+        [[test.config]]
+        name = "default"
+        command = "cargo"
+        env = { SHARED = "1" }
+
+        [[target.'cfg(windows)'.test.config]]
+        name = "default"
+        env = { SHELL_KIND = "windows" }
+
+        [[target.'cfg(unix)'.test.config]]
+        name = "default"
+        env = { SHELL_KIND = "unix" }
+
+        [target.'cfg(unix)'.run]
+        default = "native"
+        [[target.'cfg(unix)'.run.config]]
+        name = "native"
+        command = "cargo"
+        "#;
+
+        let config: CargoRunner = toml::from_str(content).expect("Failed to parse config");
+        let env = config
+            .find(Context::Test, "default")
+            .and_then(|default| default.env.clone())
+            .unwrap();
+        let family = if cfg!(windows) { "windows" } else { "unix" };
+        assert_eq!(env.get("SHARED"), Some(&"1".to_string()));
+        assert_eq!(env.get("SHELL_KIND"), Some(&family.to_string()));
+        assert_eq!(config.get_default(Context::Run).is_some(), cfg!(unix));
+        assert!(!config.0.contains_key("target"));
+
+        let invalid = "[target.'cfg(feature = \"x\")'.test]\ndefault = \"default\"\n";
+        assert!(toml::from_str::<CargoRunner>(invalid).is_err());
+    }
+
+    #[test]
+    fn test_merge_local_configs() {
+        let root = tempfile::tempdir().unwrap();
+        let integration = root.path().join("tests").join("integration");
+        fs::create_dir_all(&integration).unwrap();
+        fs::write(
+            root.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.path().join(LOCAL_CONFIG_FILE),
+            "[[test.config]]\nname = \"default\"\nenv = { RUST_LOG = \"info\", SERVICES = \"off\" }\n",
+        )
+        .unwrap();
+        fs::write(
+            integration.join(LOCAL_CONFIG_FILE),
+            "[[test.config]]\nname = \"default\"\nenv = { SERVICES = \"on\" }\n",
+        )
+        .unwrap();
+
+        let mut config = CargoRunner::default();
+        let applied = config
+            .merge_local_configs(&integration.join("api.rs"))
+            .unwrap();
+        assert_eq!(applied.len(), 2);
+
+        let env = config
+            .find(Context::Test, "default")
+            .and_then(|c| c.env.clone())
+            .unwrap();
+        assert_eq!(env.get("SERVICES").map(String::as_str), Some("on"));
+        assert_eq!(env.get("RUST_LOG").map(String::as_str), Some("info"));
+
+        let mut outside = CargoRunner::default();
+        let applied = outside
+            .merge_local_configs(&root.path().join("src").join("lib.rs"))
+            .unwrap();
+        assert_eq!(applied, vec![root.path().join(LOCAL_CONFIG_FILE)]);
+
+        let team_dir = root.path().join(TEAM_CONFIG_DIR);
+        CargoRunner::init_team(root.path()).unwrap();
+        fs::write(
+            team_dir.join("local.toml"),
+            "[[test.config]]\nname = \"default\"\nenv = { SERVICES = \"mocked\" }\n",
+        )
+        .unwrap();
+
+        let mut layered = CargoRunner::default();
+        let applied = layered
+            .merge_local_configs(&integration.join("api.rs"))
+            .unwrap();
+        assert_eq!(applied.first(), Some(&team_dir.join("config.toml")));
+        assert_eq!(applied.last(), Some(&team_dir.join("local.toml")));
+        let env = layered
+            .find(Context::Test, "default")
+            .and_then(|c| c.env.clone())
+            .unwrap();
+        assert_eq!(env.get("SERVICES").map(String::as_str), Some("mocked"));
+    }
 }
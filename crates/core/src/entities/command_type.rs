@@ -8,9 +8,9 @@ pub enum CommandType {
     Shell,
 }
 
-impl Into<String> for CommandType {
-    fn into(self) -> String {
-        match self {
+impl From<CommandType> for String {
+    fn from(val: CommandType) -> Self {
+        match val {
             CommandType::Cargo => "cargo".to_string(),
             CommandType::SubCommand => "subcommand".to_string(),
             CommandType::Shell => "shell".to_string(),
@@ -18,9 +18,9 @@ impl Into<String> for CommandType {
     }
 }
 
-impl Into<&str> for CommandType {
-    fn into(self) -> &'static str {
-        match self {
+impl From<CommandType> for &str {
+    fn from(val: CommandType) -> Self {
+        match val {
             CommandType::Cargo => "cargo",
             CommandType::SubCommand => "subcommand",
             CommandType::Shell => "shell",
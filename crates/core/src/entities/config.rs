@@ -1,11 +1,18 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    process::Command,
+};
 
 use serde::{Deserialize, Serialize, Serializer};
 
 
 use crate::Error;
 
-use super::CommandType;
+use super::{
+    CommandType, CursorStrategy, Detectors, EnvPolicy, Matrix, Memcheck, ModuleTests, Override,
+    Profiler, RunnableKind, TestFramework, TestOptions, UserRunnable, Watch,
+};
 
 use anyhow::Result;
 
@@ -25,6 +32,110 @@ pub struct Config {
     pub allowed_subcommands: Option<Vec<String>>,
     #[serde(default)]
     pub env: Option<HashMap<String, String>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_policy: Option<EnvPolicy>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_allowlist: Option<Vec<String>>,
+    /// Directory the command runs in, supports `{workspace_root}` and `{package_root}`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<Vec<Override>>,
+    /// Tags given to tests by function path globs e.g. `smoke = ["api::tests::*"]`,
+    /// in addition to `test_tag = "smoke"` attributes.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<HashMap<String, Vec<String>>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<Matrix>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profiler: Option<Profiler>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memcheck: Option<Memcheck>,
+    /// Lint args passed to `cargo clippy` after `--` e.g. `["-D", "warnings"]`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lints: Option<Vec<String>>,
+    /// Whether to prime defaults from the package's dev-dependencies, see [Heuristics](crate::Heuristics).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heuristics: Option<bool>,
+    /// `RUSTC_WRAPPER` for the builds of the command e.g. `sccache`, an empty string disables
+    /// a wrapper set in the environment or cargo config.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rustc_wrapper: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module_tests: Option<ModuleTests>,
+    /// Label templates of the runnables by kind e.g. `test = "{package}: {path}"`,
+    /// see [label](crate::label).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<HashMap<String, String>>,
+    /// How a cursor between runnables picks one, read from the default config of the
+    /// context given with `--context`, else of `test`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor_strategy: Option<CursorStrategy>,
+    /// Kinds of runnables tried first at the cursor e.g. `["module-tests"]` to run the
+    /// tests module around a test, read like `cursor_strategy`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope_order: Option<Vec<RunnableKind>>,
+    /// Kinds of runnables left out of detection, read like `cursor_strategy`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detectors: Option<Detectors>,
+    /// Ignore patterns and debounce of `cargo runner watch`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watch: Option<Watch>,
+    /// Whether `cargo` configs run tests with libtest or nextest, see [TestFramework].
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub framework_test: Option<TestFramework>,
+    /// Test binary options e.g. `threads`, lowered to the flags of the test tool.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub test_options: Option<TestOptions>,
+    /// Whether doc tests, which nextest can't run, go to `cargo test --doc` when the
+    /// config runs nextest. On by default.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doctest_fallback: Option<bool>,
+    /// Seconds after which a run is killed.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Cargo features to build with e.g. `["cli"]`, on top of the `required-features` of
+    /// the target.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub all_features: Option<bool>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_default_features: Option<bool>,
+    /// Target triple to build for e.g. `thumbv7em-none-eabihf`, passed as `--target`.
+    /// With `command = "cross"` a `cargo` config runs `cross`, which builds in a container
+    /// with the toolchain of the target.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Runnables declared for the files matching their globs, see [UserRunnable].
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runnables: Option<Vec<UserRunnable>>,
 }
 
 fn serialize_command_type<S>(
@@ -66,6 +177,158 @@ impl Config {
             let base_env = self.env.get_or_insert_with(HashMap::new);
             base_env.extend(other_env.clone());
         }
+        if let Some(policy) = &other.env_policy {
+            self.env_policy = Some(policy.clone());
+        }
+        if let Some(allowlist) = &other.env_allowlist {
+            let base_allowlist = self.env_allowlist.get_or_insert_with(Vec::new);
+            for key in allowlist {
+                if !base_allowlist.contains(key) {
+                    base_allowlist.push(key.clone());
+                }
+            }
+        }
+        if let Some(dir) = &other.working_dir {
+            self.working_dir = Some(dir.clone());
+        }
+        if let Some(overrides) = &other.overrides {
+            let base_overrides = self.overrides.get_or_insert_with(Vec::new);
+            base_overrides.extend(overrides.iter().cloned());
+        }
+        if let Some(tags) = &other.tags {
+            let base_tags = self.tags.get_or_insert_with(HashMap::new);
+            for (tag, globs) in tags {
+                let base_globs = base_tags.entry(tag.clone()).or_default();
+                for glob in globs {
+                    if !base_globs.contains(glob) {
+                        base_globs.push(glob.clone());
+                    }
+                }
+            }
+        }
+        if let Some(matrix) = &other.matrix {
+            self.matrix = Some(matrix.clone());
+        }
+        if let Some(profiler) = other.profiler {
+            self.profiler = Some(profiler);
+        }
+        if let Some(memcheck) = &other.memcheck {
+            self.memcheck = Some(memcheck.clone());
+        }
+        if let Some(lints) = &other.lints {
+            self.lints = Some(lints.clone());
+        }
+        if let Some(heuristics) = other.heuristics {
+            self.heuristics = Some(heuristics);
+        }
+        if let Some(wrapper) = &other.rustc_wrapper {
+            self.rustc_wrapper = Some(wrapper.clone());
+        }
+        if let Some(module_tests) = &other.module_tests {
+            self.module_tests = Some(module_tests.clone());
+        }
+        if let Some(labels) = &other.labels {
+            let base_labels = self.labels.get_or_insert_with(HashMap::new);
+            base_labels.extend(labels.clone());
+        }
+        if let Some(strategy) = other.cursor_strategy {
+            self.cursor_strategy = Some(strategy);
+        }
+        if let Some(order) = &other.scope_order {
+            self.scope_order = Some(order.clone());
+        }
+        if let Some(detectors) = &other.detectors {
+            self.detectors = Some(detectors.clone());
+        }
+        if let Some(watch) = &other.watch {
+            self.watch = Some(watch.clone());
+        }
+        if let Some(framework) = other.framework_test {
+            self.framework_test = Some(framework);
+        }
+        if let Some(options) = &other.test_options {
+            self.test_options
+                .get_or_insert_with(TestOptions::default)
+                .merge(options);
+        }
+        if let Some(fallback) = other.doctest_fallback {
+            self.doctest_fallback = Some(fallback);
+        }
+        if let Some(timeout) = other.timeout_secs {
+            self.timeout_secs = Some(timeout);
+        }
+        if let Some(features) = &other.features {
+            self.features = Some(features.clone());
+        }
+        if let Some(all) = other.all_features {
+            self.all_features = Some(all);
+        }
+        if let Some(no_default) = other.no_default_features {
+            self.no_default_features = Some(no_default);
+        }
+        if let Some(target) = &other.target {
+            self.target = Some(target.clone());
+        }
+        if let Some(runnables) = &other.runnables {
+            let base_runnables = self.runnables.get_or_insert_with(Vec::new);
+            for runnable in runnables {
+                base_runnables.retain(|base| base.name != runnable.name);
+                base_runnables.push(runnable.clone());
+            }
+        }
         Ok(())
     }
+
+    /// The cargo flags of `features`, `all_features` and `no_default_features`.
+    pub fn feature_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(features) = self.features.as_ref().filter(|features| !features.is_empty()) {
+            args.push("--features".to_string());
+            args.push(features.join(","));
+        }
+        if self.all_features == Some(true) {
+            args.push("--all-features".to_string());
+        }
+        if self.no_default_features == Some(true) {
+            args.push("--no-default-features".to_string());
+        }
+        args
+    }
+
+    /// Applies the [EnvPolicy] to `command`, then sets the configured `env` on top of it.
+    pub fn apply_env(&self, command: &mut Command) {
+        self.apply_env_from(command, &self.capture_env());
+    }
+
+    /// The parent environment the [EnvPolicy] may pass on: `PATH` plus `env_allowlist`.
+    pub fn capture_env(&self) -> HashMap<OsString, OsString> {
+        let allowlist = self.env_allowlist.iter().flatten().map(String::as_str);
+        std::iter::once("PATH")
+            .chain(allowlist)
+            .filter_map(|key| Some((OsString::from(key), std::env::var_os(key)?)))
+            .collect()
+    }
+
+    /// Like [Config::apply_env] but takes the parent environment from `parent`
+    /// instead of the current process.
+    pub fn apply_env_from(&self, command: &mut Command, parent: &HashMap<OsString, OsString>) {
+        match self.env_policy.clone().unwrap_or_default() {
+            EnvPolicy::Inherit => {}
+            EnvPolicy::Clean => {
+                command.env_clear();
+                let allowlist = self.env_allowlist.iter().flatten().map(String::as_str);
+                for key in std::iter::once("PATH").chain(allowlist) {
+                    if let Some(value) = parent.get(OsStr::new(key)) {
+                        command.env(key, value);
+                    }
+                }
+            }
+            EnvPolicy::Explicit => {
+                command.env_clear();
+            }
+        }
+        if let Some(env) = &self.env {
+            command.envs(env);
+        }
+    }
 }
@@ -1,6 +1,8 @@
 use std::fmt::Display;
 
-#[derive(Debug, Clone, Copy)]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Context {
     Run,
     Build,
@@ -23,13 +25,14 @@ impl From<&str> for Context {
 
 impl Display for Context {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
+        let s: &str = (*self).into();
+        write!(f, "{}", s)
     }
 }
 
-impl Into<String> for Context {
-    fn into(self) -> String {
-        match self {
+impl From<Context> for String {
+    fn from(val: Context) -> Self {
+        match val {
             Context::Run => String::from("run"),
             Context::Build => String::from("build"),
             Context::Test => String::from("test"),
@@ -39,9 +42,9 @@ impl Into<String> for Context {
     }
 }
 
-impl Into<&str> for Context {
-    fn into(self) -> &'static str {
-        match self {
+impl From<Context> for &str {
+    fn from(val: Context) -> Self {
+        match val {
             Context::Run => "run",
             Context::Build => "build",
             Context::Test => "test",
@@ -50,3 +53,23 @@ impl Into<&str> for Context {
         }
     }
 }
+
+impl Serialize for Context {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let s: &str = (*self).into();
+        serializer.serialize_str(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for Context {
+    fn deserialize<D>(deserializer: D) -> Result<Context, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        Ok(Context::from(s.as_str()))
+    }
+}
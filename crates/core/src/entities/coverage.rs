@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+/// The report `cargo runner coverage` asks cargo-llvm-cov for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum CoverageReport {
+    /// The summary table printed after the run.
+    #[default]
+    Summary,
+    /// An lcov tracefile written to the path, e.g. for editor gutters.
+    Lcov(PathBuf),
+    /// An HTML report written to the directory.
+    Html(PathBuf),
+}
+
+impl CoverageReport {
+    /// The `cargo llvm-cov` args writing the report.
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            CoverageReport::Summary => Vec::new(),
+            CoverageReport::Lcov(path) => vec![
+                "--lcov".to_string(),
+                "--output-path".to_string(),
+                path.display().to_string(),
+            ],
+            CoverageReport::Html(dir) => vec![
+                "--html".to_string(),
+                "--output-dir".to_string(),
+                dir.display().to_string(),
+            ],
+        }
+    }
+}
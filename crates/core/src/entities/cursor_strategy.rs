@@ -0,0 +1,69 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// How a cursor line outside any test, bench, `main` or doc test picks a runnable.
+///
+/// ```toml
+/// [[test.config]]
+/// name = "default"
+/// cursor_strategy = "nearest-above"
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CursorStrategy {
+    /// The innermost scope containing the line e.g. the module between two tests.
+    #[default]
+    Enclosing,
+    /// The closest test, bench, `main` or doc test above or below, above on a tie.
+    Nearest,
+    /// The closest test, bench, `main` or doc test above, like the cursor being "after" it.
+    NearestAbove,
+}
+
+const CURSOR_STRATEGIES: &[&str] = &["enclosing", "nearest", "nearest-above"];
+
+impl TryFrom<&str> for CursorStrategy {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "enclosing" => Ok(CursorStrategy::Enclosing),
+            "nearest" => Ok(CursorStrategy::Nearest),
+            "nearest-above" => Ok(CursorStrategy::NearestAbove),
+            _ => Err(format!(
+                "invalid cursor strategy `{}`, expected one of: {}",
+                value,
+                CURSOR_STRATEGIES.join(", ")
+            )),
+        }
+    }
+}
+
+impl From<CursorStrategy> for &str {
+    fn from(val: CursorStrategy) -> Self {
+        match val {
+            CursorStrategy::Enclosing => "enclosing",
+            CursorStrategy::Nearest => "nearest",
+            CursorStrategy::NearestAbove => "nearest-above",
+        }
+    }
+}
+
+impl Serialize for CursorStrategy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let s: &str = (*self).into();
+        serializer.serialize_str(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for CursorStrategy {
+    fn deserialize<D>(deserializer: D) -> Result<CursorStrategy, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        CursorStrategy::try_from(s.as_str())
+            .map_err(|_| serde::de::Error::unknown_variant(&s, CURSOR_STRATEGIES))
+    }
+}
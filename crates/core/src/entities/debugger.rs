@@ -0,0 +1,61 @@
+/// How `cargo runner debug` hands the compiled binary to a debugger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Debugger {
+    /// Launch it under `rust-lldb`.
+    Lldb,
+    /// Launch it under `rust-gdb`.
+    Gdb,
+    /// Print a DAP launch configuration for an editor to start it.
+    Dap,
+}
+
+const DEBUGGERS: &[&str] = &["lldb", "gdb", "dap"];
+
+impl Default for Debugger {
+    /// lldb on macOS, where gdb needs codesigning, gdb elsewhere.
+    fn default() -> Self {
+        if cfg!(target_os = "macos") {
+            Debugger::Lldb
+        } else {
+            Debugger::Gdb
+        }
+    }
+}
+
+impl From<Debugger> for &str {
+    fn from(val: Debugger) -> Self {
+        match val {
+            Debugger::Lldb => "lldb",
+            Debugger::Gdb => "gdb",
+            Debugger::Dap => "dap",
+        }
+    }
+}
+
+impl TryFrom<&str> for Debugger {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "lldb" => Ok(Debugger::Lldb),
+            "gdb" => Ok(Debugger::Gdb),
+            "dap" => Ok(Debugger::Dap),
+            _ => Err(format!(
+                "unknown debugger `{}`, expected one of: {}",
+                value,
+                DEBUGGERS.join(", ")
+            )),
+        }
+    }
+}
+
+impl Debugger {
+    /// The command line placed before the binary and its args, empty for DAP.
+    pub fn wrapper(&self) -> Vec<String> {
+        match self {
+            Debugger::Lldb => vec!["rust-lldb".to_string(), "--".to_string()],
+            Debugger::Gdb => vec!["rust-gdb".to_string(), "--args".to_string()],
+            Debugger::Dap => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+use crate::RunnableKind;
+
+/// Which built-in detectors run, e.g. hiding bench and doc test lenses. A config
+/// layer closer to the file replaces the list, so a directory can re-enable them.
+///
+/// ```toml
+/// [[test.config]]
+/// name = "default"
+/// detectors = { disabled = ["doctest", "bench"] }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Detectors {
+    #[serde(default)]
+    pub disabled: Vec<RunnableKind>,
+}
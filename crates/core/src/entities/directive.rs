@@ -0,0 +1,121 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The marker of a directive comment.
+pub const DIRECTIVE_PREFIX: &str = "cargo-runner:";
+
+/// A comment above an item customizing its runnable, applied after config overrides.
+///
+/// ```rust,ignore
+/// // cargo-runner: env RUST_LOG=debug
+/// // cargo-runner: args --features slow -- --nocapture
+/// #[test]
+/// fn it_works() {}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Directive {
+    /// `env KEY=VALUE`, set in the environment of the command.
+    Env(String, String),
+    /// `args ...`, added like trailing CLI args: before `--` to cargo, after it to the binary.
+    Args(Vec<String>),
+}
+
+impl Directive {
+    /// The directive of a `// cargo-runner: ...` comment line, `None` for other lines.
+    pub fn parse_comment(line: &str) -> Option<Result<Directive, String>> {
+        let comment = line.trim_start().strip_prefix("//")?;
+        if comment.starts_with('/') || comment.starts_with('!') {
+            return None;
+        }
+        let directive = comment.trim_start().strip_prefix(DIRECTIVE_PREFIX)?;
+        Some(Directive::try_from(directive.trim()))
+    }
+}
+
+impl TryFrom<&str> for Directive {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let (name, rest) = value.split_once(char::is_whitespace).unwrap_or((value, ""));
+        match name {
+            "env" => match rest.trim().split_once('=') {
+                Some((key, value)) if !key.is_empty() => {
+                    Ok(Directive::Env(key.to_string(), value.to_string()))
+                }
+                _ => Err(format!(
+                    "invalid env directive `{}`, expected `env KEY=VALUE`",
+                    value
+                )),
+            },
+            "args" => Ok(Directive::Args(
+                rest.split_whitespace().map(str::to_string).collect(),
+            )),
+            _ => Err(format!(
+                "invalid directive `{}`, expected `env KEY=VALUE` or `args ...`",
+                value
+            )),
+        }
+    }
+}
+
+impl Display for Directive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Directive::Env(key, value) => write!(f, "env {}={}", key, value),
+            Directive::Args(args) => write!(f, "args {}", args.join(" ")),
+        }
+    }
+}
+
+impl Serialize for Directive {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Directive {
+    fn deserialize<D>(deserializer: D) -> Result<Directive, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        Directive::try_from(s.as_str()).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan;
+
+    #[test]
+    fn test_directives_above_items() {
+        let source = "// cargo-runner: env RUST_LOG=debug\n/// Checks it.\n// cargo-runner: args --features slow -- --nocapture\n#[test]\nfn it_works() {}\n\n// cargo-runner: bogus\n// a plain comment\nfn helper() {}\n";
+        let scopes = scan(source);
+        let directives = |name: &str| {
+            scopes
+                .iter()
+                .find(|scope| scope.name == name)
+                .map(|scope| scope.extended.directives.clone())
+                .unwrap()
+        };
+
+        assert_eq!(
+            directives("it_works"),
+            vec![
+                Directive::Env("RUST_LOG".to_string(), "debug".to_string()),
+                Directive::Args(
+                    ["--features", "slow", "--", "--nocapture"]
+                        .map(str::to_string)
+                        .to_vec()
+                ),
+            ]
+        );
+        assert_eq!(directives("helper"), vec![]);
+        assert_eq!(Directive::parse_comment("/// cargo-runner: env A=1"), None);
+    }
+}
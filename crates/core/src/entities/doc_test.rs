@@ -0,0 +1,94 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// How rustdoc treats a fenced code block of a doc comment, read from its info
+/// string e.g. ```` ```no_run ````.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocTestMode {
+    /// Compiled and run.
+    Run,
+    /// Compiled only, running the doc test builds it without executing it.
+    NoRun,
+    /// Passes when it fails to compile.
+    CompileFail,
+    /// Reported as ignored unless run with `--include-ignored`.
+    Ignore,
+}
+
+const DOC_TEST_MODES: &[&str] = &["run", "no_run", "compile_fail", "ignore"];
+
+/// Info string words rustdoc understands besides `rust` and the modes.
+const KNOWN_ATTRIBUTES: &[&str] = &["should_panic", "test_harness", "standalone_crate"];
+
+impl DocTestMode {
+    /// The mode of a code block from its info string, `None` for blocks rustdoc doesn't
+    /// test e.g. ```` ```text ```` or ```` ```toml ````.
+    pub fn from_fence(info: &str) -> Option<Self> {
+        let mut mode = DocTestMode::Run;
+        let mut rust = false;
+        let mut other = false;
+
+        for word in info
+            .split(|c: char| c == ',' || c == '{' || c == '}' || c.is_whitespace())
+            .filter(|word| !word.is_empty())
+        {
+            match word {
+                "rust" => rust = true,
+                "ignore" => mode = DocTestMode::Ignore,
+                word if word.starts_with("ignore-") => mode = DocTestMode::Ignore,
+                "compile_fail" if mode != DocTestMode::Ignore => mode = DocTestMode::CompileFail,
+                "no_run" if mode == DocTestMode::Run => mode = DocTestMode::NoRun,
+                "compile_fail" | "no_run" => {}
+                word if word.starts_with("edition") || KNOWN_ATTRIBUTES.contains(&word) => {}
+                _ => other = true,
+            }
+        }
+
+        (rust || !other).then_some(mode)
+    }
+}
+
+impl From<DocTestMode> for &str {
+    fn from(val: DocTestMode) -> Self {
+        match val {
+            DocTestMode::Run => "run",
+            DocTestMode::NoRun => "no_run",
+            DocTestMode::CompileFail => "compile_fail",
+            DocTestMode::Ignore => "ignore",
+        }
+    }
+}
+
+impl Display for DocTestMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s: &str = (*self).into();
+        write!(f, "{}", s)
+    }
+}
+
+impl Serialize for DocTestMode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let s: &str = (*self).into();
+        serializer.serialize_str(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for DocTestMode {
+    fn deserialize<D>(deserializer: D) -> Result<DocTestMode, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        match s.as_str() {
+            "run" => Ok(DocTestMode::Run),
+            "no_run" => Ok(DocTestMode::NoRun),
+            "compile_fail" => Ok(DocTestMode::CompileFail),
+            "ignore" => Ok(DocTestMode::Ignore),
+            _ => Err(serde::de::Error::unknown_variant(&s, DOC_TEST_MODES)),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Controls which environment variables a spawned command inherits from the parent process.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum EnvPolicy {
+    /// Inherit the full parent environment (cargo's own behavior).
+    #[default]
+    Inherit,
+    /// Start from an empty environment and keep only `PATH` plus `env_allowlist`.
+    Clean,
+    /// Start from an empty environment; only the configured `env` is set.
+    Explicit,
+}
+
+impl From<EnvPolicy> for &str {
+    fn from(val: EnvPolicy) -> Self {
+        match val {
+            EnvPolicy::Inherit => "inherit",
+            EnvPolicy::Clean => "clean",
+            EnvPolicy::Explicit => "explicit",
+        }
+    }
+}
+
+impl Serialize for EnvPolicy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let s: &str = self.clone().into();
+        serializer.serialize_str(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for EnvPolicy {
+    fn deserialize<D>(deserializer: D) -> Result<EnvPolicy, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        match s.as_str() {
+            "inherit" => Ok(EnvPolicy::Inherit),
+            "clean" => Ok(EnvPolicy::Clean),
+            "explicit" => Ok(EnvPolicy::Explicit),
+            _ => Err(serde::de::Error::unknown_variant(
+                &s,
+                &["inherit", "clean", "explicit"],
+            )),
+        }
+    }
+}
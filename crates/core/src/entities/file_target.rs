@@ -0,0 +1,160 @@
+use std::{fmt::Display, path::PathBuf, str::FromStr};
+
+use crate::Error;
+
+/// A location sent by an editor or the cli: `path[:line[:column]]`.
+///
+/// Accepts Windows drive letters (`C:\proj\src\lib.rs:10`) and
+/// `file://` URIs (`file:///home/me/proj/src/lib.rs:10:5`).
+/// Lines and columns are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTarget {
+    pub path: PathBuf,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+impl FileTarget {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileTarget {
+            path: path.into(),
+            line: None,
+            column: None,
+        }
+    }
+}
+
+impl FromStr for FileTarget {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(Error::InvalidTarget(value.to_string()));
+        }
+
+        let (mut path, mut numbers) = (value, Vec::new());
+        while numbers.len() < 2 {
+            let Some((rest, last)) = path.rsplit_once(':') else {
+                break;
+            };
+            if last.is_empty() || !last.chars().all(|c| c.is_ascii_digit()) {
+                break;
+            }
+            let number: u32 = last
+                .parse()
+                .map_err(|_| Error::InvalidTarget(value.to_string()))?;
+            numbers.insert(0, number);
+            path = rest;
+        }
+
+        let path = match path.strip_prefix("file://") {
+            Some(uri) => {
+                decode_file_uri(uri).ok_or_else(|| Error::InvalidTarget(value.to_string()))?
+            }
+            None => path.to_string(),
+        };
+
+        if path.is_empty() || numbers.contains(&0) {
+            return Err(Error::InvalidTarget(value.to_string()));
+        }
+
+        Ok(FileTarget {
+            path: PathBuf::from(path),
+            line: numbers.first().copied(),
+            column: numbers.get(1).copied(),
+        })
+    }
+}
+
+impl Display for FileTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        Ok(())
+    }
+}
+
+fn decode_file_uri(uri: &str) -> Option<String> {
+    let path = uri.strip_prefix("localhost").unwrap_or(uri);
+
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = path.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    let decoded = String::from_utf8(decoded).ok()?;
+
+    // `file:///C:/proj` carries a leading slash before the drive letter.
+    let drive = decoded.as_bytes();
+    if drive.len() >= 3 && drive[0] == b'/' && drive[1].is_ascii_alphabetic() && drive[2] == b':' {
+        return Some(decoded[1..].to_string());
+    }
+
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(value: &str) -> FileTarget {
+        value.parse().expect("target should parse")
+    }
+
+    #[test]
+    fn test_parse_unix_path() {
+        assert_eq!(parse("src/lib.rs"), FileTarget::new("src/lib.rs"));
+
+        let target = parse("src/lib.rs:10:5");
+        assert_eq!(target.path, PathBuf::from("src/lib.rs"));
+        assert_eq!(target.line, Some(10));
+        assert_eq!(target.column, Some(5));
+    }
+
+    #[test]
+    fn test_parse_windows_drive_letter() {
+        let target = parse(r"C:\proj\src\lib.rs:10");
+        assert_eq!(target.path, PathBuf::from(r"C:\proj\src\lib.rs"));
+        assert_eq!(target.line, Some(10));
+        assert_eq!(target.column, None);
+
+        assert_eq!(
+            parse(r"C:\proj\src\lib.rs").path,
+            PathBuf::from(r"C:\proj\src\lib.rs")
+        );
+    }
+
+    #[test]
+    fn test_parse_file_uri() {
+        let target = parse("file:///home/me/my%20proj/src/lib.rs:3:1");
+        assert_eq!(target.path, PathBuf::from("/home/me/my proj/src/lib.rs"));
+        assert_eq!(target.line, Some(3));
+        assert_eq!(target.column, Some(1));
+
+        assert_eq!(
+            parse("file:///c%3A/proj/src/main.rs:7").path,
+            PathBuf::from("c:/proj/src/main.rs")
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!("".parse::<FileTarget>().is_err());
+        assert!("src/lib.rs:0".parse::<FileTarget>().is_err());
+        assert!(":10".parse::<FileTarget>().is_err());
+    }
+}
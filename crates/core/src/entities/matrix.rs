@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Config;
+
+/// Combinations a runnable is expanded into by `cargo runner matrix`.
+///
+/// ```toml
+/// [test.config.matrix]
+/// toolchains = ["stable", "nightly"]
+/// features = ["default", "all"]
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Matrix {
+    /// Rustup toolchains, passed as `cargo +<toolchain>`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toolchains: Option<Vec<String>>,
+    /// `default`, `all` (`--all-features`), `none` (`--no-default-features`)
+    /// or a comma separated feature list.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+    /// Target triples, passed as `--target <triple>`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub targets: Option<Vec<String>>,
+}
+
+/// One combination of a [Matrix].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatrixCell {
+    pub toolchain: Option<String>,
+    pub features: Option<String>,
+    pub target: Option<String>,
+}
+
+impl Matrix {
+    /// Every combination of the configured axes, toolchains varying slowest.
+    pub fn cells(&self) -> Vec<MatrixCell> {
+        let axis = |values: &Option<Vec<String>>| match values {
+            Some(values) if !values.is_empty() => values.iter().cloned().map(Some).collect(),
+            _ => vec![None],
+        };
+
+        let mut cells = Vec::new();
+        for toolchain in axis(&self.toolchains) {
+            for features in axis(&self.features) {
+                for target in axis(&self.targets) {
+                    cells.push(MatrixCell {
+                        toolchain: toolchain.clone(),
+                        features: features.clone(),
+                        target,
+                    });
+                }
+            }
+        }
+        cells
+    }
+}
+
+impl MatrixCell {
+    /// Arguments for cargo e.g. `--all-features --target wasm32-unknown-unknown`.
+    pub fn cargo_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+
+        match self.features.as_deref() {
+            None | Some("default") => {}
+            Some("all") => args.push("--all-features".to_string()),
+            Some("none") => args.push("--no-default-features".to_string()),
+            Some(features) => args.extend(["--features".to_string(), features.to_string()]),
+        }
+        if let Some(target) = &self.target {
+            args.extend(["--target".to_string(), target.clone()]);
+        }
+
+        args
+    }
+
+    /// The config the cell runs with: a cell on the features axis replaces the feature
+    /// flags of `config` with its own, a cell on the targets axis its target.
+    pub fn config(&self, config: &Config) -> Config {
+        let mut config = config.clone();
+        if self.features.is_some() {
+            config.features = None;
+            config.all_features = None;
+            config.no_default_features = None;
+        }
+        if self.target.is_some() {
+            config.target = None;
+        }
+        config
+    }
+
+    /// A short description of the cell for result tables e.g. `nightly / all`.
+    pub fn label(&self) -> String {
+        let parts: Vec<&str> = [&self.toolchain, &self.features, &self.target]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        if parts.is_empty() {
+            "default".to_string()
+        } else {
+            parts.join(" / ")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matrix_cells() {
+        let matrix: Matrix = toml::from_str(
+            r#"
+            toolchains = ["stable", "nightly"]
+            features = ["default", "all"]
+            "#,
+        )
+        .unwrap();
+
+        let cells = matrix.cells();
+        assert_eq!(cells.len(), 4);
+        assert_eq!(cells[1].label(), "stable / all");
+        assert_eq!(cells[1].cargo_args(), vec!["--all-features"]);
+        assert_eq!(cells[2].toolchain.as_deref(), Some("nightly"));
+        assert_eq!(Matrix::default().cells(), vec![MatrixCell::default()]);
+
+        let config = Config {
+            name: "default".to_string(),
+            features: Some(vec!["fast".to_string()]),
+            no_default_features: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            cells[1].config(&config).feature_args(),
+            Vec::<String>::new()
+        );
+        let toolchains = MatrixCell {
+            toolchain: Some("nightly".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            toolchains.config(&config).feature_args(),
+            vec!["--features", "fast", "--no-default-features"]
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+/// Memory checkers a runnable's binary can be run under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemcheckTool {
+    Valgrind,
+    Heaptrack,
+}
+
+/// Tool paths and options for `exec --valgrind` and `exec --heaptrack`.
+///
+/// ```toml
+/// [test.config.memcheck]
+/// valgrind = "/usr/bin/valgrind"
+/// suppressions = ["valgrind.supp"]
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Memcheck {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub valgrind: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heaptrack: Option<String>,
+    /// Valgrind suppression files.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppressions: Option<Vec<String>>,
+}
+
+impl Memcheck {
+    /// The command line placed before the checked binary.
+    pub fn wrapper(&self, tool: MemcheckTool) -> Vec<String> {
+        match tool {
+            MemcheckTool::Valgrind => {
+                let mut args = vec![
+                    self.valgrind
+                        .clone()
+                        .unwrap_or_else(|| "valgrind".to_string()),
+                    "--leak-check=full".to_string(),
+                    "--error-exitcode=1".to_string(),
+                ];
+                args.extend(
+                    self.suppressions
+                        .iter()
+                        .flatten()
+                        .map(|file| format!("--suppressions={}", file)),
+                );
+                args
+            }
+            MemcheckTool::Heaptrack => {
+                vec![self
+                    .heaptrack
+                    .clone()
+                    .unwrap_or_else(|| "heaptrack".to_string())]
+            }
+        }
+    }
+
+    /// Extra args for a libtest binary: valgrind serializes threads anyway and
+    /// reports are far easier to read when tests don't interleave.
+    pub fn test_args(&self, tool: MemcheckTool) -> Vec<String> {
+        match tool {
+            MemcheckTool::Valgrind => vec!["--test-threads=1".to_string()],
+            MemcheckTool::Heaptrack => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valgrind_wrapper() {
+        let memcheck: Memcheck = toml::from_str(
+            r#"
+            valgrind = "/opt/valgrind/bin/valgrind"
+            suppressions = ["ci.supp"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            memcheck.wrapper(MemcheckTool::Valgrind),
+            vec![
+                "/opt/valgrind/bin/valgrind",
+                "--leak-check=full",
+                "--error-exitcode=1",
+                "--suppressions=ci.supp"
+            ]
+        );
+        assert_eq!(
+            Memcheck::default().wrapper(MemcheckTool::Heaptrack),
+            vec!["heaptrack"]
+        );
+    }
+}
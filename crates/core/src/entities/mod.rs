@@ -1,9 +1,55 @@
+mod alias;
+mod anchor;
 mod config;
 mod command_type;
 mod cargo_runner;
 mod context;
+mod coverage;
+mod cursor_strategy;
+mod debugger;
+mod directive;
+mod detectors;
+mod doc_test;
+mod env_policy;
+mod file_target;
+mod matrix;
+mod memcheck;
+mod module_tests;
+mod overrides;
+mod profiler;
+mod runnable;
+mod scope;
+mod shard;
+mod target_cfg;
+mod test_framework;
+mod test_options;
+mod user_runnable;
+mod watch;
 
+pub use alias::{Alias, Aliases};
+pub use anchor::Anchor;
 pub use config::Config;
 pub use command_type::CommandType;
-pub use cargo_runner::CargoRunner;
+pub use cargo_runner::{CargoRunner, LOCAL_CONFIG_FILE, TEAM_CONFIG_DIR};
 pub use context::Context;
+pub use coverage::CoverageReport;
+pub use cursor_strategy::CursorStrategy;
+pub use debugger::Debugger;
+pub use directive::{Directive, DIRECTIVE_PREFIX};
+pub use detectors::Detectors;
+pub use doc_test::DocTestMode;
+pub use env_policy::EnvPolicy;
+pub use file_target::FileTarget;
+pub use matrix::{Matrix, MatrixCell};
+pub use memcheck::{Memcheck, MemcheckTool};
+pub use module_tests::ModuleTests;
+pub use overrides::Override;
+pub use profiler::Profiler;
+pub use runnable::{Runnable, RunnableKind, TargetKind};
+pub use scope::{ExtendedScope, Scope, ScopeKind};
+pub use shard::Shard;
+pub use target_cfg::Platform;
+pub use test_framework::TestFramework;
+pub use test_options::TestOptions;
+pub use user_runnable::UserRunnable;
+pub use watch::Watch;
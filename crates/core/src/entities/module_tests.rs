@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// How module test runnables select their tests.
+///
+/// ```toml
+/// [test.config.module_tests]
+/// include_nested = false
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ModuleTests {
+    /// Whether running a module also runs the tests of its submodules, `true` by default.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_nested: Option<bool>,
+}
@@ -0,0 +1,98 @@
+use std::{collections::HashMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Runnable, RunnableKind};
+use crate::PathId;
+
+/// Extra args and env applied to the runnables of a [Config](super::Config) that match it.
+///
+/// ```toml
+/// [[run.config.overrides]]
+/// function = "main"
+/// kind = "binary"
+/// args = ["--features", "cli"]
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Override {
+    /// Function path, matched on `::` boundaries so `it_works` matches `tests::it_works`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    /// File path suffix e.g. `src/main.rs`, of the path as given or of the file it
+    /// links to.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<RunnableKind>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub args: Option<Vec<String>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<HashMap<String, String>>,
+    /// Target triple replacing the `target` of the config.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+impl Override {
+    /// Returns `true` if every field that is set matches the runnable. `file_id` is the
+    /// identity of `file_path`, resolved once by the caller.
+    pub fn matches(
+        &self,
+        runnable: &Runnable,
+        file_path: Option<&Path>,
+        file_id: Option<&PathId>,
+    ) -> bool {
+        let function = self.function.as_deref().is_none_or(|function| {
+            runnable
+                .function
+                .as_deref()
+                .is_some_and(|path| path == function || path.ends_with(&format!("::{}", function)))
+        });
+        let file = self.file.as_deref().is_none_or(|file| {
+            file_path.is_some_and(|path| path.ends_with(file))
+                || file_id.is_some_and(|id| id.ends_with(file))
+        });
+        let kind = self.kind.is_none_or(|kind| kind == runnable.kind);
+
+        function && file && kind
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Context;
+
+    #[test]
+    fn test_override_scoped_by_kind() {
+        let main_override: Override = toml::from_str(
+            r#"
+            function = "main"
+            kind = "binary"
+            args = ["--features", "cli"]
+            "#,
+        )
+        .expect("override should parse");
+
+        let mut run = Runnable::new(Context::Run);
+        run.function = Some("main".to_string());
+        assert!(main_override.matches(&run, None, None));
+
+        let mut test = Runnable::new(Context::Test);
+        test.kind = RunnableKind::Test;
+        test.function = Some("tests::main".to_string());
+        assert!(!main_override.matches(&test, None, None));
+
+        let any_kind = Override {
+            kind: None,
+            ..main_override
+        };
+        assert!(any_kind.matches(&test, None, None));
+    }
+}
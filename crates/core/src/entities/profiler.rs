@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The tool `cargo runner profile` wraps the compiled binary with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Profiler {
+    /// `flamegraph` from cargo-flamegraph, writes an SVG.
+    #[default]
+    Flamegraph,
+    /// `samply record`, writes a Firefox Profiler profile.
+    Samply,
+    /// `perf record`, writes `perf.data`.
+    Perf,
+}
+
+const PROFILERS: &[&str] = &["flamegraph", "samply", "perf"];
+
+impl From<Profiler> for &str {
+    fn from(val: Profiler) -> Self {
+        match val {
+            Profiler::Flamegraph => "flamegraph",
+            Profiler::Samply => "samply",
+            Profiler::Perf => "perf",
+        }
+    }
+}
+
+impl TryFrom<&str> for Profiler {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "flamegraph" => Ok(Profiler::Flamegraph),
+            "samply" => Ok(Profiler::Samply),
+            "perf" => Ok(Profiler::Perf),
+            _ => Err(format!(
+                "unknown profiler `{}`, expected one of: {}",
+                value,
+                PROFILERS.join(", ")
+            )),
+        }
+    }
+}
+
+impl Serialize for Profiler {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let s: &str = (*self).into();
+        serializer.serialize_str(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for Profiler {
+    fn deserialize<D>(deserializer: D) -> Result<Profiler, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        Profiler::try_from(s.as_str()).map_err(|_| serde::de::Error::unknown_variant(&s, PROFILERS))
+    }
+}
+
+impl Profiler {
+    /// File extension of the profile written by the tool.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Profiler::Flamegraph => "svg",
+            Profiler::Samply => "json.gz",
+            Profiler::Perf => "data",
+        }
+    }
+
+    /// The command line placed before the profiled binary, writing the profile to `output`.
+    pub fn wrapper(&self, output: &Path) -> Vec<String> {
+        let output = output.display().to_string();
+        let args: &[&str] = match self {
+            Profiler::Flamegraph => &["flamegraph", "-o"],
+            Profiler::Samply => &["samply", "record", "--save-only", "-o"],
+            Profiler::Perf => &["perf", "record", "-g", "-o"],
+        };
+        args.iter()
+            .map(|arg| arg.to_string())
+            .chain([output, "--".to_string()])
+            .collect()
+    }
+}
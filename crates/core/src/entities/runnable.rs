@@ -0,0 +1,244 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{Context, Directive, DocTestMode};
+
+/// The cargo target a file belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetKind {
+    Lib,
+    Bin(String),
+    Example(String),
+    Test(String),
+    Bench(String),
+}
+
+impl TryFrom<&str> for TargetKind {
+    type Error = String;
+
+    /// Parses the [Display] form e.g. `bin:cli`.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let (kind, name) = value.split_once(':').unwrap_or((value, ""));
+        let name = name.to_string();
+        match (kind, name.is_empty()) {
+            ("lib", true) => Ok(TargetKind::Lib),
+            ("bin", false) => Ok(TargetKind::Bin(name)),
+            ("example", false) => Ok(TargetKind::Example(name)),
+            ("test", false) => Ok(TargetKind::Test(name)),
+            ("bench", false) => Ok(TargetKind::Bench(name)),
+            _ => Err(format!(
+                "invalid target `{}`, expected `lib` or `bin|example|test|bench:<name>`",
+                value
+            )),
+        }
+    }
+}
+
+impl Serialize for TargetKind {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for TargetKind {
+    fn deserialize<D>(deserializer: D) -> Result<TargetKind, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        TargetKind::try_from(s.as_str()).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Display for TargetKind {
+    /// `lib`, or the kind and name of the target e.g. `bin:cli`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetKind::Lib => write!(f, "lib"),
+            TargetKind::Bin(name) => write!(f, "bin:{}", name),
+            TargetKind::Example(name) => write!(f, "example:{}", name),
+            TargetKind::Test(name) => write!(f, "test:{}", name),
+            TargetKind::Bench(name) => write!(f, "bench:{}", name),
+        }
+    }
+}
+
+/// What kind of item a [Runnable] runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunnableKind {
+    /// A single test function.
+    Test,
+    /// All tests of a module, file or test target.
+    ModuleTests,
+    /// A binary or example run through its `main`.
+    Binary,
+    Bench,
+    DocTest,
+    Build,
+    /// `cargo check` of the whole package.
+    Check,
+    /// `cargo clippy` of the whole package.
+    Clippy,
+}
+
+impl From<RunnableKind> for &str {
+    fn from(val: RunnableKind) -> Self {
+        match val {
+            RunnableKind::Test => "test",
+            RunnableKind::ModuleTests => "module-tests",
+            RunnableKind::Binary => "binary",
+            RunnableKind::Bench => "bench",
+            RunnableKind::DocTest => "doctest",
+            RunnableKind::Build => "build",
+            RunnableKind::Check => "check",
+            RunnableKind::Clippy => "clippy",
+        }
+    }
+}
+
+impl Serialize for RunnableKind {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let s: &str = (*self).into();
+        serializer.serialize_str(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for RunnableKind {
+    fn deserialize<D>(deserializer: D) -> Result<RunnableKind, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        match s.as_str() {
+            "test" => Ok(RunnableKind::Test),
+            "module-tests" => Ok(RunnableKind::ModuleTests),
+            "binary" => Ok(RunnableKind::Binary),
+            "bench" | "benchmark" => Ok(RunnableKind::Bench),
+            "doctest" => Ok(RunnableKind::DocTest),
+            "build" => Ok(RunnableKind::Build),
+            "check" => Ok(RunnableKind::Check),
+            "clippy" => Ok(RunnableKind::Clippy),
+            _ => Err(serde::de::Error::unknown_variant(
+                &s,
+                &[
+                    "test",
+                    "module-tests",
+                    "binary",
+                    "bench",
+                    "doctest",
+                    "build",
+                    "check",
+                    "clippy",
+                ],
+            )),
+        }
+    }
+}
+
+/// What to run for a location in a file, independent of the command used to run it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Runnable {
+    pub context: Context,
+    pub kind: RunnableKind,
+    pub package: Option<String>,
+    pub target: Option<TargetKind>,
+    /// Test or bench name filter passed to the test binary.
+    pub filter: Option<String>,
+    /// Whether the filter must match exactly e.g. a single test function.
+    pub exact: bool,
+    /// Path of the function this runnable was found in e.g. `math::tests::it_works` or `main`.
+    pub function: Option<String>,
+    /// Full paths of the test modules directly inside the module of a
+    /// [RunnableKind::ModuleTests] runnable, skipped when nested modules are excluded.
+    #[serde(default)]
+    pub nested: Vec<String>,
+    /// How rustdoc runs a [RunnableKind::DocTest] e.g. `no_run` blocks are only compiled.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_test: Option<DocTestMode>,
+    /// The [Directive]s of the item the runnable was found at.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub directives: Vec<Directive>,
+    /// The `required-features` of the target in `Cargo.toml`, without which cargo
+    /// skips it.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub required_features: Vec<String>,
+}
+
+impl Runnable {
+    pub fn new(context: Context) -> Self {
+        let kind = match context {
+            Context::Run => RunnableKind::Binary,
+            Context::Test => RunnableKind::ModuleTests,
+            Context::Bench => RunnableKind::Bench,
+            Context::Build | Context::None => RunnableKind::Build,
+        };
+        Runnable {
+            context,
+            kind,
+            package: None,
+            target: None,
+            filter: None,
+            exact: false,
+            function: None,
+            nested: Vec::new(),
+            doc_test: None,
+            directives: Vec::new(),
+            required_features: Vec::new(),
+        }
+    }
+
+    /// Arguments for cargo itself e.g. `-p core --lib`, with the required features of
+    /// the target e.g. `--bin cli --features cli`.
+    pub fn cargo_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if let Some(package) = &self.package {
+            args.push("-p".to_string());
+            args.push(package.clone());
+        }
+
+        match (&self.target, self.context) {
+            (Some(TargetKind::Lib), Context::Run) | (None, _) => {}
+            (Some(TargetKind::Lib), _) if self.kind == RunnableKind::DocTest => {
+                args.push("--doc".to_string())
+            }
+            (Some(TargetKind::Lib), _) => args.push("--lib".to_string()),
+            (Some(TargetKind::Bin(name)), _) => args.extend(["--bin".to_string(), name.clone()]),
+            (Some(TargetKind::Example(name)), _) => {
+                args.extend(["--example".to_string(), name.clone()])
+            }
+            (Some(TargetKind::Test(name)), _) => args.extend(["--test".to_string(), name.clone()]),
+            (Some(TargetKind::Bench(name)), _) => {
+                args.extend(["--bench".to_string(), name.clone()])
+            }
+        }
+        if self.target.is_some() && !self.required_features.is_empty() {
+            args.push("--features".to_string());
+            args.push(self.required_features.join(","));
+        }
+
+        args
+    }
+
+    /// Whether both run the same command, wherever they were found e.g. a binary's
+    /// file and its `main`.
+    pub fn same_command(&self, other: &Runnable) -> bool {
+        Runnable {
+            function: None,
+            ..self.clone()
+        } == Runnable {
+            function: None,
+            ..other.clone()
+        }
+    }
+}
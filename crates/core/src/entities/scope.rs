@@ -0,0 +1,184 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{Directive, DocTestMode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    File,
+    Module,
+    Impl,
+    Trait,
+    Function,
+    /// A fenced code block of a doc comment rustdoc tests, named after the documented item.
+    DocTest,
+}
+
+const SCOPE_KINDS: &[&str] = &["file", "module", "impl", "trait", "function", "doctest"];
+
+impl From<ScopeKind> for &str {
+    fn from(val: ScopeKind) -> Self {
+        match val {
+            ScopeKind::File => "file",
+            ScopeKind::Module => "module",
+            ScopeKind::Impl => "impl",
+            ScopeKind::Trait => "trait",
+            ScopeKind::Function => "function",
+            ScopeKind::DocTest => "doctest",
+        }
+    }
+}
+
+impl Serialize for ScopeKind {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let s: &str = (*self).into();
+        serializer.serialize_str(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for ScopeKind {
+    fn deserialize<D>(deserializer: D) -> Result<ScopeKind, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        match s.as_str() {
+            "file" => Ok(ScopeKind::File),
+            "module" => Ok(ScopeKind::Module),
+            "impl" => Ok(ScopeKind::Impl),
+            "trait" => Ok(ScopeKind::Trait),
+            "function" => Ok(ScopeKind::Function),
+            "doctest" => Ok(ScopeKind::DocTest),
+            _ => Err(serde::de::Error::unknown_variant(&s, SCOPE_KINDS)),
+        }
+    }
+}
+
+/// The lines around an item that rust-analyzer places its lenses above: the doc
+/// comment first, then the attributes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtendedScope {
+    /// First and last line of the `///` doc comment above the item.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_comment_lines: Option<(usize, usize)>,
+    /// First and last line of the outer attributes of the item.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_lines: Option<(usize, usize)>,
+    /// Whether the doc comment has code blocks rustdoc tests.
+    #[serde(default)]
+    pub has_doc_tests: bool,
+    /// The benchmark id of a criterion bench function, the string given to
+    /// `bench_function` or `benchmark_group`, else the function name.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bench_id: Option<String>,
+    /// The `// cargo-runner: ...` comments right above the item, see [Directive].
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub directives: Vec<Directive>,
+}
+
+/// An item found in a source file. Lines are 1-based and inclusive,
+/// `start_line` includes the attributes placed above the item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub name: String,
+    /// Names of the inline modules enclosing this scope.
+    pub module_path: Vec<String>,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Attributes without `#[` and `]` e.g. `test`, `tokio::test`, `cfg(test)`.
+    pub attributes: Vec<String>,
+    /// Stable across re-scans of the same source: derived from the file hash,
+    /// the kind, path and span of the scope rather than a counter. A
+    /// [crate::ParsedSource] also keeps it across edits.
+    pub id: u64,
+    #[serde(default)]
+    pub extended: ExtendedScope,
+}
+
+impl Scope {
+    pub fn contains(&self, line: usize) -> bool {
+        self.start_line <= line && line <= self.end_line
+    }
+
+    /// Returns `true` if an attribute with the given path is present, ignoring its arguments.
+    pub fn has_attribute(&self, path: &str) -> bool {
+        self.attributes
+            .iter()
+            .any(|attr| attr.split('(').next() == Some(path))
+    }
+
+    pub fn is_test(&self) -> bool {
+        self.kind == ScopeKind::Function
+            && self.attributes.iter().any(|attr| {
+                let path = attr.split('(').next().unwrap_or_default();
+                path == "test" || path.ends_with("::test") || path == "rstest"
+            })
+    }
+
+    /// A `#[bench]` function, or a criterion one taking `&mut Criterion` or listed
+    /// in `criterion_group!`.
+    pub fn is_bench(&self) -> bool {
+        self.kind == ScopeKind::Function
+            && (self.has_attribute("bench") || self.extended.bench_id.is_some())
+    }
+
+    /// How rustdoc runs a [ScopeKind::DocTest], its attributes being the info string words.
+    pub fn doc_test_mode(&self) -> Option<DocTestMode> {
+        match self.kind {
+            ScopeKind::DocTest => DocTestMode::from_fence(&self.attributes.join(",")),
+            _ => None,
+        }
+    }
+
+    pub fn is_cfg_test(&self) -> bool {
+        self.attributes.iter().any(|attr| attr == "cfg(test)")
+    }
+
+    /// Tags from marker attributes e.g. `#[cfg_attr(any(), test_tag = "smoke")]`.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = Vec::new();
+        for attr in &self.attributes {
+            let mut rest = attr.as_str();
+            while let Some(start) = rest.find("test_tag") {
+                rest = rest[start + "test_tag".len()..].trim_start();
+                let Some(value) = rest.strip_prefix('=').map(str::trim_start) else {
+                    continue;
+                };
+                let Some(value) = value.strip_prefix('"') else {
+                    continue;
+                };
+                let Some(end) = value.find('"') else {
+                    break;
+                };
+                tags.push(value[..end].to_string());
+                rest = &value[end + 1..];
+            }
+        }
+        tags
+    }
+
+    /// The line an editor lens for this scope goes on: above its doc comment and attributes.
+    pub fn lens_line(&self) -> usize {
+        self.extended
+            .doc_comment_lines
+            .map_or(self.start_line, |(start, _)| start)
+    }
+
+    /// The `::` separated path of this scope relative to its file.
+    pub fn path(&self) -> String {
+        self.module_path
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(self.name.as_str()))
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+}
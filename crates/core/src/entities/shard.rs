@@ -0,0 +1,108 @@
+use std::fmt::Display;
+
+use crate::scanner::{fnv1a, FNV_OFFSET};
+
+/// One of `count` parts of a test run, `index` counting from 1 like `--shard 2/4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: u32,
+    pub count: u32,
+}
+
+impl TryFrom<&str> for Shard {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let invalid = || format!("invalid shard `{}`, expected `i/n` with 1 <= i <= n", value);
+        let (index, count) = value.split_once('/').ok_or_else(invalid)?;
+        let index: u32 = index.trim().parse().map_err(|_| invalid())?;
+        let count: u32 = count.trim().parse().map_err(|_| invalid())?;
+        if index == 0 || index > count {
+            return Err(invalid());
+        }
+        Ok(Shard { index, count })
+    }
+}
+
+impl Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+impl Shard {
+    /// Whether the test named `name` runs in this shard. Names are hashed so a
+    /// test stays in the same shard as tests are added around it.
+    pub fn contains(&self, name: &str) -> bool {
+        fnv1a(FNV_OFFSET, name.as_bytes()) % self.count as u64 == (self.index - 1) as u64
+    }
+
+    /// The value of nextest's `--partition` for this shard.
+    pub fn partition(&self) -> String {
+        format!("count:{}", self)
+    }
+
+    /// The names of the tests of this shard in `list`, the output of libtest's
+    /// `--list --format terse`.
+    pub fn select(&self, list: &str) -> Vec<String> {
+        list.lines()
+            .filter_map(|line| line.strip_suffix(": test"))
+            .filter(|name| self.contains(name))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Splits the names of [Shard::select] into batches of at most `max_bytes` of names
+    /// each, run one after the other so a large shard stays under the command line
+    /// limit of the OS. Exact filters can't be shortened to a shared module prefix,
+    /// which would also match tests of other shards. A longer name gets its own batch.
+    pub fn batches(names: Vec<String>, max_bytes: usize) -> Vec<Vec<String>> {
+        let mut batches: Vec<Vec<String>> = Vec::new();
+        let mut bytes = 0;
+        for name in names {
+            // Counting the separating space.
+            let size = name.len() + 1;
+            match batches.last_mut() {
+                Some(batch) if bytes + size <= max_bytes => batch.push(name),
+                _ => {
+                    batches.push(vec![name]);
+                    bytes = 0;
+                }
+            }
+            bytes += size;
+        }
+        batches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shards_split_names() {
+        assert_eq!(Shard::try_from("2/4"), Ok(Shard { index: 2, count: 4 }));
+        assert!(Shard::try_from("0/4").is_err());
+        assert!(Shard::try_from("5/4").is_err());
+        assert_eq!(Shard::try_from("2/4").unwrap().partition(), "count:2/4");
+
+        let shards: Vec<Shard> = (1..=3).map(|index| Shard { index, count: 3 }).collect();
+        for name in ["a::one", "a::two", "b::three", "b::four", "c::five"] {
+            let owners = shards.iter().filter(|shard| shard.contains(name)).count();
+            assert_eq!(owners, 1, "{} must run in exactly one shard", name);
+        }
+
+        let list = "a::one: test\na::two: test\nbench_parse: benchmark\n\n2 tests, 1 benchmark\n";
+        let all = Shard { index: 1, count: 1 };
+        assert_eq!(all.select(list), vec!["a::one", "a::two"]);
+        let names = ["a::one", "a::two", "a::three", "a_very_long_name"].map(String::from);
+        assert_eq!(
+            Shard::batches(names.to_vec(), 14),
+            vec![
+                vec!["a::one", "a::two"],
+                vec!["a::three"],
+                vec!["a_very_long_name"]
+            ]
+        );
+    }
+}
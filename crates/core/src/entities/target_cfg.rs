@@ -0,0 +1,157 @@
+use std::env::consts;
+
+/// The platform the key of a `[target.<spec>]` config section is matched against,
+/// the one cargo-runner runs on unless testing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Platform<'a> {
+    pub os: &'a str,
+    pub family: &'a str,
+    pub arch: &'a str,
+}
+
+impl Platform<'static> {
+    pub const HOST: Platform<'static> = Platform {
+        os: consts::OS,
+        family: consts::FAMILY,
+        arch: consts::ARCH,
+    };
+}
+
+impl Platform<'_> {
+    /// Whether the section key `spec` selects this platform. Like cargo's
+    /// `[target.'cfg(..)']`, `spec` is `cfg(windows)`, `cfg(unix)`, `cfg(target_os = "macos")`,
+    /// `cfg(target_family = "..")` or `cfg(target_arch = "..")`, combined with `not`, `any`
+    /// and `all`, or a bare OS or family name e.g. `linux`.
+    ///
+    /// ```toml
+    /// [[target.'cfg(windows)'.test.config]]
+    /// name = "default"
+    /// env = { RUST_MIN_STACK = "8388608" }
+    /// ```
+    pub fn matches(&self, spec: &str) -> Result<bool, String> {
+        let spec = spec.trim();
+        match spec
+            .strip_prefix("cfg(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            Some(predicate) => self.eval(predicate),
+            None if is_ident(spec) => Ok(spec == self.os || spec == self.family),
+            None => Err(format!(
+                "invalid target `{}`, expected `cfg(..)` or an OS name",
+                spec
+            )),
+        }
+    }
+
+    fn eval(&self, predicate: &str) -> Result<bool, String> {
+        let predicate = predicate.trim();
+        if let Some((name, args)) = predicate
+            .strip_suffix(')')
+            .and_then(|rest| rest.split_once('('))
+        {
+            let args = split_args(args)?;
+            return match (name.trim(), args.as_slice()) {
+                ("not", [arg]) => Ok(!self.eval(arg)?),
+                ("any", args) => args
+                    .iter()
+                    .try_fold(false, |any, arg| Ok(any | self.eval(arg)?)),
+                ("all", args) => args
+                    .iter()
+                    .try_fold(true, |all, arg| Ok(all & self.eval(arg)?)),
+                _ => Err(format!("invalid cfg predicate `{}`", predicate)),
+            };
+        }
+
+        if let Some((key, value)) = predicate.split_once('=') {
+            let value = value
+                .trim()
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .ok_or_else(|| format!("expected a quoted value in `{}`", predicate))?;
+            return match key.trim() {
+                "target_os" => Ok(value == self.os),
+                "target_family" => Ok(value == self.family),
+                "target_arch" => Ok(value == self.arch),
+                key => Err(format!("unsupported cfg key `{}`", key)),
+            };
+        }
+
+        match predicate {
+            "windows" | "unix" => Ok(predicate == self.family),
+            _ => Err(format!("unsupported cfg predicate `{}`", predicate)),
+        }
+    }
+}
+
+fn is_ident(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The comma separated arguments of `any(..)` or `all(..)`, without splitting nested ones.
+fn split_args(args: &str) -> Result<Vec<&str>, String> {
+    let mut split = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (at, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| format!("unbalanced parentheses in `{}`", args))?
+            }
+            ',' if depth == 0 => {
+                split.push(&args[start..at]);
+                start = at + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(format!("unbalanced parentheses in `{}`", args));
+    }
+    split.push(&args[start..]);
+    Ok(split
+        .into_iter()
+        .filter(|arg| !arg.trim().is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_platform_matches() {
+        let windows = Platform {
+            os: "windows",
+            family: "windows",
+            arch: "x86_64",
+        };
+        let macos = Platform {
+            os: "macos",
+            family: "unix",
+            arch: "aarch64",
+        };
+        let both = |spec: &str| (windows.matches(spec), macos.matches(spec));
+
+        assert_eq!(both("cfg(windows)"), (Ok(true), Ok(false)));
+        assert_eq!(both("cfg(unix)"), (Ok(false), Ok(true)));
+        assert_eq!(both("macos"), (Ok(false), Ok(true)));
+        assert_eq!(both("unix"), (Ok(false), Ok(true)));
+        assert_eq!(both("cfg(target_os = \"macos\")"), (Ok(false), Ok(true)));
+        assert_eq!(both("cfg(target_arch = \"x86_64\")"), (Ok(true), Ok(false)));
+        assert_eq!(both("cfg(not(windows))"), (Ok(false), Ok(true)));
+        assert_eq!(
+            both("cfg(any(windows, all(unix, target_arch = \"aarch64\")))"),
+            (Ok(true), Ok(true))
+        );
+        assert_eq!(
+            both("cfg(all(unix, not(target_os = \"macos\")))"),
+            (Ok(false), Ok(false))
+        );
+
+        assert!(macos.matches("cfg(feature = \"x\")").is_err());
+        assert!(macos.matches("cfg(not(unix)").is_err());
+        assert!(macos.matches("x86_64-apple-darwin").is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The harness `cargo` test configs run tests with.
+///
+/// ```toml
+/// [[test.config]]
+/// name = "default"
+/// framework_test = "auto"
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TestFramework {
+    /// `cargo test`.
+    #[default]
+    Libtest,
+    /// `cargo nextest run`.
+    Nextest,
+    /// `cargo nextest run` when cargo-nextest is installed, else `cargo test`.
+    Auto,
+    /// `cargo +nightly miri test`, checking for undefined behavior. Set `MIRIFLAGS`
+    /// in the `env` of the config. Miri can't run doc tests.
+    Miri,
+}
+
+const TEST_FRAMEWORKS: &[&str] = &["libtest", "nextest", "auto", "miri"];
+
+impl TryFrom<&str> for TestFramework {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "libtest" => Ok(TestFramework::Libtest),
+            "nextest" => Ok(TestFramework::Nextest),
+            "auto" => Ok(TestFramework::Auto),
+            "miri" => Ok(TestFramework::Miri),
+            _ => Err(format!(
+                "invalid test framework `{}`, expected one of: {}",
+                value,
+                TEST_FRAMEWORKS.join(", ")
+            )),
+        }
+    }
+}
+
+impl From<TestFramework> for &str {
+    fn from(val: TestFramework) -> Self {
+        match val {
+            TestFramework::Libtest => "libtest",
+            TestFramework::Nextest => "nextest",
+            TestFramework::Auto => "auto",
+            TestFramework::Miri => "miri",
+        }
+    }
+}
+
+impl Serialize for TestFramework {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let s: &str = (*self).into();
+        serializer.serialize_str(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for TestFramework {
+    fn deserialize<D>(deserializer: D) -> Result<TestFramework, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        TestFramework::try_from(s.as_str())
+            .map_err(|_| serde::de::Error::unknown_variant(&s, TEST_FRAMEWORKS))
+    }
+}
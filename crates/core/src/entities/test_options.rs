@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ArgStrategy, CommandArgs};
+
+/// Common options of the test binary, lowered to the flags of the tool running
+/// the tests instead of keeping a list of args per framework.
+///
+/// ```toml
+/// [[test.config]]
+/// name = "default"
+/// test_options = { threads = 1, nocapture = true, color = "always" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestOptions {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threads: Option<u32>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nocapture: Option<bool>,
+    /// libtest output format e.g. `pretty` or `terse`, nextest has no equivalent.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// `auto`, `always` or `never`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl TestOptions {
+    /// Layers `other` on top, options it sets win.
+    pub fn merge(&mut self, other: &TestOptions) {
+        if let Some(threads) = other.threads {
+            self.threads = Some(threads);
+        }
+        if let Some(nocapture) = other.nocapture {
+            self.nocapture = Some(nocapture);
+        }
+        if let Some(format) = &other.format {
+            self.format = Some(format.clone());
+        }
+        if let Some(color) = &other.color {
+            self.color = Some(color.clone());
+        }
+    }
+
+    /// Adds the flags of the options for `strategy` to `args`. `harness = false`
+    /// targets parse their own args, they get none.
+    pub fn apply(&self, args: &mut CommandArgs, strategy: ArgStrategy) {
+        match strategy {
+            ArgStrategy::Libtest => {
+                if let Some(threads) = self.threads {
+                    args.binary.push(format!("--test-threads={}", threads));
+                }
+                if self.nocapture == Some(true) {
+                    args.binary.push("--nocapture".to_string());
+                }
+                if let Some(format) = &self.format {
+                    args.binary.push(format!("--format={}", format));
+                }
+                if let Some(color) = &self.color {
+                    args.binary.push(format!("--color={}", color));
+                }
+            }
+            ArgStrategy::Nextest => {
+                if let Some(threads) = self.threads {
+                    args.cargo.push(format!("--test-threads={}", threads));
+                }
+                if self.nocapture == Some(true) {
+                    args.cargo.push("--no-capture".to_string());
+                }
+                if let Some(color) = &self.color {
+                    args.cargo.push(format!("--color={}", color));
+                }
+            }
+            ArgStrategy::NoHarness => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_per_strategy() {
+        let options = TestOptions {
+            threads: Some(1),
+            nocapture: Some(true),
+            format: Some("terse".to_string()),
+            color: None,
+        };
+        let lowered = |strategy: ArgStrategy| {
+            let mut args = CommandArgs::default();
+            options.apply(&mut args, strategy);
+            (args.cargo, args.binary)
+        };
+
+        assert_eq!(
+            lowered(ArgStrategy::Libtest),
+            (
+                vec![],
+                vec![
+                    "--test-threads=1".to_string(),
+                    "--nocapture".to_string(),
+                    "--format=terse".to_string()
+                ]
+            )
+        );
+        assert_eq!(
+            lowered(ArgStrategy::Nextest),
+            (
+                vec!["--test-threads=1".to_string(), "--no-capture".to_string()],
+                vec![]
+            )
+        );
+        assert_eq!(lowered(ArgStrategy::NoHarness), (vec![], vec![]));
+    }
+}
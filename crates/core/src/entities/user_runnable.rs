@@ -0,0 +1,119 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use ignore::gitignore::GitignoreBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::{find_package_root, find_workspace_root, CargoRunner, Context};
+
+/// A runnable declared in config instead of detected: a fixed command offered on the
+/// files matching `files`, next to the runnables found in them.
+///
+/// ```toml
+/// [[test.config]]
+/// name = "default"
+/// runnables = [
+///     { name = "Run migration tests", files = ["migrations/"], command = ["cargo", "test", "-p", "db", "--test", "migrations"] },
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserRunnable {
+    pub name: String,
+    /// Gitignore-style patterns relative to the workspace root e.g. `migrations/` or `*.sql`.
+    pub files: Vec<String>,
+    /// The program and its args, run from the workspace root.
+    pub command: Vec<String>,
+}
+
+impl UserRunnable {
+    /// Whether `path` matches one of the `files` patterns.
+    pub fn matches(&self, path: &Path) -> bool {
+        let path = &std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let root = workspace_dir(path);
+        let mut builder = GitignoreBuilder::new(&root);
+        for pattern in &self.files {
+            if builder.add_line(None, pattern).is_err() {
+                return false;
+            }
+        }
+        builder.build().is_ok_and(|globs| {
+            globs
+                .matched_path_or_any_parents(path, path.is_dir())
+                .is_ignore()
+        })
+    }
+
+    /// The command for the workspace of `path`, `None` when none is configured.
+    pub fn command(&self, path: &Path) -> Option<Command> {
+        let (program, args) = self.command.split_first()?;
+        let mut command = Command::new(program);
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        command.args(args).current_dir(workspace_dir(&path));
+        Some(command)
+    }
+}
+
+/// The workspace root of `path`, else its package root, else its directory.
+fn workspace_dir(path: &Path) -> PathBuf {
+    find_workspace_root(path)
+        .or_else(|| find_package_root(path))
+        .unwrap_or_else(|| path.parent().unwrap_or(path).to_path_buf())
+}
+
+impl CargoRunner {
+    /// The runnables of the default config of every context that match `path`,
+    /// without duplicate names.
+    pub fn user_runnables(&self, path: &Path) -> Vec<&UserRunnable> {
+        let mut found: Vec<&UserRunnable> = Vec::new();
+        for runnable in self.declared_runnables() {
+            if runnable.matches(path) && !found.iter().any(|f| f.name == runnable.name) {
+                found.push(runnable);
+            }
+        }
+        found
+    }
+
+    /// The runnable named `name` of the default config of a context.
+    pub fn user_runnable(&self, name: &str) -> Option<&UserRunnable> {
+        self.declared_runnables()
+            .find(|runnable| runnable.name == name)
+    }
+
+    fn declared_runnables(&self) -> impl Iterator<Item = &UserRunnable> {
+        [Context::Run, Context::Build, Context::Test, Context::Bench]
+            .into_iter()
+            .filter_map(|context| {
+                self.get_default(context)
+                    .and_then(|name| self.find(context, name))
+            })
+            .flat_map(|config| config.runnables.iter().flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(
+            root.path().join("Cargo.toml"),
+            "[package]\nname = \"db\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        let runnable = UserRunnable {
+            name: "Run migration tests".to_string(),
+            files: vec!["migrations/".to_string(), "*.sql".to_string()],
+            command: vec!["cargo".to_string(), "test".to_string()],
+        };
+
+        assert!(runnable.matches(&root.path().join("migrations").join("v1.rs")));
+        assert!(runnable.matches(&root.path().join("schema.sql")));
+        assert!(!runnable.matches(&root.path().join("src").join("lib.rs")));
+        let command = runnable.command(&root.path().join("schema.sql")).unwrap();
+        assert_eq!(command.get_current_dir(), Some(root.path()));
+    }
+}
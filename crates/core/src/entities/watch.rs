@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// How `cargo runner watch` reacts to changes in the workspace.
+///
+/// ```toml
+/// [[test.config]]
+/// name = "default"
+/// watch = { ignore = ["*.md", "fixtures/"], debounce_ms = 500 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Watch {
+    /// Gitignore-style patterns, relative to the workspace root, of files whose
+    /// changes don't trigger a re-run. `target/` and `.git/` are always ignored.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// How long the workspace must be quiet after a change before re-running.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debounce_ms: Option<u64>,
+}
@@ -16,6 +16,15 @@ pub enum Error {
     #[error("Config Merge Conflict, Name doesnt match: {0} and {1}")]
     MergeConflict(String,String),
 
+    #[error("could not find Cargo.toml for: {0}")]
+    CargoTomlNotFound(String),
+
+    #[error("invalid target, expected `path[:line[:column]]`: {0}")]
+    InvalidTarget(String),
+
+    #[error("{0} belongs to no member of the virtual workspace, members: {1}")]
+    NoMemberPackage(String, String),
+
     #[error("Unknown error: {0}")]
     Other(#[from] anyhow::Error), 
 }
\ No newline at end of file
@@ -0,0 +1,329 @@
+use std::{
+    env,
+    io::{BufRead, BufReader, Read, Write},
+    path::PathBuf,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, PathTranslator};
+
+/// How often a run with a timeout checks whether its process exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How a command run by [Execution] ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionResult {
+    /// Exit code, `None` when the process was killed by a signal.
+    pub code: Option<i32>,
+    /// The signal that killed the process, on unix.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+    pub duration: Duration,
+    /// Whether the process was killed for running past the timeout.
+    #[serde(default)]
+    pub timed_out: bool,
+}
+
+impl ExecutionResult {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Runs a [Command] to completion, streaming its output.
+///
+/// Output goes straight to the terminal unless lines are prefixed or their paths
+/// translated. Without a timeout the process stays in the terminal's process group,
+/// so Ctrl-C reaches it as it would when run by hand. With one it leads its own group,
+/// so the timeout kills every process it started, and Ctrl-C is passed on to the group.
+pub struct Execution<'a> {
+    command: &'a mut Command,
+    prefix: Option<String>,
+    translator: Option<PathTranslator>,
+    timeout: Option<Duration>,
+}
+
+impl<'a> Execution<'a> {
+    pub fn new(command: &'a mut Command) -> Self {
+        Execution {
+            command,
+            prefix: None,
+            translator: None,
+            timeout: None,
+        }
+    }
+
+    /// Prefixes every line of output e.g. `[shard 1/4] `, to tell interleaved runs apart.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Rewrites the paths printed by the command, see [PathTranslator].
+    pub fn translator(mut self, translator: PathTranslator) -> Self {
+        self.translator = Some(translator).filter(|translator| !translator.is_identity());
+        self
+    }
+
+    /// Kills the process once it has run for `timeout`.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Spawns the command, forwarding its output, and waits for it to exit.
+    #[tracing::instrument(skip_all)]
+    pub fn run(self) -> Result<ExecutionResult, Error> {
+        let started = Instant::now();
+        let piped = self.prefix.is_some() || self.translator.is_some();
+        if piped {
+            self.command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        #[cfg(unix)]
+        if self.timeout.is_some() {
+            use std::os::unix::process::CommandExt;
+            self.command.process_group(0);
+        }
+        let mut child = self.command.spawn()?;
+
+        let mut forwarders = Vec::new();
+        if piped {
+            let prefix = self.prefix.unwrap_or_default();
+            let stdout = child.stdout.take().expect("stdout is piped");
+            let stderr = child.stderr.take().expect("stderr is piped");
+            let (out_prefix, out_translator) = (prefix.clone(), self.translator.clone());
+            forwarders.push(thread::spawn(move || {
+                forward(
+                    stdout,
+                    std::io::stdout(),
+                    &out_prefix,
+                    out_translator.as_ref(),
+                )
+            }));
+            forwarders.push(thread::spawn(move || {
+                forward(stderr, std::io::stderr(), &prefix, self.translator.as_ref())
+            }));
+        }
+
+        let (status, timed_out) = wait(&mut child, self.timeout)?;
+        // A process that escaped the kill may hold the pipes open, don't wait for it.
+        if !timed_out {
+            for forwarder in forwarders {
+                let _ = forwarder.join();
+            }
+        }
+
+        Ok(ExecutionResult {
+            code: status.code(),
+            signal: signal(&status),
+            duration: started.elapsed(),
+            timed_out,
+        })
+    }
+}
+
+/// Waits for `child`, killing it past `timeout`.
+fn wait(child: &mut Child, timeout: Option<Duration>) -> Result<(ExitStatus, bool), Error> {
+    let Some(timeout) = timeout else {
+        return Ok((child.wait()?, false));
+    };
+
+    let deadline = Instant::now() + timeout;
+    #[cfg(unix)]
+    let mut interrupts = group::Interrupts::catch();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status, false));
+        }
+        if Instant::now() >= deadline {
+            kill_tree(child)?;
+            return Ok((child.wait()?, true));
+        }
+        #[cfg(unix)]
+        if interrupts.received() {
+            group::signal(child, libc::SIGINT)?;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Kills `child` and, on unix, the processes it started in its group.
+#[cfg(unix)]
+fn kill_tree(child: &mut Child) -> Result<(), Error> {
+    group::signal(child, libc::SIGKILL)
+}
+
+#[cfg(not(unix))]
+fn kill_tree(child: &mut Child) -> Result<(), Error> {
+    Ok(child.kill()?)
+}
+
+/// The process group a run with a timeout spawns its command in.
+#[cfg(unix)]
+mod group {
+    use std::{
+        process::Child,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use crate::Error;
+
+    static INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        INTERRUPTS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Sends `signal` to the process group `child` leads.
+    pub(super) fn signal(child: &Child, signal: libc::c_int) -> Result<(), Error> {
+        // SAFETY: kill only reads its arguments.
+        let sent = unsafe { libc::kill(-(child.id() as libc::pid_t), signal) };
+        let error = std::io::Error::last_os_error();
+        match sent {
+            0 => Ok(()),
+            // The group already exited.
+            _ if error.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+            _ => Err(error.into()),
+        }
+    }
+
+    /// Counts the Ctrl-C presses meant for the group, which no longer gets them from the
+    /// terminal, until dropped.
+    pub(super) struct Interrupts {
+        previous: libc::sighandler_t,
+        seen: usize,
+    }
+
+    impl Interrupts {
+        pub(super) fn catch() -> Self {
+            let handler = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            // SAFETY: the handler only touches an atomic.
+            let previous = unsafe { libc::signal(libc::SIGINT, handler) };
+            Interrupts {
+                previous,
+                seen: INTERRUPTS.load(Ordering::SeqCst),
+            }
+        }
+
+        /// Whether Ctrl-C was pressed since the last call.
+        pub(super) fn received(&mut self) -> bool {
+            let count = INTERRUPTS.load(Ordering::SeqCst);
+            std::mem::replace(&mut self.seen, count) != count
+        }
+    }
+
+    impl Drop for Interrupts {
+        fn drop(&mut self) {
+            // SAFETY: restores the handler `catch` replaced.
+            unsafe { libc::signal(libc::SIGINT, self.previous) };
+        }
+    }
+}
+
+/// Whether cargo-nextest is installed, probed once per process.
+pub(crate) fn nextest_installed() -> bool {
+    static INSTALLED: OnceLock<bool> = OnceLock::new();
+    *INSTALLED.get_or_init(|| installed("cargo-nextest"))
+}
+
+/// Whether `binary` is on `PATH` or in `$CARGO_HOME/bin`, where cargo finds subcommands.
+fn installed(binary: &str) -> bool {
+    let cargo_bin = env::var_os("CARGO_HOME").map(|home| PathBuf::from(home).join("bin"));
+    env::var_os("PATH")
+        .iter()
+        .flat_map(env::split_paths)
+        .chain(cargo_bin)
+        .any(|dir| dir.join(binary).is_file() || dir.join(format!("{}.exe", binary)).is_file())
+}
+
+#[cfg(unix)]
+fn signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+fn forward(
+    source: impl Read,
+    mut sink: impl Write,
+    prefix: &str,
+    translator: Option<&PathTranslator>,
+) {
+    // Lines are bytes, output that isn't UTF-8 passes through untouched unless translated.
+    let mut source = BufReader::new(source);
+    let (mut line, mut out) = (Vec::new(), Vec::new());
+    loop {
+        line.clear();
+        match source.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = line.strip_suffix(b"\n").unwrap_or(&line);
+        out.clear();
+        out.extend_from_slice(prefix.as_bytes());
+        match translator {
+            Some(translator) => out.extend_from_slice(
+                translator
+                    .translate_line(&String::from_utf8_lossy(line))
+                    .as_bytes(),
+            ),
+            None => out.extend_from_slice(line),
+        }
+        out.push(b'\n');
+        let _ = sink.write_all(&out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_with_timeout() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "exit 3"]);
+        let result = Execution::new(&mut command).prefix("> ").run().unwrap();
+        assert_eq!(result.code, Some(3));
+        assert!(!result.timed_out);
+
+        let mut command = Command::new("sleep");
+        command.arg("5");
+        let result = Execution::new(&mut command)
+            .timeout(Some(Duration::from_millis(100)))
+            .run()
+            .unwrap();
+        assert!(result.timed_out);
+        assert!(!result.success());
+        assert_eq!(result.signal, Some(9));
+
+        // The timeout kills the processes the command started, which hold the pipes.
+        let mut command = Command::new("sh");
+        command.args(["-c", "sleep 5; echo late"]);
+        let started = Instant::now();
+        let result = Execution::new(&mut command)
+            .prefix("> ")
+            .timeout(Some(Duration::from_millis(200)))
+            .run()
+            .unwrap();
+        assert!(result.timed_out);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_forward_bytes() {
+        let mut out = Vec::new();
+        forward(&b"a\n\xff\nafter"[..], &mut out, "> ", None);
+        assert_eq!(out, b"> a\n> \xff\n> after\n");
+    }
+}
@@ -0,0 +1,199 @@
+use std::{fmt::Display, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ArgStrategy, Error, Runnable, RunnableKind, TargetKind};
+
+/// What a package's manifest tells about how its runnables should run, e.g. a
+/// `criterion` dev-dependency means benches don't use the libtest harness.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heuristics {
+    /// Names of the `[dev-dependencies]` of the package.
+    #[serde(default)]
+    pub dev_dependencies: Vec<String>,
+    /// Whether the package declares `[features]`.
+    #[serde(default)]
+    pub features: bool,
+    /// Targets declared with `harness = false`, they parse their own args instead of libtest.
+    #[serde(default)]
+    pub no_harness: Vec<TargetKind>,
+}
+
+/// A default [Heuristics] primes for a runnable, applied before config args and overrides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hint {
+    /// Benches run by `criterion` take their filter without `--exact`.
+    Strategy(ArgStrategy),
+    /// Doc tests of a package with features enable them all, so feature-gated examples run.
+    CargoArg(String),
+    /// `rstest` cases run as `<test>::case_<n>`, so the test name must match as a prefix.
+    Inexact,
+}
+
+impl Display for Hint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hint::Strategy(_) => write!(
+                f,
+                "criterion is a dev-dependency: bench filters are passed without --exact"
+            ),
+            Hint::CargoArg(arg) => {
+                write!(f, "the package has features: doc tests run with {}", arg)
+            }
+            Hint::Inexact => write!(
+                f,
+                "rstest is a dev-dependency: test names match as a prefix of their cases"
+            ),
+        }
+    }
+}
+
+impl Heuristics {
+    /// Reads the `Cargo.toml` of `package_root`, no manifest gives no heuristics.
+    #[tracing::instrument(skip_all)]
+    pub fn detect(package_root: &Path) -> Result<Self, Error> {
+        let manifest = match fs::read_to_string(package_root.join("Cargo.toml")) {
+            Ok(manifest) => manifest,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Heuristics::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let table: toml::Table = toml::from_str(&manifest)?;
+
+        let dev_dependencies = table
+            .get("dev-dependencies")
+            .and_then(toml::Value::as_table)
+            .map(|deps| deps.keys().cloned().collect())
+            .unwrap_or_default();
+        let features = table
+            .get("features")
+            .and_then(toml::Value::as_table)
+            .is_some_and(|features| !features.is_empty());
+
+        let without_harness = |target: &toml::Value| {
+            target.get("harness").and_then(toml::Value::as_bool) == Some(false)
+        };
+        let mut no_harness = Vec::new();
+        if table.get("lib").is_some_and(without_harness) {
+            no_harness.push(TargetKind::Lib);
+        }
+        for (section, kind) in [
+            ("test", TargetKind::Test as fn(String) -> TargetKind),
+            ("bench", TargetKind::Bench),
+        ] {
+            let targets = table.get(section).and_then(toml::Value::as_array);
+            for target in targets.into_iter().flatten() {
+                let name = target.get("name").and_then(toml::Value::as_str);
+                if let (Some(name), true) = (name, without_harness(target)) {
+                    no_harness.push(kind(name.to_string()));
+                }
+            }
+        }
+
+        Ok(Heuristics {
+            dev_dependencies,
+            features,
+            no_harness,
+        })
+    }
+
+    /// Whether `target` runs on the libtest harness, which is cargo's default.
+    pub fn harness(&self, target: &TargetKind) -> bool {
+        !self.no_harness.contains(target)
+    }
+
+    fn has_dev_dependency(&self, name: &str) -> bool {
+        self.dev_dependencies.iter().any(|dep| dep == name)
+    }
+
+    /// The defaults that apply to `runnable`.
+    pub fn hints(&self, runnable: &Runnable) -> Vec<Hint> {
+        let mut hints = Vec::new();
+        match runnable.kind {
+            RunnableKind::Bench if self.has_dev_dependency("criterion") => {
+                hints.push(Hint::Strategy(ArgStrategy::NoHarness));
+            }
+            RunnableKind::DocTest if self.features => {
+                hints.push(Hint::CargoArg("--all-features".to_string()));
+            }
+            RunnableKind::Test if runnable.exact && self.has_dev_dependency("rstest") => {
+                hints.push(Hint::Inexact);
+            }
+            _ => {}
+        }
+        hints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Context, TargetKind};
+
+    #[test]
+    fn test_hints_from_manifest() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(
+            root.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n\n[features]\nfast = []\n\n[dev-dependencies]\ncriterion = \"0.5\"\n\n[[bench]]\nname = \"parse\"\nharness = false\n",
+        )
+        .unwrap();
+        let heuristics = Heuristics::detect(root.path()).unwrap();
+
+        let bench = Runnable {
+            target: Some(TargetKind::Bench("parse".to_string())),
+            ..Runnable::new(Context::Bench)
+        };
+        assert_eq!(
+            heuristics.hints(&bench),
+            vec![Hint::Strategy(ArgStrategy::NoHarness)]
+        );
+        let test = Runnable {
+            kind: RunnableKind::Test,
+            exact: true,
+            ..Runnable::new(Context::Test)
+        };
+        assert!(heuristics.hints(&test).is_empty());
+
+        assert!(!heuristics.harness(&TargetKind::Bench("parse".to_string())));
+        assert!(heuristics.harness(&TargetKind::Lib));
+
+        // The manifest is not a guess, `heuristics = false` still honors it.
+        let config = crate::Config {
+            name: "default".to_string(),
+            sub_command: Some("bench".to_string()),
+            heuristics: Some(false),
+            ..Default::default()
+        };
+        let context = crate::BuildContext {
+            heuristics: heuristics.clone(),
+            ..Default::default()
+        };
+        let bench = Runnable {
+            package: Some("demo".to_string()),
+            filter: Some("parse_small".to_string()),
+            exact: true,
+            ..bench
+        };
+        let command = crate::CommandBuilder::new(&config)
+            .runnable(&bench)
+            .context(&context)
+            .build()
+            .unwrap();
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            vec![
+                "bench",
+                "-p",
+                "demo",
+                "--bench",
+                "parse",
+                "--",
+                "parse_small"
+            ]
+        );
+
+        let missing = Heuristics::detect(&root.path().join("missing")).unwrap();
+        assert_eq!(missing, Heuristics::default());
+    }
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{scan, Scope};
+
+/// A 0-based line and a column in UTF-16 code units, as LSP counts them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextRange {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// A change to a document, the `TextDocumentContentChangeEvent` of an LSP
+/// `didChange`: `text` replaces `range`, or the whole document without one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextEdit {
+    #[serde(default)]
+    pub range: Option<TextRange>,
+    pub text: String,
+}
+
+/// The text of an open document with its scopes, kept up to date edit by edit so an
+/// editor host only sends what changed. A scope keeps its id across edits as long as
+/// its kind and path stay the same, so hosts can follow it while the file is typed in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedSource {
+    text: String,
+    scopes: Vec<Scope>,
+}
+
+impl ParsedSource {
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        let scopes = scan(&text);
+        ParsedSource { text, scopes }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    /// Applies `edits` in order, each on the text the previous one left, then scans
+    /// the result once.
+    pub fn edit(&mut self, edits: &[TextEdit]) {
+        for edit in edits {
+            match &edit.range {
+                Some(range) => {
+                    let start = offset(&self.text, range.start);
+                    let end = offset(&self.text, range.end).max(start);
+                    self.text.replace_range(start..end, &edit.text);
+                }
+                None => self.text = edit.text.clone(),
+            }
+        }
+
+        let mut previous = std::mem::replace(&mut self.scopes, scan(&self.text));
+        for scope in &mut self.scopes {
+            if let Some(at) = previous
+                .iter()
+                .position(|old| old.kind == scope.kind && old.path() == scope.path())
+            {
+                scope.id = previous.remove(at).id;
+            }
+        }
+    }
+}
+
+/// The byte offset of `position` in `text`, clamped to the end of its line and to
+/// the end of the text. `\r\n`, `\n` and a lone `\r` all end a line.
+fn offset(text: &str, position: Position) -> usize {
+    let line_end = |from: usize| text[from..].find(['\n', '\r']).map(|at| from + at);
+    let mut start = 0;
+    for _ in 0..position.line {
+        start = match line_end(start) {
+            Some(end) if text[end..].starts_with("\r\n") => end + 2,
+            Some(end) => end + 1,
+            None => return text.len(),
+        };
+    }
+
+    let end = line_end(start).unwrap_or(text.len());
+    let mut units = 0;
+    for (at, c) in text[start..end].char_indices() {
+        if units >= position.character {
+            return start + at;
+        }
+        units += c.len_utf16() as u32;
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsed_source_edits() {
+        let edit =
+            |(start_line, start_character), (end_line, end_character), text: &str| TextEdit {
+                range: Some(TextRange {
+                    start: Position {
+                        line: start_line,
+                        character: start_character,
+                    },
+                    end: Position {
+                        line: end_line,
+                        character: end_character,
+                    },
+                }),
+                text: text.to_string(),
+            };
+        let mut source = ParsedSource::new(
+            "fn main() {}\r\n\r\n#[cfg(test)]\r\nmod tests {\r\n    #[test]\r\n    fn it_works() { let _ = \"🦀\"; }\r\n}\r\n",
+        );
+        let ids = |source: &ParsedSource| -> Vec<(String, u64)> {
+            source
+                .scopes()
+                .iter()
+                .map(|scope| (scope.path(), scope.id))
+                .collect()
+        };
+        let before = ids(&source);
+
+        // The crab is two UTF-16 units, the edit lands after the `;` following it.
+        source.edit(&[
+            edit((5, 33), (5, 33), " assert!(true);"),
+            edit((0, 0), (0, 0), "use std::fmt;\n\n"),
+        ]);
+        assert_eq!(
+            source.text().lines().nth(7),
+            Some("    fn it_works() { let _ = \"🦀\"; assert!(true); }")
+        );
+        let after = ids(&source);
+        assert_eq!(after, before);
+        let lines: Vec<_> = source
+            .scopes()
+            .iter()
+            .map(|scope| scope.start_line)
+            .collect();
+        let fresh: Vec<_> = scan(source.text())
+            .iter()
+            .map(|scope| scope.start_line)
+            .collect();
+        assert_eq!(lines, fresh);
+
+        source.edit(&[TextEdit {
+            range: None,
+            text: "fn other() {}\n".to_string(),
+        }]);
+        let after: Vec<_> = ids(&source).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(after, vec!["".to_string(), "other".to_string()]);
+        assert_eq!(source.scopes()[0].id, before[0].1);
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use crate::{Runnable, RunnableKind, TargetKind};
+
+/// Placeholders a label template may use.
+pub const LABEL_PLACEHOLDERS: &[&str] = &[
+    "{name}",
+    "{path}",
+    "{module}",
+    "{package}",
+    "{target}",
+    "{kind}",
+    "{mode}",
+];
+
+/// The template of `kind` when the config has none for it.
+fn default_template(kind: RunnableKind) -> &'static str {
+    match kind {
+        RunnableKind::Test => "Run test '{name}'",
+        RunnableKind::ModuleTests => "Run tests in '{path}'",
+        RunnableKind::Binary => "Run '{name}'",
+        RunnableKind::Bench => "Run bench '{name}'",
+        RunnableKind::DocTest => "Run doc test '{path}'",
+        RunnableKind::Build => "Build '{name}'",
+        RunnableKind::Check => "Check '{package}'",
+        RunnableKind::Clippy => "Clippy '{package}'",
+    }
+}
+
+/// The human readable label of `runnable` e.g. `Run test 'it_works'`, from the template
+/// `templates` has for its kind (`test`, `module-tests`, ...) or the default one.
+///
+/// ```toml
+/// [[test.config]]
+/// name = "default"
+/// labels = { test = "{package}: {path}" }
+/// ```
+pub fn label(runnable: &Runnable, templates: Option<&HashMap<String, String>>) -> String {
+    let kind: &str = runnable.kind.into();
+    let template = templates
+        .and_then(|templates| templates.get(kind))
+        .map(String::as_str)
+        .unwrap_or_else(|| default_template(runnable.kind));
+
+    let target_name = runnable.target.as_ref().map(|target| match target {
+        TargetKind::Lib => runnable
+            .package
+            .clone()
+            .unwrap_or_else(|| "lib".to_string()),
+        TargetKind::Bin(name)
+        | TargetKind::Example(name)
+        | TargetKind::Test(name)
+        | TargetKind::Bench(name) => name.clone(),
+    });
+    let path = runnable
+        .function
+        .clone()
+        .or_else(|| runnable.filter.clone())
+        .or_else(|| target_name.clone())
+        .or_else(|| runnable.package.clone())
+        .unwrap_or_default();
+    let name = match runnable.kind {
+        RunnableKind::Binary | RunnableKind::Build => target_name
+            .clone()
+            .or_else(|| runnable.package.clone())
+            .unwrap_or_else(|| path.clone()),
+        _ => path
+            .rsplit_once("::")
+            .map(|(_, name)| name.to_string())
+            .unwrap_or_else(|| path.clone()),
+    };
+    let module = path
+        .rsplit_once("::")
+        .map(|(module, _)| module.to_string())
+        .unwrap_or_default();
+
+    [
+        ("{name}", name),
+        ("{path}", path),
+        ("{module}", module),
+        ("{package}", runnable.package.clone().unwrap_or_default()),
+        (
+            "{target}",
+            runnable
+                .target
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+        ),
+        ("{kind}", kind.to_string()),
+        (
+            "{mode}",
+            runnable
+                .doc_test
+                .map(|mode| mode.to_string())
+                .unwrap_or_default(),
+        ),
+    ]
+    .iter()
+    .fold(template.to_string(), |label, (placeholder, value)| {
+        label.replace(placeholder, value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Context;
+
+    #[test]
+    fn test_label_templates() {
+        let runnable = Runnable {
+            kind: RunnableKind::Test,
+            package: Some("demo".to_string()),
+            target: Some(TargetKind::Lib),
+            filter: Some("math::tests::it_adds".to_string()),
+            exact: true,
+            function: Some("math::tests::it_adds".to_string()),
+            ..Runnable::new(Context::Test)
+        };
+        assert_eq!(label(&runnable, None), "Run test 'it_adds'");
+
+        let templates = HashMap::from([(
+            "test".to_string(),
+            "{package} [{target}] {module} > {name}".to_string(),
+        )]);
+        assert_eq!(
+            label(&runnable, Some(&templates)),
+            "demo [lib] math::tests > it_adds"
+        );
+
+        let binary = Runnable {
+            package: Some("demo".to_string()),
+            target: Some(TargetKind::Bin("server".to_string())),
+            function: Some("main".to_string()),
+            ..Runnable::new(Context::Run)
+        };
+        assert_eq!(label(&binary, Some(&templates)), "Run 'server'");
+    }
+}
@@ -2,4 +2,78 @@ mod entities;
 
 pub use entities::*;
 mod errors;
-pub use errors::Error;
\ No newline at end of file
+pub use errors::Error;
+mod path_id;
+pub use path_id::PathId;
+mod workspace;
+pub use workspace::{
+    find_claiming_member, find_package_name, find_package_root, find_workspace_root,
+    is_virtual_manifest, workspace_members,
+};
+mod command_builder;
+pub use command_builder::CommandBuilder;
+mod path_translation;
+pub use path_translation::PathTranslator;
+mod scanner;
+pub use scanner::scan;
+mod source;
+pub use source::{read_source, NormalizedSource};
+mod incremental;
+pub use incremental::{ParsedSource, Position, TextEdit, TextRange};
+mod context_finder;
+pub use context_finder::{
+    ContextFinder, Detection, DetectionBudget, FoundRunnable, Rejection, DEFAULT_SCOPE_ORDER,
+};
+mod migrate;
+pub use migrate::Migration;
+mod command_args;
+pub use command_args::{ArgStrategy, CommandArgs};
+mod tags;
+pub use tags::{find_tagged, TaggedTests};
+mod debug_plan;
+pub use debug_plan::{DapConfiguration, DebugLaunch, DebugPlan};
+mod build_context;
+pub use build_context::BuildContext;
+mod analyze;
+pub use analyze::{
+    analyze, analyze_files, analyze_within, find_by_name, qualified_name, AnalysisSummary,
+    FileAnalysis,
+};
+mod heuristics;
+pub use heuristics::{Heuristics, Hint};
+mod cargo_config;
+pub use cargo_config::CargoConfig;
+mod remote_index;
+pub use remote_index::{head_commit, RunnableIndex};
+mod test_list;
+pub use test_list::{Reconciliation, TestList};
+mod artifacts;
+pub use artifacts::{Artifact, ArtifactProfile, ArtifactTarget, Artifacts};
+mod script;
+pub use script::{command_line, shell_script, shell_script_all};
+mod candidates;
+pub use candidates::{candidates, Candidate};
+mod labels;
+pub use labels::{label, LABEL_PLACEHOLDERS};
+mod search;
+pub use search::{search, SearchHit};
+mod bug_report;
+pub use bug_report::{BugReport, ConfigLayerDigest};
+mod state;
+pub use state::{StateDir, STATE_VERSION};
+mod cache;
+pub use cache::{
+    clear_caches, CacheStats, IndexedFile, PackageStamp, ScopeCache, WorkspaceIndex, CACHE_STORES,
+};
+mod config_lint;
+pub use config_lint::{fix_config, lint_config, ConfigLint};
+mod config_tree;
+pub use config_tree::{ConfigLayer, LayerKey};
+mod watcher;
+pub use watcher::{Watcher, DEFAULT_DEBOUNCE};
+mod exec;
+pub use exec::{Execution, ExecutionResult};
+mod metadata;
+pub use metadata::{MetadataPackage, MetadataTarget, WorkspaceMetadata};
+mod config_diff;
+pub use config_diff::{diff_commands, CommandChange};
@@ -0,0 +1,239 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::UNIX_EPOCH,
+};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{workspace_members, CargoConfig, Error, PathId, TargetKind};
+
+/// A target of a package as `cargo metadata` reports it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataTarget {
+    pub name: String,
+    /// e.g. `["lib"]`, `["bin"]`, `["test"]` or `["custom-build"]`.
+    pub kind: Vec<String>,
+    /// The root file of the target.
+    pub src_path: PathBuf,
+    /// Features the target needs, cargo skips it without them.
+    #[serde(default, rename = "required-features")]
+    pub required_features: Vec<String>,
+}
+
+impl MetadataTarget {
+    /// The [TargetKind] of the target, `None` for build scripts.
+    pub fn target_kind(&self) -> Option<TargetKind> {
+        let kind = self.kind.first()?;
+        match kind.as_str() {
+            "lib" | "rlib" | "dylib" | "cdylib" | "staticlib" | "proc-macro" => {
+                Some(TargetKind::Lib)
+            }
+            "bin" => Some(TargetKind::Bin(self.name.clone())),
+            "example" => Some(TargetKind::Example(self.name.clone())),
+            "test" => Some(TargetKind::Test(self.name.clone())),
+            "bench" => Some(TargetKind::Bench(self.name.clone())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataPackage {
+    pub name: String,
+    pub manifest_path: PathBuf,
+    pub targets: Vec<MetadataTarget>,
+}
+
+/// The packages and targets of a workspace, from `cargo metadata --no-deps`, so
+/// targets declared in `Cargo.toml` resolve the way cargo sees them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceMetadata {
+    pub workspace_root: PathBuf,
+    pub packages: Vec<MetadataPackage>,
+}
+
+/// The metadata of a workspace with the manifest times it was read at.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedMetadata {
+    manifests: Vec<(PathBuf, u64, u32)>,
+    metadata: WorkspaceMetadata,
+}
+
+impl WorkspaceMetadata {
+    /// The metadata of the workspace at `workspace_root`, cached under the target
+    /// directory until one of its `Cargo.toml` files changes.
+    pub fn load(workspace_root: &Path) -> Result<Self, Error> {
+        let cache = CargoConfig::load(workspace_root)?
+            .target_dir(workspace_root)
+            .join("cargo-runner")
+            .join("metadata.json");
+        let manifests = manifest_times(workspace_root);
+        if let Some(cached) = fs::read_to_string(&cache)
+            .ok()
+            .and_then(|content| serde_json::from_str::<CachedMetadata>(&content).ok())
+            .filter(|cached| cached.manifests == manifests)
+        {
+            return Ok(cached.metadata);
+        }
+
+        let metadata = Self::read(workspace_root)?;
+        let cached = CachedMetadata {
+            manifests,
+            metadata,
+        };
+        // The cache only saves time, a read-only target directory is fine.
+        if let Some(dir) = cache.parent() {
+            let written = fs::create_dir_all(dir).and_then(|_| {
+                fs::write(&cache, serde_json::to_string(&cached).unwrap_or_default())
+            });
+            if let Err(e) = written {
+                debug!("could not cache cargo metadata: {}", e);
+            }
+        }
+        Ok(cached.metadata)
+    }
+
+    /// Runs `cargo metadata` without resolving dependencies.
+    fn read(workspace_root: &Path) -> Result<Self, Error> {
+        let output = Command::new("cargo")
+            .args(["metadata", "--format-version", "1", "--no-deps"])
+            .current_dir(workspace_root)
+            .output()?;
+        if !output.status.success() {
+            return Err(Error::Other(anyhow!(
+                "cargo metadata failed in {}: {}",
+                workspace_root.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        serde_json::from_slice(&output.stdout).map_err(|e| Error::Other(e.into()))
+    }
+
+    /// The package and target with `path` as its root file.
+    pub fn target_of(&self, path: &Path) -> Option<(&MetadataPackage, &MetadataTarget)> {
+        let path = PathId::new(path);
+        self.targets()
+            .find(|(_, target)| PathId::new(&target.src_path) == path)
+    }
+
+    /// The target of `package` whose root file is in the deepest directory holding
+    /// `path`, e.g. the `[[bin]]` of `src/tools/cli/main.rs` for a module in
+    /// `src/tools/cli/`. `None` when several targets share that directory, like
+    /// the lib and the main binary do `src/`.
+    pub fn enclosing_target(&self, package: &str, path: &Path) -> Option<&MetadataTarget> {
+        let path = path.canonicalize().ok()?;
+        let enclosing: Vec<(usize, &MetadataTarget)> = self
+            .targets()
+            .filter(|(candidate, target)| {
+                candidate.name == package && target.target_kind().is_some()
+            })
+            .filter_map(|(_, target)| {
+                let dir = target.src_path.parent()?.canonicalize().ok()?;
+                path.starts_with(&dir)
+                    .then_some((dir.components().count(), target))
+            })
+            .collect();
+        let deepest = enclosing.iter().map(|(depth, _)| *depth).max()?;
+        match enclosing
+            .iter()
+            .filter(|(depth, _)| *depth == deepest)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [(_, target)] => Some(target),
+            _ => None,
+        }
+    }
+
+    /// The target of `package` that is `kind`.
+    pub fn target_named(&self, package: &str, kind: &TargetKind) -> Option<&MetadataTarget> {
+        self.targets()
+            .find(|(candidate, target)| {
+                candidate.name == package && target.target_kind().as_ref() == Some(kind)
+            })
+            .map(|(_, target)| target)
+    }
+
+    fn targets(&self) -> impl Iterator<Item = (&MetadataPackage, &MetadataTarget)> {
+        self.packages
+            .iter()
+            .flat_map(|package| package.targets.iter().map(move |target| (package, target)))
+    }
+}
+
+/// The modification times of the manifests of the workspace, which change whenever
+/// its packages or targets could.
+fn manifest_times(workspace_root: &Path) -> Vec<(PathBuf, u64, u32)> {
+    std::iter::once(workspace_root.to_path_buf())
+        .chain(workspace_members(workspace_root))
+        .map(|dir| dir.join("Cargo.toml"))
+        .filter_map(|manifest| {
+            let modified = fs::metadata(&manifest).and_then(|m| m.modified()).ok()?;
+            let since = modified.duration_since(UNIX_EPOCH).ok()?;
+            Some((manifest, since.as_secs(), since.subsec_nanos()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_metadata() {
+        let root = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+             [[bin]]\nname = \"tool\"\npath = \"src/tools/cli/main.rs\"\n\
+             required-features = [\"cli\"]\n\n[features]\ncli = []\n",
+        );
+        write("src/lib.rs", "");
+        write("src/tools/cli/main.rs", "mod args;\nfn main() {}\n");
+        write("src/tools/cli/args.rs", "");
+        write("src/other.rs", "");
+
+        let metadata = WorkspaceMetadata::load(root.path()).unwrap();
+        let (package, target) = metadata
+            .target_of(&root.path().join("src/tools/cli/main.rs"))
+            .unwrap();
+        assert_eq!(package.name, "demo");
+        assert_eq!(
+            target.target_kind(),
+            Some(TargetKind::Bin("tool".to_string()))
+        );
+        assert_eq!(target.required_features, vec!["cli".to_string()]);
+
+        let enclosing = |path: &str| {
+            metadata
+                .enclosing_target("demo", &root.path().join(path))
+                .map(|target| target.name.as_str())
+        };
+        assert_eq!(enclosing("src/tools/cli/args.rs"), Some("tool"));
+        assert_eq!(enclosing("src/other.rs"), Some("demo"));
+
+        let runnable = crate::ContextFinder::new(root.path().join("src/tools/cli/args.rs"))
+            .source("")
+            .find(None)
+            .unwrap();
+        assert_eq!(
+            runnable.cargo_args(),
+            vec!["-p", "demo", "--bin", "tool", "--features", "cli"]
+        );
+
+        assert!(root
+            .path()
+            .join("target/cargo-runner/metadata.json")
+            .is_file());
+        assert_eq!(WorkspaceMetadata::load(root.path()).unwrap(), metadata);
+    }
+}
@@ -0,0 +1,190 @@
+use std::str::FromStr;
+
+use toml::{Table, Value};
+
+use crate::{config_lint::RENAMED_KEYS, CargoRunner, Error};
+
+/// The result of migrating a legacy config to the current format.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub config: CargoRunner,
+    /// Everything that could not be translated, one message per key.
+    pub warnings: Vec<String>,
+}
+
+/// Keys of a config entry that exist in the current format.
+pub(crate) const CONFIG_KEYS: &[&str] = &[
+    "name",
+    "command_type",
+    "command",
+    "sub_command",
+    "allowed_subcommands",
+    "env",
+    "env_policy",
+    "env_allowlist",
+    "working_dir",
+    "overrides",
+    "tags",
+    "matrix",
+    "profiler",
+    "memcheck",
+    "lints",
+    "heuristics",
+    "rustc_wrapper",
+    "module_tests",
+    "labels",
+    "cursor_strategy",
+    "scope_order",
+    "detectors",
+    "watch",
+    "framework_test",
+    "test_options",
+    "doctest_fallback",
+    "timeout_secs",
+    "features",
+    "all_features",
+    "no_default_features",
+    "target",
+    "runnables",
+];
+
+impl FromStr for Migration {
+    type Err = Error;
+
+    /// Migrates the legacy layout (`[[run.commands]]`, `command_type = "sub_command"`,
+    /// `allowed_subconfig`) to the current one (`[[run.config]]`, `"subcommand"`,
+    /// `allowed_subcommands`). Configs already in the current format pass through unchanged.
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        let legacy: Table = toml::from_str(content)?;
+        let mut warnings = Vec::new();
+        let mut migrated = Table::new();
+
+        for (context, value) in legacy {
+            if context == "target" {
+                // Per-OS sections postdate the legacy layout and resolve on parsing.
+                warnings.push("`target`: per-OS sections aren't migrated, skipped".to_string());
+                continue;
+            }
+            let Value::Table(entry) = value else {
+                warnings.push(format!("`{}`: expected a table, skipped", context));
+                continue;
+            };
+
+            let mut out = Table::new();
+            for (key, value) in entry {
+                match key.as_str() {
+                    "default" => {
+                        out.insert(key, value);
+                    }
+                    "config" | "commands" => {
+                        let Value::Array(configs) = value else {
+                            warnings
+                                .push(format!("`{}.{}`: expected an array, skipped", context, key));
+                            continue;
+                        };
+                        let target = out
+                            .entry("config")
+                            .or_insert_with(|| Value::Array(Vec::new()));
+                        if let Value::Array(configs_out) = target {
+                            for config in configs {
+                                if let Value::Table(config) = config {
+                                    configs_out.push(Value::Table(migrate_config(
+                                        &context,
+                                        config,
+                                        &mut warnings,
+                                    )));
+                                }
+                            }
+                        }
+                    }
+                    _ => warnings.push(format!("`{}.{}`: unknown key, skipped", context, key)),
+                }
+            }
+            migrated.insert(context, Value::Table(out));
+        }
+
+        let config = CargoRunner::try_from(toml::to_string(&migrated)?)?;
+
+        Ok(Migration { config, warnings })
+    }
+}
+
+fn migrate_config(context: &str, config: Table, warnings: &mut Vec<String>) -> Table {
+    let name = config
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let mut out = Table::new();
+
+    for (key, value) in config {
+        let key = match RENAMED_KEYS.iter().find(|(old, _)| *old == key) {
+            Some((_, renamed)) => renamed.to_string(),
+            None => key,
+        };
+        match key.as_str() {
+            "command_type" => {
+                let command_type = match value.as_str() {
+                    Some("cargo") => "cargo",
+                    Some("subcommand" | "sub_command") => "subcommand",
+                    Some("shell") => "shell",
+                    _ => {
+                        warnings.push(format!(
+                            "`{}.{}.command_type`: unknown value {}, using cargo",
+                            context, name, value
+                        ));
+                        "cargo"
+                    }
+                };
+                out.insert(key, Value::String(command_type.to_string()));
+            }
+            key if CONFIG_KEYS.contains(&key) => {
+                out.insert(key.to_string(), value);
+            }
+            _ => warnings.push(format!(
+                "`{}.{}.{}`: unknown key, skipped",
+                context, name, key
+            )),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CommandType, Context};
+
+    #[test]
+    fn test_migrate_legacy_layout() {
+        let legacy = r#"
+        [run]
+        default = "leptos"
+
+        [[run.commands]]
+        name = "leptos"
+        command_type = "sub_command"
+        command = "leptos"
+        sub_command = "watch"
+        allowed_subconfig = ["serve"]
+        color = true
+
+        [run.commands.env]
+        "#;
+
+        let migration: Migration = legacy.parse().expect("legacy config should migrate");
+        let leptos = migration
+            .config
+            .find(Context::Run, "leptos")
+            .expect("leptos config should exist");
+
+        assert_eq!(migration.config.get_default(Context::Run), Some("leptos"));
+        assert_eq!(leptos.command_type, Some(CommandType::SubCommand));
+        assert_eq!(leptos.allowed_subcommands, Some(vec!["serve".to_string()]));
+        assert_eq!(
+            migration.warnings,
+            vec!["`run.leptos.color`: unknown key, skipped"]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    fmt::{self, Display},
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The identity of a file: its canonical path, with symlinks and `..` resolved, so a
+/// file opened through a symlink, or as `/tmp` and `/private/tmp` on macOS, is the
+/// same file to config discovery, caches and overrides.
+///
+/// A file that doesn't exist yet, e.g. an unsaved editor buffer, keeps its name in
+/// its canonical directory, or is only made absolute when that doesn't exist either.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PathId(PathBuf);
+
+impl PathId {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        if let Ok(canonical) = path.canonicalize() {
+            return PathId(canonical);
+        }
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let in_canonical_dir = absolute
+            .parent()
+            .zip(absolute.file_name())
+            .and_then(|(dir, name)| dir.canonicalize().ok().map(|dir| dir.join(name)));
+        PathId(in_canonical_dir.unwrap_or(absolute))
+    }
+
+    pub fn as_path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Deref for PathId {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for PathId {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl From<PathId> for PathBuf {
+    fn from(id: PathId) -> Self {
+        id.0
+    }
+}
+
+impl Display for PathId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.display())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{Context, Override, Runnable};
+
+    #[test]
+    fn test_path_id_through_symlinks() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path().canonicalize().unwrap();
+        let src = root.join("project").join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("main.rs"), "fn main() {}\n").unwrap();
+        std::os::unix::fs::symlink(&src, root.join("linked")).unwrap();
+
+        let linked = root.join("linked").join("main.rs");
+        assert_eq!(PathId::new(&linked), PathId::new(src.join("main.rs")));
+        assert_eq!(
+            PathId::new(root.join("linked").join("unsaved.rs")).as_path(),
+            src.join("unsaved.rs")
+        );
+        assert_eq!(
+            PathId::new(root.join("missing").join("..").join("a.rs")).as_path(),
+            root.join("missing").join("..").join("a.rs")
+        );
+
+        let main = Override {
+            file: Some("src/main.rs".to_string()),
+            ..Default::default()
+        };
+        let run = Runnable::new(Context::Run);
+        assert!(!main.matches(&run, Some(&linked), None));
+        assert!(main.matches(&run, Some(&linked), Some(&PathId::new(&linked))));
+    }
+}
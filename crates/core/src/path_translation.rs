@@ -0,0 +1,122 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Rewrites file paths printed by a command (e.g. `src/lib.rs:10:5` in a panic message)
+/// so they are relative to the directory the user invoked cargo-runner from,
+/// instead of the working directory the command was executed in.
+#[derive(Debug, Clone)]
+pub struct PathTranslator {
+    working_dir: PathBuf,
+    display_dir: PathBuf,
+}
+
+impl PathTranslator {
+    pub fn new(working_dir: &Path, display_dir: &Path) -> Self {
+        PathTranslator {
+            working_dir: working_dir
+                .canonicalize()
+                .unwrap_or_else(|_| working_dir.to_path_buf()),
+            display_dir: display_dir
+                .canonicalize()
+                .unwrap_or_else(|_| display_dir.to_path_buf()),
+        }
+    }
+
+    /// Returns `true` when paths printed by the command need no translation.
+    pub fn is_identity(&self) -> bool {
+        self.working_dir == self.display_dir
+    }
+
+    pub fn translate_line(&self, line: &str) -> String {
+        if self.is_identity() {
+            return line.to_string();
+        }
+
+        line.split(' ')
+            .map(|token| self.translate_token(token))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn translate_token(&self, token: &str) -> String {
+        let trimmed = token.trim_end_matches([',', ';', ':', ')']);
+        let suffix = &token[trimmed.len()..];
+
+        // Split `path:line:col` into the path and its location suffix.
+        let mut path_end = trimmed.len();
+        for _ in 0..2 {
+            match trimmed[..path_end].rfind(':') {
+                Some(idx)
+                    if !trimmed[idx + 1..path_end].is_empty()
+                        && trimmed[idx + 1..path_end]
+                            .chars()
+                            .all(|c| c.is_ascii_digit()) =>
+                {
+                    path_end = idx
+                }
+                _ => break,
+            }
+        }
+
+        let path = Path::new(&trimmed[..path_end]);
+        if path.as_os_str().is_empty() || path.is_absolute() {
+            return token.to_string();
+        }
+
+        let absolute = self.working_dir.join(path);
+        if !absolute.is_file() {
+            return token.to_string();
+        }
+
+        format!(
+            "{}{}{}",
+            relative_to(&absolute, &self.display_dir).display(),
+            &trimmed[path_end..],
+            suffix
+        )
+    }
+}
+
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let path_components: Vec<Component> = path.components().collect();
+    let base_components: Vec<Component> = base.components().collect();
+
+    let common = path_components
+        .iter()
+        .zip(&base_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..base_components.len() {
+        relative.push("..");
+    }
+    for component in &path_components[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_translate_panic_location() {
+        let root = tempfile::tempdir().unwrap();
+        let package = root.path().join("crates").join("core");
+        fs::create_dir_all(package.join("src")).unwrap();
+        fs::write(package.join("src").join("lib.rs"), "").unwrap();
+
+        let translator = PathTranslator::new(&package, root.path());
+
+        assert_eq!(
+            translator.translate_line("thread 'it_works' panicked at src/lib.rs:10:5:"),
+            "thread 'it_works' panicked at crates/core/src/lib.rs:10:5:"
+        );
+        assert_eq!(
+            translator.translate_line("  --> src/missing.rs:1:1"),
+            "  --> src/missing.rs:1:1"
+        );
+    }
+}
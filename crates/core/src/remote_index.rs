@@ -0,0 +1,128 @@
+use std::{path::Path, process::Command};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{analyze, Error, FileAnalysis};
+
+/// The runnables of every file under a directory, with paths relative to it so an
+/// index built by CI can be downloaded and used by any checkout of the same commit.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunnableIndex {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    pub files: Vec<FileAnalysis>,
+}
+
+impl RunnableIndex {
+    /// Analyzes `root` like [analyze], keyed by the commit checked out there if any.
+    pub fn build(root: &Path, exclude: &[String]) -> Result<Self, Error> {
+        let files = analyze(root, exclude)?;
+        Ok(RunnableIndex::from_files(root, files).with_commit(head_commit(root)))
+    }
+
+    /// Makes the paths of `files` relative to `root`.
+    pub fn from_files(root: &Path, files: Vec<FileAnalysis>) -> Self {
+        let files = files
+            .into_iter()
+            .map(|file| FileAnalysis {
+                path: file
+                    .path
+                    .strip_prefix(root)
+                    .map(Path::to_path_buf)
+                    .unwrap_or(file.path),
+                ..file
+            })
+            .collect();
+        RunnableIndex {
+            commit: None,
+            files,
+        }
+    }
+
+    pub fn with_commit(mut self, commit: Option<String>) -> Self {
+        self.commit = commit;
+        self
+    }
+
+    /// The files of the index with their paths joined back onto `root`.
+    pub fn files_in(self, root: &Path) -> Vec<FileAnalysis> {
+        self.files
+            .into_iter()
+            .map(|file| FileAnalysis {
+                path: root.join(file.path),
+                ..file
+            })
+            .collect()
+    }
+
+    /// Downloads the index of `commit` from `url`, replacing `{commit}` in it e.g.
+    /// `https://ci.example.com/runner-index/{commit}.json` or a presigned S3 URL.
+    /// Returns `None` when the server has no index for the commit.
+    pub fn fetch(url: &str, commit: &str) -> Result<Option<Self>, Error> {
+        let url = url.replace("{commit}", commit);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        runtime.block_on(async {
+            let response = reqwest::get(&url).await?;
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            let index: RunnableIndex = response.error_for_status()?.json().await?;
+            Ok(Some(index))
+        })
+    }
+}
+
+/// The commit checked out at `root`, `None` outside git or when the worktree has
+/// changes, since an index of the commit would not match the files on disk.
+pub fn head_commit(root: &Path) -> Option<String> {
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(args)
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    let status = git(&["status", "--porcelain"])?;
+    if !status.is_empty() {
+        return None;
+    }
+    git(&["rev-parse", "HEAD"]).filter(|commit| !commit.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_index_paths_are_relative() {
+        let files = vec![FileAnalysis {
+            path: PathBuf::from("/ci/workspace/src/lib.rs"),
+            runnables: Vec::new(),
+            test_markers: 0,
+            partial: false,
+        }];
+        let index = RunnableIndex::from_files(Path::new("/ci/workspace"), files)
+            .with_commit(Some("abc123".to_string()));
+        assert_eq!(index.files[0].path, PathBuf::from("src/lib.rs"));
+
+        let json = serde_json::to_string(&index).unwrap();
+        let index: RunnableIndex = serde_json::from_str(&json).unwrap();
+        let files = index.files_in(Path::new("/home/dev/workspace"));
+        assert_eq!(
+            files[0].path,
+            PathBuf::from("/home/dev/workspace/src/lib.rs")
+        );
+    }
+}
@@ -0,0 +1,1251 @@
+use std::{collections::HashSet, time::Instant};
+
+use tracing::warn;
+
+use crate::{
+    source::NormalizedSource, DetectionBudget, Directive, DocTestMode, ExtendedScope, Scope,
+    ScopeKind,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Punct(char),
+    Literal(String),
+}
+
+/// Scans Rust source into a flat list of [Scope]s ordered by their start line.
+///
+/// This is not a full parser: it tokenizes just enough (skipping comments,
+/// strings and char literals) to track attributes and the braces of
+/// modules, impls, traits and functions. The first scope is always the file.
+/// Code blocks of doc comments rustdoc tests are scopes of their own. A BOM and
+/// `\r\n` or lone `\r` line endings don't change the lines scopes report.
+#[tracing::instrument(skip_all)]
+pub fn scan(source: &str) -> Vec<Scope> {
+    scan_within(&NormalizedSource::new(source), &DetectionBudget::default()).0
+}
+
+/// Lines longer than this are generated or minified code, whose bodies aren't worth
+/// scanning.
+const MAX_LINE_BYTES: usize = 16 * 1024;
+
+/// Like [scan] within `budget`, returning whether the scopes are partial. A source over
+/// `max_bytes` or with a line over [MAX_LINE_BYTES] only gets the items outside
+/// function, impl and trait bodies and no doc tests, a scan past `max_time` keeps the
+/// scopes found so far.
+pub(crate) fn scan_within(
+    source: &NormalizedSource,
+    budget: &DetectionBudget,
+) -> (Vec<Scope>, bool) {
+    let outer_only = budget
+        .max_bytes
+        .is_some_and(|max_bytes| source.text().len() > max_bytes)
+        || source.longest_line() > MAX_LINE_BYTES;
+    let source = source.text();
+    let deadline = budget.max_time.map(|max_time| Instant::now() + max_time);
+    let mut partial = outer_only;
+
+    let tokens = tokenize(source);
+    let line_count = source.lines().count().max(1);
+
+    let mut scopes = vec![Scope {
+        kind: ScopeKind::File,
+        name: String::new(),
+        module_path: Vec::new(),
+        start_line: 1,
+        end_line: line_count,
+        attributes: Vec::new(),
+        id: 0,
+        extended: ExtendedScope::default(),
+    }];
+
+    // Each open brace, with the scope it belongs to if it opened an item body.
+    let mut stack: Vec<Option<usize>> = Vec::new();
+    let mut attributes: Vec<String> = Vec::new();
+    let mut attributes_line: Option<usize> = None;
+    let mut pending: Option<(ScopeKind, String, usize)> = None;
+    let mut pending_attribute_lines: Option<(usize, usize)> = None;
+    // Whether the pending function takes a `Criterion`.
+    let mut pending_criterion = false;
+    let mut depth = 0usize;
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let (token, line) = &tokens[i];
+        let line = *line;
+        if i % 1024 == 0 && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            // Scopes left open end where the scan stopped.
+            for idx in stack.iter().flatten() {
+                scopes[*idx].end_line = line;
+            }
+            partial = true;
+            break;
+        }
+
+        match token {
+            Token::Punct('#') => {
+                let inner = matches!(tokens.get(i + 1), Some((Token::Punct('!'), _)));
+                let open = if inner { i + 2 } else { i + 1 };
+                if matches!(tokens.get(open), Some((Token::Punct('['), _))) {
+                    let (attribute, next) = read_attribute(&tokens, open);
+                    if inner {
+                        let owner = stack.iter().rev().find_map(|s| *s).unwrap_or(0);
+                        scopes[owner].attributes.push(attribute);
+                    } else {
+                        attributes_line.get_or_insert(line);
+                        attributes.push(attribute);
+                    }
+                    i = next;
+                    continue;
+                }
+            }
+            Token::Ident(keyword) if pending.is_none() => {
+                let kind = match keyword.as_str() {
+                    "fn" => Some(ScopeKind::Function),
+                    "mod" => Some(ScopeKind::Module),
+                    "trait" => Some(ScopeKind::Trait),
+                    "impl" => Some(ScopeKind::Impl),
+                    _ => None,
+                };
+                let name = match kind {
+                    Some(ScopeKind::Impl) => Some(read_impl_header(&tokens, i + 1)),
+                    Some(_) => match tokens.get(i + 1) {
+                        Some((Token::Ident(name), _)) => {
+                            Some(name.trim_start_matches("r#").to_string())
+                        }
+                        _ => None,
+                    },
+                    None => None,
+                };
+                if let (Some(kind), Some(name)) = (kind, name) {
+                    pending = Some((kind, name, attributes_line.unwrap_or(line)));
+                    pending_criterion = false;
+                    pending_attribute_lines =
+                        attributes_line.map(|start| (start, line.saturating_sub(1).max(start)));
+                    depth = 0;
+                }
+            }
+            Token::Ident(ident)
+                if ident == "Criterion" && matches!(pending, Some((ScopeKind::Function, ..))) =>
+            {
+                pending_criterion = true;
+            }
+            Token::Punct('(') | Token::Punct('[') => depth += 1,
+            Token::Punct(')') | Token::Punct(']') => depth = depth.saturating_sub(1),
+            Token::Punct(';') if depth == 0 => {
+                pending = None;
+                attributes.clear();
+                attributes_line = None;
+            }
+            Token::Punct('{') => {
+                let in_body = stack
+                    .iter()
+                    .flatten()
+                    .any(|idx| scopes[*idx].kind != ScopeKind::Module);
+                match pending.take().filter(|_| !(outer_only && in_body)) {
+                    Some((kind, name, start_line)) => {
+                        let bench_id = pending_criterion.then(|| name.clone());
+                        let module_path = stack
+                            .iter()
+                            .flatten()
+                            .filter(|idx| scopes[**idx].kind == ScopeKind::Module)
+                            .map(|idx| scopes[*idx].name.clone())
+                            .collect();
+                        scopes.push(Scope {
+                            kind,
+                            name,
+                            module_path,
+                            start_line,
+                            end_line: line,
+                            attributes: std::mem::take(&mut attributes),
+                            id: 0,
+                            extended: ExtendedScope {
+                                attribute_lines: pending_attribute_lines.take(),
+                                bench_id,
+                                ..Default::default()
+                            },
+                        });
+                        stack.push(Some(scopes.len() - 1));
+                    }
+                    None => {
+                        attributes.clear();
+                        stack.push(None);
+                    }
+                }
+                attributes_line = None;
+            }
+            Token::Punct('}') => {
+                if let Some(Some(idx)) = stack.pop() {
+                    scopes[idx].end_line = line;
+                }
+            }
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    criterion_benches(&tokens, &mut scopes);
+
+    let doc_tests = match partial {
+        true => Vec::new(),
+        false => doc_tests(source, &tokens, &scopes),
+    };
+    let lines: Vec<&str> = source.lines().collect();
+    for scope in scopes.iter_mut().skip(1) {
+        let end = scope.start_line.saturating_sub(1);
+        let start = (1..=end)
+            .rev()
+            .take_while(|line| doc_line(lines[line - 1]).is_some_and(|(inner, _)| !inner))
+            .last();
+        if let Some(start) = start {
+            scope.extended.doc_comment_lines = Some((start, end));
+            scope.extended.has_doc_tests = doc_tests
+                .iter()
+                .any(|doc_test| start <= doc_test.start_line && doc_test.start_line <= end);
+        }
+        // Directives may be mixed with the doc comment, in the comments right above the item.
+        let mut directives: Vec<Directive> = (1..scope.start_line)
+            .rev()
+            .map(|line| lines[line - 1])
+            .take_while(|line| line.trim_start().starts_with("//"))
+            .filter_map(Directive::parse_comment)
+            .filter_map(|directive| {
+                directive
+                    .map_err(|e| warn!("{} above `{}`", e, scope.name))
+                    .ok()
+            })
+            .collect();
+        directives.reverse();
+        scope.extended.directives = directives;
+    }
+    scopes.extend(doc_tests);
+    scopes.sort_by_key(|scope| scope.start_line);
+
+    let file_hash = fnv1a(FNV_OFFSET, source.as_bytes());
+    for scope in &mut scopes {
+        scope.id = scope_id(file_hash, scope);
+    }
+
+    (scopes, partial)
+}
+
+/// Marks the functions `criterion_group!` lists as benches, then names each criterion
+/// bench after the first `bench_function` or `benchmark_group` id of its body.
+fn criterion_benches(tokens: &[(Token, usize)], scopes: &mut [Scope]) {
+    for (i, (token, _)) in tokens.iter().enumerate() {
+        if *token != Token::Ident("criterion_group".to_string())
+            || tokens.get(i + 1).map(|(token, _)| token) != Some(&Token::Punct('!'))
+        {
+            continue;
+        }
+        for name in criterion_group_targets(&tokens[i + 2..]) {
+            for scope in scopes
+                .iter_mut()
+                .filter(|scope| scope.kind == ScopeKind::Function && scope.module_path.is_empty())
+            {
+                if scope.name == name && scope.extended.bench_id.is_none() {
+                    scope.extended.bench_id = Some(name.clone());
+                }
+            }
+        }
+    }
+
+    for scope in scopes.iter_mut() {
+        if scope.extended.bench_id.is_none() {
+            continue;
+        }
+        let body: Vec<&Token> = tokens
+            .iter()
+            .filter(|(_, line)| scope.contains(*line))
+            .map(|(token, _)| token)
+            .collect();
+        let id = body.windows(3).find_map(|window| match window {
+            [Token::Ident(method), Token::Punct('('), Token::Literal(id)]
+                if method == "bench_function" || method == "benchmark_group" =>
+            {
+                Some(unquote(id))
+            }
+            _ => None,
+        });
+        if let Some(id) = id {
+            scope.extended.bench_id = Some(id);
+        }
+    }
+}
+
+/// The functions of a `criterion_group!(name, a, b)` or
+/// `criterion_group! { name = name; config = ..; targets = a, b }` invocation.
+fn criterion_group_targets(tokens: &[(Token, usize)]) -> Vec<String> {
+    let mut depth = 0;
+    let mut args: Vec<Vec<&Token>> = vec![Vec::new()];
+    for (token, _) in tokens {
+        match token {
+            Token::Punct('(' | '{' | '[') => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            Token::Punct(')' | '}' | ']') => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Token::Punct(',' | ';') if depth == 1 => {
+                args.push(Vec::new());
+                continue;
+            }
+            _ => {}
+        }
+        if depth >= 1 {
+            args.last_mut().expect("args is never empty").push(token);
+        }
+    }
+
+    let names = |arg: &[&Token]| -> Option<String> {
+        match arg {
+            [Token::Ident(name)] => Some(name.clone()),
+            _ => None,
+        }
+    };
+    let targets = args.iter().position(|arg| {
+        matches!(arg.as_slice(), [Token::Ident(key), Token::Punct('='), ..] if key == "targets")
+    });
+    match targets {
+        Some(start) => args[start..]
+            .iter()
+            .enumerate()
+            .filter_map(|(n, arg)| match n {
+                0 => names(&arg[2..]),
+                _ => names(arg),
+            })
+            .collect(),
+        None => args.iter().skip(1).filter_map(|arg| names(arg)).collect(),
+    }
+}
+
+/// Finds the fenced code blocks of `///` and `//!` comments that rustdoc tests, each
+/// named after the item it documents e.g. `Calculator::add`. Lines within string
+/// literals aren't comments, however they start.
+fn doc_tests(source: &str, tokens: &[(Token, usize)], scopes: &[Scope]) -> Vec<Scope> {
+    let lines: Vec<&str> = source.lines().collect();
+    let in_literal = literal_lines(tokens);
+    let doc = |index: usize| match in_literal.contains(&(index + 1)) {
+        true => None,
+        false => doc_line(lines[index]),
+    };
+    let mut found = Vec::new();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let Some((inner, _)) = doc(i) else {
+            i += 1;
+            continue;
+        };
+        let start = i;
+        while i < lines.len() && doc(i).is_some_and(|(kind, _)| kind == inner) {
+            i += 1;
+        }
+
+        let (module_path, name) = documented_item(&lines, scopes, start + 1, i + 1, inner);
+        let mut fence: Option<(usize, char, usize, String)> = None;
+        for (index, line) in lines.iter().enumerate().take(i).skip(start) {
+            let text = doc_line(line)
+                .map(|(_, text)| text.trim())
+                .unwrap_or_default();
+            let Some((marker, count, info)) = fence_marker(text) else {
+                continue;
+            };
+            match &fence {
+                None => fence = Some((index + 1, marker, count, info.to_string())),
+                Some((_, open, open_count, _)) if marker == *open && count >= *open_count => {
+                    if info.is_empty() {
+                        let (start_line, _, _, info) = fence.take().unwrap();
+                        found.extend(doc_test(start_line, index + 1, &info, &module_path, &name));
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        // rustdoc closes a block left open at the end of the comment.
+        if let Some((start_line, _, _, info)) = fence {
+            found.extend(doc_test(start_line, i, &info, &module_path, &name));
+        }
+    }
+
+    found
+}
+
+fn doc_test(
+    start_line: usize,
+    end_line: usize,
+    info: &str,
+    module_path: &[String],
+    name: &str,
+) -> Option<Scope> {
+    DocTestMode::from_fence(info)?;
+    Some(Scope {
+        kind: ScopeKind::DocTest,
+        name: name.to_string(),
+        module_path: module_path.to_vec(),
+        start_line,
+        end_line,
+        attributes: info
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect(),
+        id: 0,
+        extended: ExtendedScope::default(),
+    })
+}
+
+/// The lines a string literal continues onto, after the one it starts on.
+fn literal_lines(tokens: &[(Token, usize)]) -> HashSet<usize> {
+    tokens
+        .iter()
+        .filter_map(|(token, line)| match token {
+            Token::Literal(literal) => Some((*line, literal.matches('\n').count())),
+            _ => None,
+        })
+        .flat_map(|(line, newlines)| line + 1..=line + newlines)
+        .collect()
+}
+
+/// The text of a `///` (outer) or `//!` (inner) doc comment line.
+fn doc_line(line: &str) -> Option<(bool, &str)> {
+    let line = line.trim_start();
+    if let Some(text) = line.strip_prefix("//!") {
+        Some((true, text))
+    } else if line.starts_with("////") {
+        None
+    } else {
+        line.strip_prefix("///").map(|text| (false, text))
+    }
+}
+
+/// The fence character, its count and the info string of a ```` ``` ```` or `~~~` line.
+fn fence_marker(text: &str) -> Option<(char, usize, &str)> {
+    let marker = text.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let count = text.chars().take_while(|c| *c == marker).count();
+    (count >= 3).then(|| (marker, count, text[count..].trim()))
+}
+
+/// The module path and name of the item documented by the doc comment starting at
+/// `start_line`: the enclosing module for `//!`, else the item after the comment.
+fn documented_item(
+    lines: &[&str],
+    scopes: &[Scope],
+    start_line: usize,
+    next_line: usize,
+    inner: bool,
+) -> (Vec<String>, String) {
+    let mut enclosing: Vec<&Scope> = scopes
+        .iter()
+        .filter(|scope| scope.kind != ScopeKind::File && scope.contains(start_line))
+        .collect();
+    enclosing.sort_by_key(|scope| scope.start_line);
+    let module_path: Vec<String> = match enclosing
+        .iter()
+        .rev()
+        .find(|scope| scope.kind == ScopeKind::Module)
+    {
+        Some(module) => module
+            .module_path
+            .iter()
+            .cloned()
+            .chain(std::iter::once(module.name.clone()))
+            .collect(),
+        None => Vec::new(),
+    };
+    if inner {
+        return (module_path, String::new());
+    }
+
+    let owner = enclosing
+        .last()
+        .filter(|scope| matches!(scope.kind, ScopeKind::Impl | ScopeKind::Trait))
+        .map(|scope| match scope.kind {
+            ScopeKind::Impl => impl_type(&scope.name),
+            _ => scope.name.clone(),
+        });
+    let item_line = (next_line..=lines.len()).find(|line| {
+        let text = lines[line - 1].trim();
+        !text.is_empty() && !text.starts_with("#[") && !text.starts_with("//")
+    });
+    let item = item_line
+        .map(|line| match item_name(lines[line - 1]) {
+            Some(name) if name == "impl" => scopes
+                .iter()
+                .find(|scope| scope.kind == ScopeKind::Impl && scope.contains(line))
+                .map(|scope| impl_type(&scope.name))
+                .unwrap_or_default(),
+            Some(name) => name,
+            None => data_member(lines, line).unwrap_or_default(),
+        })
+        .unwrap_or_default();
+
+    let name = match owner {
+        Some(owner) if !item.is_empty() => format!("{}::{}", owner, item),
+        Some(owner) => owner,
+        None => item,
+    };
+    (module_path, name)
+}
+
+/// The name declared by an item line e.g. `add` for `pub const fn add(`, or `impl`.
+fn item_name(line: &str) -> Option<String> {
+    const ITEMS: &[&str] = &[
+        "fn",
+        "struct",
+        "enum",
+        "union",
+        "trait",
+        "type",
+        "mod",
+        "const",
+        "static",
+        "macro_rules",
+    ];
+    let words: Vec<&str> = line
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .collect();
+    for (index, word) in words.iter().enumerate() {
+        let next = words.get(index + 1).copied();
+        if *word == "impl" {
+            return Some("impl".to_string());
+        }
+        if matches!((*word, next), ("const" | "static", Some("fn" | "mut"))) {
+            continue;
+        }
+        if ITEMS.contains(word) {
+            return next.map(str::to_string);
+        }
+    }
+    None
+}
+
+/// The rustdoc name of the field or variant declared on `line` e.g. `Bits::int` for
+/// `pub int: u32,` inside `union Bits`, `None` outside a struct, enum or union.
+fn data_member(lines: &[&str], line: usize) -> Option<String> {
+    let text = lines[line - 1].trim();
+    let text = match text.strip_prefix("pub") {
+        Some(rest) if rest.starts_with('(') => rest.split_once(')')?.1,
+        Some(rest) if rest.starts_with(char::is_whitespace) => rest,
+        _ => text,
+    };
+    let member: String = text
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    if member.is_empty() {
+        return None;
+    }
+
+    // The header of the innermost brace still open above the member.
+    let mut depth = 0;
+    let mut header = None;
+    'lines: for index in (0..line - 1).rev() {
+        if lines[index].trim_start().starts_with("//") {
+            continue;
+        }
+        for c in lines[index].chars().rev() {
+            match c {
+                '}' => depth += 1,
+                '{' if depth == 0 => {
+                    header = Some(index);
+                    break 'lines;
+                }
+                '{' => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    // The header may span lines e.g. generics or a where clause before the brace.
+    let header = header?;
+    let item = (header.saturating_sub(8)..=header).rev().find_map(|index| {
+        let words: Vec<&str> = lines[index]
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|word| !word.is_empty())
+            .collect();
+        let keyword = words.iter().position(|word| {
+            matches!(
+                *word,
+                "struct" | "enum" | "union" | "fn" | "impl" | "trait" | "mod"
+            )
+        })?;
+        Some((words[keyword], words.get(keyword + 1).copied()))
+    });
+    match item? {
+        ("struct" | "enum" | "union", Some(owner)) => Some(format!("{}::{}", owner, member)),
+        _ => None,
+    }
+}
+
+/// The type of an impl header e.g. `Context` for `Display for Context` or `<T> Stack<T>`.
+fn impl_type(header: &str) -> String {
+    let header = header.rsplit(" for ").next().unwrap_or(header);
+    let header = match header.strip_prefix('<') {
+        Some(rest) => {
+            let mut depth = 1;
+            let end = rest
+                .char_indices()
+                .find(|(_, c)| {
+                    match c {
+                        '<' => depth += 1,
+                        '>' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                })
+                .map(|(index, _)| index + 1)
+                .unwrap_or(rest.len());
+            &rest[end..]
+        }
+        None => header,
+    };
+    header
+        .split('<')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// A file pulled into the module tree by `#[path = "..."] mod name;` or `include!("...")`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Inclusion {
+    /// The path as written, relative to the directory of the including file.
+    pub file: String,
+    /// The module the included items end up in, relative to the including file.
+    pub module_path: Vec<String>,
+}
+
+/// Finds the files `source` includes through `#[path]` module declarations and `include!`.
+pub(crate) fn inclusions(source: &str) -> Vec<Inclusion> {
+    let normalized = NormalizedSource::new(source);
+    let source = normalized.text();
+    let tokens = tokenize(source);
+    let modules: Vec<Scope> = scan(source)
+        .into_iter()
+        .filter(|scope| scope.kind == ScopeKind::Module)
+        .collect();
+    let enclosing = |line: usize| -> Vec<String> {
+        modules
+            .iter()
+            .filter(|module| module.contains(line))
+            .max_by_key(|module| module.start_line)
+            .map(|module| module.path().split("::").map(str::to_string).collect())
+            .unwrap_or_default()
+    };
+
+    let mut found = Vec::new();
+    let mut path_attribute: Option<String> = None;
+    let mut i = 0;
+    while i < tokens.len() {
+        let (token, line) = &tokens[i];
+        match token {
+            Token::Punct('#') if matches!(tokens.get(i + 1), Some((Token::Punct('['), _))) => {
+                let (attribute, next) = read_attribute(&tokens, i + 1);
+                if let Some(value) = attribute.strip_prefix("path=") {
+                    path_attribute = Some(unquote(value));
+                }
+                i = next;
+                continue;
+            }
+            Token::Ident(keyword) if keyword == "mod" => {
+                if let (Some((Token::Ident(name), _)), Some((Token::Punct(';'), _)), Some(file)) =
+                    (tokens.get(i + 1), tokens.get(i + 2), path_attribute.take())
+                {
+                    let mut module_path = enclosing(*line);
+                    module_path.push(name.trim_start_matches("r#").to_string());
+                    found.push(Inclusion { file, module_path });
+                }
+            }
+            Token::Ident(keyword) if keyword == "include" => {
+                if let (
+                    Some((Token::Punct('!'), _)),
+                    Some((Token::Punct('('), _)),
+                    Some((Token::Literal(file), _)),
+                ) = (tokens.get(i + 1), tokens.get(i + 2), tokens.get(i + 3))
+                {
+                    found.push(Inclusion {
+                        file: unquote(file),
+                        module_path: enclosing(*line),
+                    });
+                }
+            }
+            Token::Punct(';') | Token::Punct('{') => path_attribute = None,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    found
+}
+
+/// The contents of a string literal token, e.g. `"a.rs"` or `r#"a.rs"#`.
+fn unquote(literal: &str) -> String {
+    literal
+        .trim_start_matches('r')
+        .trim_matches('#')
+        .trim_matches('"')
+        .to_string()
+}
+
+pub(crate) const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// FNV-1a, used instead of `DefaultHasher` so ids stay the same across Rust releases.
+pub(crate) fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Derives the id of a scope from the file hash, its kind, path and span, so
+/// re-scanning unchanged source gives the same ids.
+fn scope_id(file_hash: u64, scope: &Scope) -> u64 {
+    let key = format!(
+        "{:?}\0{}\0{}\0{}",
+        scope.kind,
+        scope.path(),
+        scope.start_line,
+        scope.end_line
+    );
+    fnv1a(fnv1a(FNV_OFFSET, &file_hash.to_le_bytes()), key.as_bytes())
+}
+
+/// Reads `[ ... ]` starting at `open`, returning the attribute text and the index after `]`.
+fn read_attribute(tokens: &[(Token, usize)], open: usize) -> (String, usize) {
+    let mut depth = 0;
+    let mut text = String::new();
+    let mut previous_ident = false;
+    let mut i = open;
+
+    while i < tokens.len() {
+        let token = &tokens[i].0;
+        i += 1;
+        match token {
+            Token::Punct('[') => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            Token::Punct(']') => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        match token {
+            Token::Ident(ident) => {
+                if previous_ident {
+                    text.push(' ');
+                }
+                text.push_str(ident);
+            }
+            Token::Punct(c) => text.push(*c),
+            Token::Literal(literal) => text.push_str(literal),
+        }
+        previous_ident = matches!(token, Token::Ident(_));
+    }
+
+    (text, i)
+}
+
+/// Renders the tokens of an impl header e.g. `Display for Context`.
+fn read_impl_header(tokens: &[(Token, usize)], start: usize) -> String {
+    let mut text = String::new();
+    let mut previous_ident = false;
+    for (token, _) in &tokens[start.min(tokens.len())..] {
+        match token {
+            Token::Punct('{') | Token::Punct(';') => break,
+            Token::Ident(ident) if ident == "where" => break,
+            Token::Ident(ident) => {
+                if previous_ident {
+                    text.push(' ');
+                }
+                text.push_str(ident);
+            }
+            Token::Punct(c) => text.push(*c),
+            Token::Literal(literal) => text.push_str(literal),
+        }
+        previous_ident = matches!(token, Token::Ident(_));
+    }
+    text
+}
+
+fn tokenize(source: &str) -> Vec<(Token, usize)> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            c if c.is_whitespace() => i += 1,
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let mut nesting = 0;
+                while i < chars.len() {
+                    if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                        nesting += 1;
+                        i += 2;
+                    } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                        nesting -= 1;
+                        i += 2;
+                        if nesting == 0 {
+                            break;
+                        }
+                    } else {
+                        if chars[i] == '\n' {
+                            line += 1;
+                        }
+                        i += 1;
+                    }
+                }
+            }
+            '"' => {
+                let start = i;
+                let start_line = line;
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    if chars.get(i) == Some(&'\n') {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+                i += 1;
+                let literal = chars[start..i.min(chars.len())].iter().collect();
+                tokens.push((Token::Literal(literal), start_line));
+            }
+            '\'' => {
+                if chars.get(i + 1) == Some(&'\\') {
+                    i += 2;
+                    while i < chars.len() && chars[i] != '\'' {
+                        i += 1;
+                    }
+                    i += 1;
+                } else if chars.get(i + 2) == Some(&'\'') {
+                    i += 3;
+                } else {
+                    // A lifetime or label, the identifier is tokenized on its own.
+                    i += 1;
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let ident: String = chars[start..i].iter().collect();
+
+                if matches!(ident.as_str(), "r" | "br" | "cr") && is_raw_string_start(&chars, i) {
+                    let start_line = line;
+                    let mut hashes = 0;
+                    while chars.get(i) == Some(&'#') {
+                        hashes += 1;
+                        i += 1;
+                    }
+                    i += 1;
+                    while i < chars.len() {
+                        if chars[i] == '"' && (1..=hashes).all(|n| chars.get(i + n) == Some(&'#')) {
+                            i += 1 + hashes;
+                            break;
+                        }
+                        if chars[i] == '\n' {
+                            line += 1;
+                        }
+                        i += 1;
+                    }
+                    let literal = chars[start..i.min(chars.len())].iter().collect();
+                    tokens.push((Token::Literal(literal), start_line));
+                } else if ident == "r"
+                    && chars.get(i) == Some(&'#')
+                    && chars
+                        .get(i + 1)
+                        .is_some_and(|c| c.is_alphabetic() || *c == '_')
+                {
+                    let start = i + 1;
+                    i += 1;
+                    while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                        i += 1;
+                    }
+                    let ident: String = chars[start..i].iter().collect();
+                    tokens.push((Token::Ident(format!("r#{}", ident)), line));
+                } else {
+                    tokens.push((Token::Ident(ident), line));
+                }
+            }
+            c if c.is_ascii_digit() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let literal = chars[start..i].iter().collect();
+                tokens.push((Token::Literal(literal), line));
+            }
+            c => {
+                tokens.push((Token::Punct(c), line));
+                i += 1;
+            }
+        }
+    }
+
+    tokens
+}
+
+fn is_raw_string_start(chars: &[char], mut i: usize) -> bool {
+    while chars.get(i) == Some(&'#') {
+        i += 1;
+    }
+    chars.get(i) == Some(&'"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r##"#![allow(dead_code)]
+
+pub fn add(a: u32, b: u32) -> u32 {
+    let s = "}{";
+    let c = '}';
+    a + b
+}
+
+/* fn commented() { } */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        assert_eq!(add(2, 2), 4);
+    }
+
+    mod nested {
+        #[tokio::test]
+        async fn it_works_async<'a>() {
+            let raw = r#"fn fake() {"#;
+        }
+    }
+}
+"##;
+
+    #[test]
+    fn test_scan_scopes() {
+        let scopes = scan(SOURCE);
+
+        let file = &scopes[0];
+        assert_eq!(file.kind, ScopeKind::File);
+        assert_eq!(file.attributes, vec!["allow(dead_code)"]);
+
+        let names: Vec<_> = scopes.iter().skip(1).map(|s| s.path()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "add",
+                "tests",
+                "tests::it_works",
+                "tests::nested",
+                "tests::nested::it_works_async"
+            ]
+        );
+
+        let add = &scopes[1];
+        assert_eq!((add.start_line, add.end_line), (3, 7));
+        assert!(!add.is_test());
+
+        let tests = &scopes[2];
+        assert_eq!((tests.start_line, tests.end_line), (10, 25));
+        assert!(tests.is_cfg_test());
+
+        let it_works = &scopes[3];
+        assert_eq!((it_works.start_line, it_works.end_line), (14, 17));
+        assert!(it_works.is_test());
+
+        let it_works_async = &scopes[5];
+        assert_eq!(
+            (it_works_async.start_line, it_works_async.end_line),
+            (20, 23)
+        );
+        assert!(it_works_async.is_test());
+    }
+
+    #[test]
+    fn test_scan_line_endings() {
+        let crlf = SOURCE.replace('\n', "\r\n");
+        let bom = format!("\u{feff}{}", crlf);
+        // Alternate `\n`, `\r` and `\r\n`, as files edited on several systems end up.
+        // A lone `\r` is never followed by `\n`, which would make them one `\r\n`.
+        let mixed: String = SOURCE
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(i, line)| match i % 3 {
+                0 => line.to_string(),
+                1 => line.replace('\n', "\r"),
+                _ => line.replace('\n', "\r\n"),
+            })
+            .collect();
+
+        let expected = scan(SOURCE);
+        for source in [crlf, bom, mixed] {
+            assert_eq!(scan(&source), expected);
+        }
+    }
+
+    #[test]
+    fn test_scope_ids_are_stable() {
+        let ids = |source: &str| scan(source).iter().map(|s| s.id).collect::<Vec<_>>();
+
+        let first = ids(SOURCE);
+        assert_eq!(first, ids(SOURCE));
+
+        let mut unique = first.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), first.len());
+
+        assert_ne!(first, ids(&SOURCE.replace("a + b", "b + a")));
+    }
+
+    #[test]
+    fn test_scan_doc_tests() {
+        let source = r#"//! ```
+//! assert!(true);
+//! ```
+
+pub struct Stack<T>(Vec<T>);
+
+impl<T> Stack<T> {
+    /// ```no_run
+    /// let stack = demo::Stack::new();
+    /// ```
+    ///
+    /// ```text
+    /// not rust
+    /// ```
+    #[inline]
+    pub const fn new() -> Self {
+        Stack(Vec::new())
+    }
+}
+
+/// ```ignore,edition2021
+/// todo!()
+pub fn unfinished() {}
+
+const FIXTURE: &str = "
+/// ```
+/// fixture();
+/// ```
+";
+const RAW_FIXTURE: &str = r"/// ```
+/// fixture();
+/// ```";
+"#;
+        let doc_tests: Vec<_> = scan(source)
+            .into_iter()
+            .filter(|scope| scope.kind == ScopeKind::DocTest)
+            .map(|scope| {
+                (
+                    scope.path(),
+                    scope.start_line,
+                    scope.end_line,
+                    scope.doc_test_mode(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            doc_tests,
+            vec![
+                (String::new(), 1, 3, Some(DocTestMode::Run)),
+                ("Stack::new".to_string(), 8, 10, Some(DocTestMode::NoRun)),
+                ("unfinished".to_string(), 21, 22, Some(DocTestMode::Ignore)),
+            ]
+        );
+
+        let scopes = scan(source);
+        let new = scopes.iter().find(|scope| scope.name == "new").unwrap();
+        assert_eq!(
+            new.extended,
+            ExtendedScope {
+                doc_comment_lines: Some((8, 14)),
+                attribute_lines: Some((15, 15)),
+                has_doc_tests: true,
+                bench_id: None,
+                directives: Vec::new(),
+            }
+        );
+        assert_eq!(new.lens_line(), 8);
+    }
+
+    #[test]
+    fn test_scan_criterion_benches() {
+        let source = r#"use criterion::{criterion_group, criterion_main, Criterion};
+
+fn bench_add(c: &mut Criterion) {
+    c.bench_function("add 2", |b| b.iter(|| 1 + 1));
+}
+
+fn bench_group(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sums");
+    group.finish();
+}
+
+fn configured() {}
+
+fn helper() {}
+
+criterion_group!(benches, bench_add, bench_group);
+criterion_group! {
+    name = slow;
+    config = Criterion::default().sample_size(10);
+    targets = configured
+}
+criterion_main!(benches, slow);
+"#;
+        let benches: Vec<_> = scan(source)
+            .iter()
+            .filter(|scope| scope.is_bench())
+            .map(|scope| (scope.name.clone(), scope.extended.bench_id.clone()))
+            .collect();
+        assert_eq!(
+            benches,
+            vec![
+                ("bench_add".to_string(), Some("add 2".to_string())),
+                ("bench_group".to_string(), Some("sums".to_string())),
+                ("configured".to_string(), Some("configured".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_doc_tests_on_items() {
+        let source = r#"/// ```
+/// assert_eq!(demo::LIMIT, 3);
+/// ```
+pub const LIMIT: u32 = 3;
+
+/// ```
+/// assert!(!demo::NAMES.is_empty());
+/// ```
+pub static NAMES: &[&str] = &["a"];
+
+pub trait Shape<T>
+where
+    T: Copy,
+{
+    /// ```
+    /// assert!(true);
+    /// ```
+    const SIDES: u32;
+}
+
+#[repr(C)]
+pub union Raw {
+    /// ```
+    /// assert!(true);
+    /// ```
+    pub(crate) int: u32,
+    pub float: f32,
+}
+
+pub enum Mode<T> {
+    Slow { delay: T },
+    /// ```
+    /// let _ = demo::Mode::<u32>::Fast;
+    /// ```
+    Fast,
+}
+"#;
+        let names: Vec<_> = scan(source)
+            .into_iter()
+            .filter(|scope| scope.kind == ScopeKind::DocTest)
+            .map(|scope| scope.path())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["LIMIT", "NAMES", "Shape::SIDES", "Raw::int", "Mode::Fast"]
+        );
+    }
+
+    #[test]
+    fn test_scan_over_budget() {
+        let source = "/// ```\n/// assert!(true);\n/// ```\nfn main() {\n    fn helper() {}\n}\n\nmod tests {\n    #[test]\n    fn it_works() {}\n}\n";
+        let budget = DetectionBudget {
+            max_bytes: Some(16),
+            max_time: None,
+        };
+        let (scopes, partial) = scan_within(&NormalizedSource::new(source), &budget);
+        assert!(partial);
+        let paths: Vec<_> = scopes.iter().skip(1).map(Scope::path).collect();
+        assert_eq!(paths, vec!["main", "tests", "tests::it_works"]);
+
+        let default = DetectionBudget::default();
+        let (scopes, partial) = scan_within(&NormalizedSource::new(source), &default);
+        assert!(!partial);
+        assert_eq!(scopes.len(), 6);
+
+        // A minified line degrades to the outer items whatever the budget.
+        let minified = format!(
+            "{}\nconst DATA: &str = \"{}\";\n",
+            source,
+            "x".repeat(20_000)
+        );
+        let (scopes, partial) = scan_within(&NormalizedSource::new(&minified), &default);
+        assert!(partial);
+        assert_eq!(scopes.len(), 4);
+    }
+
+    #[test]
+    fn test_inclusions() {
+        let source = r#"#[cfg(test)]
+#[path = "shared/tests.rs"]
+mod tests;
+
+mod generated {
+    include!("generated.rs");
+}
+
+mod plain;
+"#;
+        assert_eq!(
+            inclusions(source),
+            vec![
+                Inclusion {
+                    file: "shared/tests.rs".to_string(),
+                    module_path: vec!["tests".to_string()],
+                },
+                Inclusion {
+                    file: "generated.rs".to_string(),
+                    module_path: vec!["generated".to_string()],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    env,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Renders `command` as a standalone POSIX shell script: `cd` into `working_dir`, the
+/// env the command sets, then the command itself. With `clean_env` the command runs
+/// under `env -i` with only its own variables, like a clean or explicit env policy.
+/// Without a `PATH` of its own the program is then resolved to its absolute path.
+pub fn shell_script(command: &Command, working_dir: &Path, clean_env: bool) -> String {
+    let mut script = String::from("#!/bin/sh\nset -e\n\n");
+    script.push_str(&steps(command, working_dir, clean_env));
+    script
+}
+
+/// Like [shell_script] for commands run one after the other, each in a subshell with
+/// its own working directory, `working_dir` for those without one. The script stops
+/// at the first failing command.
+pub fn shell_script_all<'a>(
+    commands: impl IntoIterator<Item = &'a Command>,
+    working_dir: &Path,
+    clean_env: bool,
+) -> String {
+    let mut script = String::from("#!/bin/sh\nset -e\n");
+    for command in commands {
+        let dir = command.get_current_dir().unwrap_or(working_dir);
+        script.push_str("\n(\n");
+        for line in steps(command, dir, clean_env).lines() {
+            script.push_str(&format!("    {}\n", line));
+        }
+        script.push_str(")\n");
+    }
+    script
+}
+
+/// The lines of a script running `command`: `cd`, the env, then `exec`.
+fn steps(command: &Command, working_dir: &Path, clean_env: bool) -> String {
+    let mut script = format!("cd {}\n", quote(&working_dir.to_string_lossy()));
+
+    let mut envs: Vec<(String, Option<String>)> = command
+        .get_envs()
+        .map(|(key, value)| {
+            (
+                key.to_string_lossy().to_string(),
+                value.map(|value| value.to_string_lossy().to_string()),
+            )
+        })
+        .collect();
+    envs.sort();
+
+    let mut line: Vec<String> = Vec::new();
+    if clean_env {
+        line.push("env".to_string());
+        line.push("-i".to_string());
+        for (key, value) in &envs {
+            if let Some(value) = value {
+                line.push(format!("{}={}", key, quote(value)));
+            }
+        }
+    } else {
+        for (key, value) in &envs {
+            match value {
+                Some(value) => script.push_str(&format!("export {}={}\n", key, quote(value))),
+                None => script.push_str(&format!("unset {}\n", key)),
+            }
+        }
+    }
+
+    let program = match clean_env && !envs.iter().any(|(key, _)| key == "PATH") {
+        // `env -i` leaves no `PATH` to find the program with.
+        true => on_path(command.get_program()).unwrap_or_else(|| command.get_program().into()),
+        false => command.get_program().into(),
+    };
+    line.push(quote(&program.to_string_lossy()));
+    line.extend(command.get_args().map(|arg| quote(&arg.to_string_lossy())));
+    script.push_str(&format!("exec {}\n", line.join(" ")));
+    script
+}
+
+/// The absolute path of `program` on the `PATH` of this process, `None` for a path.
+fn on_path(program: &OsStr) -> Option<PathBuf> {
+    if Path::new(program).components().count() != 1 {
+        return None;
+    }
+    env::var_os("PATH")
+        .iter()
+        .flat_map(env::split_paths)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file() && path.is_absolute())
+}
+
+/// `command` as it would be typed in a shell, e.g. for a dry run.
+pub fn command_line(command: &Command) -> String {
+    join_quoted(
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().to_string()),
+    )
+}
+
+/// `args` quoted for the shell and joined with spaces.
+pub(crate) fn join_quoted(args: impl Iterator<Item = String>) -> String {
+    args.map(|arg| quote(&arg)).collect::<Vec<_>>().join(" ")
+}
+
+/// Single quotes `value` unless it only has characters the shell leaves alone.
+fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=+,@%".contains(c));
+    match plain {
+        true => value.to_string(),
+        false => format!("'{}'", value.replace('\'', "'\\''")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_script() {
+        let mut command = Command::new("cargo");
+        command
+            .args(["test", "-p", "demo", "--", "it's", "--exact"])
+            .env("RUST_LOG", "debug,hyper=info")
+            .env_remove("CARGO_INCREMENTAL");
+
+        assert_eq!(
+            shell_script(&command, Path::new("/work/my repo"), false),
+            "#!/bin/sh\nset -e\n\ncd '/work/my repo'\nunset CARGO_INCREMENTAL\nexport RUST_LOG=debug,hyper=info\nexec cargo test -p demo -- 'it'\\''s' --exact\n"
+        );
+        let cargo = on_path(OsStr::new("cargo")).unwrap_or_else(|| "cargo".into());
+        assert!(on_path(OsStr::new("sh")).is_some_and(|sh| sh.is_absolute()));
+        assert!(
+            shell_script(&command, Path::new("/work"), true).ends_with(&format!(
+                "exec env -i RUST_LOG=debug,hyper=info {} test -p demo -- 'it'\\''s' --exact\n",
+                cargo.display()
+            ))
+        );
+        command.env("PATH", "/opt/bin");
+        assert!(shell_script(&command, Path::new("/work"), true)
+            .ends_with("exec env -i PATH=/opt/bin RUST_LOG=debug,hyper=info cargo test -p demo -- 'it'\\''s' --exact\n"));
+
+        let mut doc_tests = Command::new("cargo");
+        doc_tests.args(["test", "--doc"]).current_dir("/work/docs");
+        assert_eq!(
+            shell_script_all([&Command::new("true"), &doc_tests], Path::new("/work"), false),
+            "#!/bin/sh\nset -e\n\n(\n    cd /work\n    exec true\n)\n\n(\n    cd /work/docs\n    exec cargo test --doc\n)\n"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{context_finder::FoundRunnable, qualified_name, FileAnalysis};
+
+/// A runnable matching a fuzzy query, higher scores first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit<'a> {
+    pub file: &'a FileAnalysis,
+    pub found: &'a FoundRunnable,
+    /// The name the query matched, see [qualified_name].
+    pub name: String,
+    pub score: i64,
+}
+
+/// The runnables of `files` whose qualified name contains the characters of `query`
+/// in order, ignoring case, best matches first. Consecutive characters and characters
+/// starting a path segment or word score higher, shorter names win ties.
+pub fn search<'a>(files: &'a [FileAnalysis], query: &str) -> Vec<SearchHit<'a>> {
+    let mut hits: Vec<SearchHit> = files
+        .iter()
+        .flat_map(|file| file.runnables.iter().map(move |found| (file, found)))
+        .filter_map(|(file, found)| {
+            let name = qualified_name(&found.runnable)?;
+            let score = fuzzy_score(&name, query)?;
+            Some(SearchHit {
+                file,
+                found,
+                name,
+                score,
+            })
+        })
+        .collect();
+    hits.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.name.len().cmp(&b.name.len()))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    hits
+}
+
+/// Scores `query` as a subsequence of `candidate`, `None` when it isn't one.
+fn fuzzy_score(candidate: &str, query: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+
+    for wanted in query.chars().filter(|c| !c.is_whitespace()) {
+        let wanted = wanted.to_ascii_lowercase();
+        let index = (position..candidate.len())
+            .find(|&index| candidate[index].to_ascii_lowercase() == wanted)?;
+
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        }
+        let boundary = index == 0 || matches!(candidate[index - 1], ':' | '_' | '-');
+        if boundary {
+            score += 8;
+        }
+        if let Some(previous) = previous {
+            score -= (index - previous - 1).min(3) as i64;
+        }
+        previous = Some(index);
+        position = index + 1;
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{Context, Runnable, RunnableKind, Scope, ScopeKind, TargetKind};
+
+    #[test]
+    fn test_search_ranking() {
+        let found = |function: &str| FoundRunnable {
+            scope: Scope {
+                kind: ScopeKind::Function,
+                name: function.rsplit("::").next().unwrap().to_string(),
+                module_path: Vec::new(),
+                start_line: 1,
+                end_line: 1,
+                attributes: vec!["test".to_string()],
+                id: 0,
+                extended: Default::default(),
+            },
+            runnable: Runnable {
+                kind: RunnableKind::Test,
+                package: Some("demo".to_string()),
+                target: Some(TargetKind::Lib),
+                filter: Some(function.to_string()),
+                exact: true,
+                function: Some(function.to_string()),
+                ..Runnable::new(Context::Test)
+            },
+        };
+        let files = vec![FileAnalysis {
+            path: PathBuf::from("src/lib.rs"),
+            runnables: vec![
+                found("parser::tests::parses_empty_input"),
+                found("tests::parse_errors"),
+                found("tests::it_works"),
+            ],
+            test_markers: 3,
+            partial: false,
+        }];
+
+        let names: Vec<_> = search(&files, "parse err")
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect();
+        assert_eq!(names, vec!["demo::tests::parse_errors"]);
+
+        let names: Vec<_> = search(&files, "parse")
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "demo::tests::parse_errors",
+                "demo::parser::tests::parses_empty_input"
+            ]
+        );
+        assert!(search(&files, "xyz").is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use std::{borrow::Cow, fs, ops::Range, path::Path};
+
+use crate::Error;
+
+const BOM: char = '\u{feff}';
+
+/// Reads the source file at `path`, replacing invalid UTF-8 like generated fixtures
+/// often contain instead of failing. Line breaks are never replaced, so lines stay
+/// those of the file.
+pub fn read_source(path: &Path) -> Result<String, Error> {
+    let bytes = fs::read(path)?;
+    Ok(match String::from_utf8(bytes) {
+        Ok(source) => source,
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    })
+}
+
+/// Source with its UTF-8 BOM removed, every line ending, `\r\n` or a lone `\r`,
+/// turned into `\n` and invalid UTF-8 replaced by `U+FFFD`, for the scanner. Line `n`
+/// of the text is line `n` as editors count them in the file, and
+/// [NormalizedSource::file_offset] maps byte offsets back to the file on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedSource<'a> {
+    text: Cow<'a, str>,
+    bom: bool,
+    /// From which offset of the text on positions in the file are shifted by how
+    /// many bytes, after each `\r\n` and each replaced invalid sequence.
+    shifts: Vec<(usize, isize)>,
+}
+
+impl<'a> NormalizedSource<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self::from_bytes(source.as_bytes())
+    }
+
+    /// Like [NormalizedSource::new] for the bytes of a file that may not be UTF-8.
+    pub fn from_bytes(bytes: &'a [u8]) -> Self {
+        let mut utf8_bom = [0; 3];
+        BOM.encode_utf8(&mut utf8_bom);
+        let (bom, bytes) = match bytes.strip_prefix(&utf8_bom) {
+            Some(rest) => (true, rest),
+            None => (false, bytes),
+        };
+        // Most files need no change, keep them borrowed.
+        if !bytes.contains(&b'\r') {
+            if let Ok(text) = std::str::from_utf8(bytes) {
+                return NormalizedSource {
+                    text: Cow::Borrowed(text),
+                    bom,
+                    shifts: Vec::new(),
+                };
+            }
+        }
+
+        let mut text = String::with_capacity(bytes.len());
+        let mut shifts = Vec::new();
+        let mut shift = 0;
+        for chunk in bytes.utf8_chunks() {
+            let mut chars = chunk.valid().chars().peekable();
+            while let Some(c) = chars.next() {
+                if c != '\r' {
+                    text.push(c);
+                    continue;
+                }
+                text.push('\n');
+                if chars.next_if_eq(&'\n').is_some() {
+                    shift += 1;
+                    shifts.push((text.len(), shift));
+                }
+            }
+            if !chunk.invalid().is_empty() {
+                text.push(char::REPLACEMENT_CHARACTER);
+                shift += chunk.invalid().len() as isize
+                    - char::REPLACEMENT_CHARACTER.len_utf8() as isize;
+                shifts.push((text.len(), shift));
+            }
+        }
+        NormalizedSource {
+            text: Cow::Owned(text),
+            bom,
+            shifts,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The byte offset in the file of byte `offset` of the text.
+    pub fn file_offset(&self, offset: usize) -> usize {
+        let bom = if self.bom { BOM.len_utf8() } else { 0 };
+        let shift = match self.shifts.partition_point(|(at, _)| *at <= offset) {
+            0 => 0,
+            shifted => self.shifts[shifted - 1].1,
+        };
+        (offset + bom).saturating_add_signed(shift)
+    }
+
+    /// The bytes of the file holding the 1-based `line`, without its line ending.
+    /// `None` past the last line.
+    pub fn file_line_range(&self, line: usize) -> Option<Range<usize>> {
+        let start = match line {
+            0 => return None,
+            1 => 0,
+            _ => self
+                .text
+                .match_indices('\n')
+                .nth(line - 2)
+                .map(|(at, _)| at + 1)?,
+        };
+        if start > 0 && start == self.text.len() {
+            return None;
+        }
+        let end = self.text[start..]
+            .find('\n')
+            .map_or(self.text.len(), |len| start + len);
+        Some(self.file_offset(start)..self.file_offset(end))
+    }
+
+    /// The length of the longest line of the text, in bytes.
+    pub fn longest_line(&self) -> usize {
+        self.text.split('\n').map(str::len).max().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalized_source_positions() {
+        let file = "\u{feff}mod a {\r\n    fn b() {}\r}\n\r\nfn c() {}";
+        let source = NormalizedSource::new(file);
+        assert_eq!(source.text(), "mod a {\n    fn b() {}\n}\n\nfn c() {}");
+
+        let line = |n: usize| source.file_line_range(n).map(|range| &file[range]);
+        assert_eq!(line(1), Some("mod a {"));
+        assert_eq!(line(2), Some("    fn b() {}"));
+        assert_eq!(line(3), Some("}"));
+        assert_eq!(line(4), Some(""));
+        assert_eq!(line(5), Some("fn c() {}"));
+        assert_eq!(line(6), None);
+
+        let c = source.text().find("fn c").unwrap();
+        assert_eq!(&file[source.file_offset(c)..], "fn c() {}");
+
+        let plain = NormalizedSource::new("fn main() {}\n");
+        assert!(matches!(plain.text, Cow::Borrowed(_)));
+        assert_eq!(plain.file_line_range(1), Some(0..12));
+        assert_eq!(plain.file_line_range(2), None);
+
+        // Latin-1 `é` then a truncated 4-byte sequence, each replaced by one `U+FFFD`.
+        let file: &[u8] = b"// caf\xe9\r\nconst A: &[u8] = b\"\xf0\x9f\x98\";\nfn d() {}\n";
+        let source = NormalizedSource::from_bytes(file);
+        assert_eq!(
+            source.text(),
+            "// caf\u{fffd}\nconst A: &[u8] = b\"\u{fffd}\";\nfn d() {}\n"
+        );
+        let d = source.text().find("fn d").unwrap();
+        assert_eq!(&file[source.file_offset(d)..], b"fn d() {}\n");
+        assert_eq!(
+            source.file_line_range(3),
+            Some(file.len() - 10..file.len() - 1)
+        );
+    }
+}
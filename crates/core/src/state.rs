@@ -0,0 +1,146 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::Error;
+
+/// Version of the state directory layout this build reads and writes.
+pub const STATE_VERSION: u32 = 1;
+
+/// File at the root of the state directory recording its layout version.
+const VERSION_FILE: &str = "state.toml";
+
+/// A step upgrading the state directory from `version - 1` to `version`.
+struct StateMigration {
+    version: u32,
+    description: &'static str,
+    apply: fn(&Path) -> Result<(), Error>,
+}
+
+/// Every migration, in version order. A store changing its on-disk format bumps
+/// [STATE_VERSION] and appends the migration converting the old files.
+const MIGRATIONS: &[StateMigration] = &[StateMigration {
+    version: 1,
+    description: "create the state directory",
+    apply: |_| Ok(()),
+}];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionFile {
+    version: u32,
+}
+
+/// The directory holding the on-disk state of cargo-runner (caches, history, ...), one
+/// subdirectory per store, so each feature doesn't hand-roll its persistence.
+///
+/// It lives in `$XDG_STATE_HOME/cargo-runner` (`~/.local/state/cargo-runner`), the local
+/// data directory on other platforms, or `CARGO_RUNNER_STATE_DIR` when set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateDir {
+    root: PathBuf,
+}
+
+impl StateDir {
+    /// The default location, see [StateDir].
+    pub fn default_path() -> Result<PathBuf, Error> {
+        if let Some(dir) = std::env::var_os("CARGO_RUNNER_STATE_DIR") {
+            return Ok(PathBuf::from(dir));
+        }
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("cargo-runner"))
+            .ok_or_else(|| Error::Other(anyhow!("Could not find a state directory")))
+    }
+
+    /// Opens the state directory at `root`, creating it or migrating it to
+    /// [STATE_VERSION]. State written by a newer version is an error rather than
+    /// being overwritten.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self, Error> {
+        let state = StateDir { root: root.into() };
+        fs::create_dir_all(&state.root)?;
+
+        let version = state.version()?;
+        if version > STATE_VERSION {
+            return Err(Error::Other(anyhow!(
+                "state at {} is version {}, newer than the supported {}, run `state reset` to discard it",
+                state.root.display(),
+                version,
+                STATE_VERSION
+            )));
+        }
+        for migration in MIGRATIONS.iter().filter(|m| m.version > version) {
+            info!(
+                "migrating state to version {}: {}",
+                migration.version, migration.description
+            );
+            (migration.apply)(&state.root)?;
+            state.write_version(migration.version)?;
+        }
+
+        Ok(state)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The directory of the store `name` e.g. `cache`, created when missing.
+    pub fn store(&self, name: &str) -> Result<PathBuf, Error> {
+        let path = self.root.join(name);
+        fs::create_dir_all(&path)?;
+        Ok(path)
+    }
+
+    /// The layout version on disk, 0 for a new directory.
+    pub fn version(&self) -> Result<u32, Error> {
+        let path = self.root.join(VERSION_FILE);
+        if !path.is_file() {
+            return Ok(0);
+        }
+        let file: VersionFile = toml::from_str(&fs::read_to_string(path)?)?;
+        Ok(file.version)
+    }
+
+    /// Deletes every store and starts over at [STATE_VERSION].
+    pub fn reset(root: impl Into<PathBuf>) -> Result<Self, Error> {
+        let root = root.into();
+        if root.exists() {
+            fs::remove_dir_all(&root)?;
+        }
+        Self::open(root)
+    }
+
+    fn write_version(&self, version: u32) -> Result<(), Error> {
+        let content = toml::to_string(&VersionFile { version })?;
+        fs::write(self.root.join(VERSION_FILE), content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("state");
+
+        let state = StateDir::open(&root).unwrap();
+        assert_eq!(state.version().unwrap(), STATE_VERSION);
+        let cache = state.store("cache").unwrap();
+        fs::write(cache.join("entry"), "cached").unwrap();
+
+        fs::write(root.join(VERSION_FILE), "version = 99\n").unwrap();
+        assert!(StateDir::open(&root).is_err());
+
+        let state = StateDir::reset(&root).unwrap();
+        assert_eq!(state.version().unwrap(), STATE_VERSION);
+        assert!(!cache.join("entry").exists());
+    }
+}
@@ -0,0 +1,129 @@
+use std::{fs, path::Path};
+
+use crate::{analyze::walk_rust_files, scan, ContextFinder, Error, Runnable};
+
+/// Tagged tests of one cargo target, run together with a single command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedTests {
+    pub runnable: Runnable,
+    /// Exact filters of the tagged tests.
+    pub filters: Vec<String>,
+}
+
+/// Finds the tests under `path` (a file or directory) tagged with `tag`, either by
+/// a `test_tag = "<tag>"` attribute or by a function path matching one of `globs`.
+/// Tests are grouped by package and target. Files are walked like [crate::analyze],
+/// honoring `.gitignore`.
+pub fn find_tagged(path: &Path, tag: &str, globs: &[String]) -> Result<Vec<TaggedTests>, Error> {
+    let mut files = walk_rust_files(path, &[], Ok)?;
+    files.sort();
+
+    let mut groups: Vec<TaggedTests> = Vec::new();
+    for file in files {
+        let source = fs::read_to_string(&file)?;
+        let scopes = scan(&source);
+        if !scopes.iter().any(|scope| scope.is_test()) {
+            continue;
+        }
+        // One detection per file locates it once, rather than once per test.
+        let found = ContextFinder::new(&file)
+            .source(source.as_str())
+            .scanned(scopes)
+            .runnables()?;
+
+        for found in found.into_iter().filter(|found| found.scope.is_test()) {
+            let runnable = found.runnable;
+            let Some(function) = runnable.function.clone() else {
+                continue;
+            };
+            let tagged = found.scope.tags().iter().any(|t| t == tag)
+                || globs.iter().any(|glob| glob_match(glob, &function));
+            if !tagged {
+                continue;
+            }
+
+            match groups.iter_mut().find(|group| {
+                group.runnable.package == runnable.package
+                    && group.runnable.target == runnable.target
+            }) {
+                Some(group) => group.filters.push(function),
+                None => groups.push(TaggedTests {
+                    runnable,
+                    filters: vec![function],
+                }),
+            }
+        }
+    }
+
+    Ok(groups)
+}
+
+/// Matches `text` against `pattern`, where `*` matches any sequence of characters.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let Some((first, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut remaining) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        match remaining.find(part) {
+            Some(index) => remaining = &remaining[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    remaining.len() >= last.len() && remaining.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_tagged_tests() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(
+            root.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.path().join("src")).unwrap();
+        fs::write(
+            root.path().join("src").join("lib.rs"),
+            r#"#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg_attr(any(), test_tag = "smoke")]
+    fn login() {}
+
+    #[test]
+    fn logout() {}
+
+    #[test]
+    fn api_health() {}
+}
+"#,
+        )
+        .unwrap();
+
+        // Ignored files are generated or vendored, not the crate's tests.
+        fs::write(root.path().join(".gitignore"), "/src/generated.rs\n").unwrap();
+        fs::write(
+            root.path().join("src").join("generated.rs"),
+            "#[test]\n#[cfg_attr(any(), test_tag = \"smoke\")]\nfn generated() {}\n",
+        )
+        .unwrap();
+
+        let tagged = find_tagged(root.path(), "smoke", &["tests::api_*".to_string()]).unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].runnable.package.as_deref(), Some("demo"));
+        assert_eq!(tagged[0].filters, vec!["tests::login", "tests::api_health"]);
+
+        assert!(glob_match("*::tests::*", "api::tests::health"));
+        assert!(!glob_match("api::*::b", "api::tests::c"));
+    }
+}
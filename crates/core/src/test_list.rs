@@ -0,0 +1,147 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, TargetKind};
+
+/// The tests of one cargo target as listed by the test binary itself with
+/// `-- --list --format terse`, the authority detection is checked against.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestList {
+    pub package: Option<String>,
+    pub target: Option<TargetKind>,
+    pub tests: Vec<String>,
+}
+
+/// How the tests detected in the source compare to a [TestList].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Listed tests detection missed.
+    pub missed: Vec<String>,
+    /// Detected tests whose filter matches no listed test.
+    pub stale: Vec<String>,
+}
+
+impl Reconciliation {
+    pub fn is_clean(&self) -> bool {
+        self.missed.is_empty() && self.stale.is_empty()
+    }
+}
+
+impl TestList {
+    /// Reads the `name: test` lines of libtest's terse list output.
+    pub fn parse(package: Option<String>, target: Option<TargetKind>, output: &str) -> Self {
+        let mut tests: Vec<String> = output
+            .lines()
+            .filter_map(|line| line.strip_suffix(": test"))
+            .map(str::to_string)
+            .collect();
+        tests.sort();
+        tests.dedup();
+        TestList {
+            package,
+            target,
+            tests,
+        }
+    }
+
+    /// Where the list of a target is cached under the cargo target directory.
+    pub fn cache_path(
+        target_dir: &Path,
+        package: Option<&str>,
+        target: Option<&TargetKind>,
+    ) -> PathBuf {
+        let target = target.map(ToString::to_string).unwrap_or_default();
+        let name = format!(
+            "{}-{}.txt",
+            package.unwrap_or("_"),
+            target.replace(':', "-")
+        );
+        target_dir
+            .join("cargo-runner")
+            .join("test-lists")
+            .join(name)
+    }
+
+    /// The cached list of a target, `None` if it was never listed.
+    pub fn load(
+        target_dir: &Path,
+        package: Option<&str>,
+        target: Option<&TargetKind>,
+    ) -> Result<Option<Self>, Error> {
+        let path = Self::cache_path(target_dir, package, target);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(TestList {
+            package: package.map(str::to_string),
+            target: target.cloned(),
+            tests: content.lines().map(str::to_string).collect(),
+        }))
+    }
+
+    pub fn save(&self, target_dir: &Path) -> Result<PathBuf, Error> {
+        let path = Self::cache_path(target_dir, self.package.as_deref(), self.target.as_ref());
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, self.tests.join("\n"))?;
+        Ok(path)
+    }
+
+    /// Whether `filter` selects at least one listed test, the way libtest matches it.
+    pub fn matches(&self, filter: &str, exact: bool) -> bool {
+        self.tests.iter().any(|test| {
+            if exact {
+                test == filter
+            } else {
+                test.contains(filter)
+            }
+        })
+    }
+
+    /// Compares the full paths of the detected test functions with the list.
+    pub fn reconcile(&self, detected: &[String]) -> Reconciliation {
+        Reconciliation {
+            missed: self
+                .tests
+                .iter()
+                .filter(|test| !detected.contains(test))
+                .cloned()
+                .collect(),
+            stale: detected
+                .iter()
+                .filter(|test| !self.matches(test, true))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconcile_with_list() {
+        let output = "tests::it_works: test\ntests::generated_case: test\nbench_parse: bench\n";
+        let list = TestList::parse(Some("demo".to_string()), Some(TargetKind::Lib), output);
+        assert_eq!(list.tests, vec!["tests::generated_case", "tests::it_works"]);
+
+        let reconciliation =
+            list.reconcile(&["tests::it_works".to_string(), "tests::removed".to_string()]);
+        assert_eq!(reconciliation.missed, vec!["tests::generated_case"]);
+        assert_eq!(reconciliation.stale, vec!["tests::removed"]);
+        assert!(list.matches("tests", false));
+
+        let target_dir = tempfile::tempdir().unwrap();
+        list.save(target_dir.path()).unwrap();
+        let cached = TestList::load(target_dir.path(), Some("demo"), Some(&TargetKind::Lib))
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached, list);
+    }
+}
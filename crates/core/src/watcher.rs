@@ -0,0 +1,111 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use crate::{Error, Watch};
+
+/// Debounce used when the config sets none.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watches a workspace for changes to its sources, ignoring build output and
+/// the `ignore` patterns of [Watch].
+pub struct Watcher {
+    root: PathBuf,
+    ignore: Gitignore,
+    debounce: Duration,
+    events: Receiver<notify::Result<notify::Event>>,
+    // Dropping the watcher stops the events.
+    _watcher: RecommendedWatcher,
+}
+
+impl Watcher {
+    pub fn new(root: &Path, watch: Option<&Watch>) -> Result<Self, Error> {
+        let mut builder = GitignoreBuilder::new(root);
+        let patterns = ["target/", ".git/"].into_iter().chain(
+            watch
+                .iter()
+                .flat_map(|w| w.ignore.iter().map(String::as_str)),
+        );
+        for pattern in patterns {
+            builder
+                .add_line(None, pattern)
+                .map_err(|e| anyhow!("invalid watch ignore pattern `{}`: {}", pattern, e))?;
+        }
+        let ignore = builder.build().map_err(|e| anyhow!(e))?;
+
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(|e| anyhow!(e))?;
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|e| anyhow!(e))?;
+
+        Ok(Watcher {
+            root: root.to_path_buf(),
+            ignore,
+            debounce: watch
+                .and_then(|w| w.debounce_ms)
+                .map_or(DEFAULT_DEBOUNCE, Duration::from_millis),
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// Blocks until files that are not ignored changed and the workspace has then
+    /// been quiet for the debounce, returning the changed files.
+    pub fn next_change(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut changed = Vec::new();
+        loop {
+            let event = match changed.is_empty() {
+                true => self.events.recv().map_err(|e| anyhow!(e))?,
+                false => match self.events.recv_timeout(self.debounce) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => return Ok(changed),
+                    Err(e) => return Err(anyhow!(e).into()),
+                },
+            };
+            let event = event.map_err(|e| anyhow!(e))?;
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            for path in event.paths {
+                if self.is_relevant(&path) && !changed.contains(&path) {
+                    changed.push(path);
+                }
+            }
+        }
+    }
+
+    fn is_relevant(&self, path: &Path) -> bool {
+        path.starts_with(&self.root)
+            && !self
+                .ignore
+                .matched_path_or_any_parents(path, path.is_dir())
+                .is_ignore()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignored_changes() {
+        let root = tempfile::tempdir().unwrap();
+        let watch = Watch {
+            ignore: vec!["*.md".to_string()],
+            debounce_ms: None,
+        };
+        let watcher = Watcher::new(root.path(), Some(&watch)).unwrap();
+
+        assert!(watcher.is_relevant(&root.path().join("src").join("lib.rs")));
+        assert!(!watcher.is_relevant(&root.path().join("README.md")));
+        assert!(!watcher.is_relevant(&root.path().join("target").join("debug").join("build.rs")));
+        assert!(!watcher.is_relevant(Path::new("/elsewhere/lib.rs")));
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{tags::glob_match, TargetKind};
+
+/// Returns the directory of the nearest `Cargo.toml` that declares a `[package]`,
+/// walking up from `path`.
+pub fn find_package_root(path: &Path) -> Option<PathBuf> {
+    find_manifest_dir(path, |manifest| manifest.contains_key("package"))
+}
+
+/// Returns the root of the workspace `path` belongs to, like cargo resolves it: the
+/// `package.workspace` of the package if set, else the nearest `[workspace]` above the
+/// package that lists it in `members` without `exclude`-ing it. A package no workspace
+/// claims, e.g. a vendored or nested one, is its own root.
+pub fn find_workspace_root(path: &Path) -> Option<PathBuf> {
+    let Some(package_root) = find_package_root(path) else {
+        return find_manifest_dir(path, |manifest| manifest.contains_key("workspace"));
+    };
+
+    let manifest = read_manifest(&package_root)?;
+    if manifest.contains_key("workspace") {
+        return Some(package_root);
+    }
+    if let Some(workspace) = manifest
+        .get("package")
+        .and_then(|package| package.get("workspace"))
+        .and_then(toml::Value::as_str)
+    {
+        return Some(normalize(&package_root.join(workspace)));
+    }
+
+    let parent = package_root.parent()?;
+    let claimed =
+        find_manifest_dir(parent, |manifest| manifest.contains_key("workspace")).filter(|root| {
+            let relative = package_root
+                .strip_prefix(root)
+                .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            read_manifest(root).is_some_and(|manifest| is_member(&manifest, &relative))
+        });
+    claimed.or(Some(package_root))
+}
+
+/// Whether the `Cargo.toml` at `root` is a virtual manifest: a `[workspace]` without `[package]`.
+pub fn is_virtual_manifest(root: &Path) -> bool {
+    read_manifest(root).is_some_and(|manifest| {
+        manifest.contains_key("workspace") && !manifest.contains_key("package")
+    })
+}
+
+/// The package directories of the `[workspace]` at `root`: its `members` globs
+/// expanded against the filesystem, without the `exclude`d ones.
+pub fn workspace_members(root: &Path) -> Vec<PathBuf> {
+    let Some(manifest) = read_manifest(root) else {
+        return Vec::new();
+    };
+    let patterns = manifest
+        .get("workspace")
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(toml::Value::as_str);
+
+    let mut members = Vec::new();
+    for pattern in patterns {
+        let components: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+        for dir in expand(root, &components) {
+            let relative = dir
+                .strip_prefix(root)
+                .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            if dir.join("Cargo.toml").is_file()
+                && is_member(&manifest, &relative)
+                && !members.contains(&dir)
+            {
+                members.push(dir);
+            }
+        }
+    }
+    members.sort();
+    members
+}
+
+/// The directories under `dir` matching the `/` separated glob `components`.
+fn expand(dir: &Path, components: &[&str]) -> Vec<PathBuf> {
+    let Some((first, rest)) = components.split_first() else {
+        return vec![dir.to_path_buf()];
+    };
+    if !first.contains('*') {
+        return expand(&dir.join(first), rest);
+    }
+
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .is_some_and(|name| glob_match(first, &name.to_string_lossy()))
+        })
+        .collect();
+    entries.sort();
+    entries
+        .iter()
+        .flat_map(|entry| expand(entry, rest))
+        .collect()
+}
+
+/// The package and target of the member of the workspace at `root` that declares
+/// `path` as a target outside its own directory e.g. `[[test]] path = "../../tests/smoke.rs"`.
+pub fn find_claiming_member(root: &Path, path: &Path) -> Option<(String, TargetKind)> {
+    workspace_members(root).into_iter().find_map(|member| {
+        let target = declared_target(&member, path)?;
+        Some((find_package_name(&member)?, target))
+    })
+}
+
+/// The target the manifest of the package at `package_root` declares with `path`
+/// as its root file e.g. `[[bin]] path = "firmware/entry.rs"`.
+pub(crate) fn declared_target(package_root: &Path, path: &Path) -> Option<TargetKind> {
+    let path = path.canonicalize().ok()?;
+    let manifest = read_manifest(package_root)?;
+    let claims = |target: &toml::Value| {
+        target
+            .get("path")
+            .and_then(toml::Value::as_str)
+            .and_then(|target_path| package_root.join(target_path).canonicalize().ok())
+            .is_some_and(|target_path| target_path == path)
+    };
+
+    if manifest.get("lib").is_some_and(claims) {
+        return Some(TargetKind::Lib);
+    }
+    for (section, kind) in [
+        ("bin", TargetKind::Bin as fn(String) -> TargetKind),
+        ("example", TargetKind::Example),
+        ("test", TargetKind::Test),
+        ("bench", TargetKind::Bench),
+    ] {
+        let targets = manifest.get(section).and_then(toml::Value::as_array);
+        for target in targets.into_iter().flatten() {
+            let name = target.get("name").and_then(toml::Value::as_str);
+            if let (Some(name), true) = (name, claims(target)) {
+                return Some(kind(name.to_string()));
+            }
+        }
+    }
+    None
+}
+
+/// Whether the `[workspace]` of `manifest` includes the package at `relative`.
+fn is_member(manifest: &toml::Table, relative: &str) -> bool {
+    let paths = |key: &str| -> Vec<String> {
+        manifest
+            .get("workspace")
+            .and_then(|workspace| workspace.get(key))
+            .and_then(toml::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(toml::Value::as_str)
+            .map(|path| path.trim_end_matches('/').to_string())
+            .collect()
+    };
+
+    let excluded = paths("exclude")
+        .iter()
+        .any(|excluded| relative == excluded || relative.starts_with(&format!("{}/", excluded)));
+    !excluded
+        && paths("members")
+            .iter()
+            .any(|member| glob_match(member, relative))
+}
+
+/// Resolves `.` and `..` without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn read_manifest(dir: &Path) -> Option<toml::Table> {
+    let content = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    toml::from_str(&content).ok()
+}
+
+fn find_manifest_dir(path: &Path, predicate: impl Fn(&toml::Table) -> bool) -> Option<PathBuf> {
+    let start = if path.is_file() { path.parent()? } else { path };
+
+    start.ancestors().find_map(|dir| {
+        let manifest = dir.join("Cargo.toml");
+        let content = fs::read_to_string(&manifest).ok()?;
+        let table: toml::Table = toml::from_str(&content).ok()?;
+        predicate(&table).then(|| dir.to_path_buf())
+    })
+}
+
+/// Returns the `[package].name` declared in the `Cargo.toml` at `package_root`.
+pub fn find_package_name(package_root: &Path) -> Option<String> {
+    let content = fs::read_to_string(package_root.join("Cargo.toml")).ok()?;
+    let table: toml::Table = toml::from_str(&content).ok()?;
+    table
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_boundaries() {
+        let root = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/vendored\"]\n",
+        );
+        write("crates/core/Cargo.toml", "[package]\nname = \"core\"\n");
+        write(
+            "crates/vendored/Cargo.toml",
+            "[package]\nname = \"vendored\"\n",
+        );
+        write(
+            "tools/nested/Cargo.toml",
+            "[package]\nname = \"nested\"\n\n[workspace]\n",
+        );
+        write(
+            "tools/nested/member/Cargo.toml",
+            "[package]\nname = \"member\"\nworkspace = \"..\"\n",
+        );
+
+        let root_of = |path: &str| find_workspace_root(&root.path().join(path));
+        assert_eq!(
+            root_of("crates/core/src/lib.rs"),
+            Some(root.path().to_path_buf())
+        );
+        assert_eq!(
+            root_of("crates/vendored/src/lib.rs"),
+            Some(root.path().join("crates/vendored"))
+        );
+        assert_eq!(
+            root_of("tools/nested/member/src/lib.rs"),
+            Some(root.path().join("tools/nested"))
+        );
+        assert_eq!(root_of("src/main.rs"), Some(root.path().to_path_buf()));
+
+        assert!(is_virtual_manifest(root.path()));
+        assert_eq!(
+            workspace_members(root.path()),
+            vec![root.path().join("crates/core")]
+        );
+        write("crates/core/Cargo.toml", "[package]\nname = \"core\"\n\n[[test]]\nname = \"smoke\"\npath = \"../../tests/smoke.rs\"\n");
+        write("tests/smoke.rs", "#[test]\nfn smoke() {}\n");
+        assert_eq!(
+            find_claiming_member(root.path(), &root.path().join("tests/smoke.rs")),
+            Some(("core".to_string(), TargetKind::Test("smoke".to_string())))
+        );
+    }
+}